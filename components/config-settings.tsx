@@ -502,11 +502,12 @@ export function ConfigSettings() {
                         <Input
                           id="max-concurrent-tasks"
                           type="number"
-                          min="1"
+                          min="0"
                           max="10"
                           value={config.app.max_concurrent_tasks}
                           onChange={(e) => handleConfigChange('app', 'max_concurrent_tasks', parseInt(e.target.value))}
                         />
+                        <p className="text-sm text-muted-foreground">每次样本运行中同时执行的步骤数，0 表示不限制</p>
                       </div>
                       <div className="space-y-2">
                         <Label htmlFor="timeout-seconds">超时时间（秒）</Label>
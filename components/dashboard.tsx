@@ -83,8 +83,11 @@ export default function DashBoard() {
       setConfig(configData);
       
       // 检查必要的配置是否为空
-      const hasXunfeiConfig = configData.xunfei.appid && configData.xunfei.api_key && configData.xunfei.api_secret;
-      const hasOpenRouterConfig = configData.openrouter.api_key;
+      // 使用本地或模拟识别后端时不需要讯飞密钥
+      const usesXunfei = (configData.asr?.provider || 'xunfei') === 'xunfei';
+      const hasXunfeiConfig = !usesXunfei || (configData.xunfei.appid && configData.xunfei.api_key && configData.xunfei.api_secret);
+      // 本地部署的 OpenAI 兼容服务可以不填密钥
+      const hasOpenRouterConfig = configData.openrouter.api_key || (configData.openrouter.base_url && !configData.openrouter.base_url.includes('openrouter.ai'));
       
       if (!hasXunfeiConfig || !hasOpenRouterConfig) {
         setShowConfigAlert(true);
@@ -332,8 +335,9 @@ export default function DashBoard() {
           {/* 配置状态提示 */}
           {config && (
             (() => {
-              const hasXunfeiConfig = config.xunfei.appid && config.xunfei.api_key && config.xunfei.api_secret;
-              const hasOpenRouterConfig = config.openrouter.api_key;
+              const usesXunfei = (config.asr?.provider || 'xunfei') === 'xunfei';
+              const hasXunfeiConfig = !usesXunfei || (config.xunfei.appid && config.xunfei.api_key && config.xunfei.api_secret);
+              const hasOpenRouterConfig = config.openrouter.api_key || (config.openrouter.base_url && !config.openrouter.base_url.includes('openrouter.ai'));
               const isConfigComplete = hasXunfeiConfig && hasOpenRouterConfig;
               
               // 只有当配置不完整时才显示提醒卡片
@@ -1,7 +1,7 @@
 import { invoke } from '@tauri-apps/api/core';
 import type { Event as TauriEvent } from '@tauri-apps/api/event';
 import { listen } from '@tauri-apps/api/event';
-import type { AnalysisResult as TauriAnalysisResult, TaskProgress as TauriTaskProgress, PlayAudioEvent as TauriPlayAudioEvent, Task as TauriTask } from '@/types/tauri'; // Corrected import path
+import type { AnalysisResult as TauriAnalysisResult, TaskProgress as TauriTaskProgress, PlayAudioEvent as TauriPlayAudioEvent, Task as TauriTask, WakeWordStrategy } from '@/types/tauri'; // Corrected import path
 
 /**
  * Starts the automated test process for the current task via Tauri.
@@ -10,20 +10,23 @@ import type { AnalysisResult as TauriAnalysisResult, TaskProgress as TauriTaskPr
  * @param frameRate 可选的帧率，默认10
  * @param threshold 可选的阈值，默认0.5
  * @param maxDetectionTimeSecs 可选的最大检测时间，默认30秒
+ * @param wakeWordStrategy 可选的唤醒词分配策略，未提供时沿用任务上次使用的策略
  */
 export async function tauriStartAutomatedTest(
   wakeWordId?: number,
   templateData?: Array<[string, string]>,
   frameRate?: number,
   threshold?: number,
-  maxDetectionTimeSecs?: number
+  maxDetectionTimeSecs?: number,
+  wakeWordStrategy?: WakeWordStrategy
 ): Promise<void> {
   await invoke('new_meta_workflow', {
     wakeWordId,
     templateData,
     frameRate,
     threshold,
-    maxDetectionTimeSecs
+    maxDetectionTimeSecs,
+    wakeWordStrategy
   });
 }
 
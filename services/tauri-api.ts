@@ -11,12 +11,23 @@ export interface ConfigData {
   openrouter: {
     api_key: string
     base_url: string
+    model?: string
+    temperature?: number | null
+    timeout_seconds?: number
+    json_mode?: boolean
+    max_retries?: number
   }
   app: {
     log_level: string
     max_concurrent_tasks: number
     timeout_seconds: number
   }
+  asr?: {
+    provider: string
+    local_url: string
+    replay_file: string
+    mock_delay_ms: number
+  }
 }
 
 export class TauriApiService {
@@ -148,8 +159,8 @@ export class TauriApiService {
     return await invoke('submit_analysis', { sampleId, machineResponse });
   }
 
-  static async getTaskProgress(): Promise<TaskProgress> {
-    return await invoke('get_task_progress');
+  static async getTaskProgress(taskId?: number): Promise<TaskProgress> {
+    return await invoke('get_task_progress', { taskId });
   }
 
   static async getAnalysisResults(): Promise<Record<number, AnalysisResult>> {
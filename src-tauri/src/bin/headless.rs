@@ -0,0 +1,4 @@
+// 无界面运行评估任务，用法见 `headless --help`
+fn main() {
+    std::process::exit(app_lib::headless::main());
+}
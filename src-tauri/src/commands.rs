@@ -1,14 +1,22 @@
+use crate::db::database::DatabaseService;
 use crate::models::*;
 use crate::services::active_task::VisualWakeConfig;
+use crate::services::analysis_task::AnalysisSettings;
+use crate::services::llm_provider::LlmOverrides;
 use crate::services::meta_task_executor::MetaTaskExecutor;
+use crate::services::pipeline::{self, PipelineDefinition, DEFAULT_PIPELINE};
+use crate::services::run_plan::{plan_sample_runs, SampleRun, WakeWordStrategy};
+use crate::services::scheduler;
 use crate::services::wake_detection_meta_executor::wake_detection_meta_executor;
-use crate::services::workflow::Workflow;
+use crate::services::workflow::{ControlHandle, Workflow};
 use crate::services::visual_wake_detection::get_or_create_detector;
 use crate::state::AppState;
 use chrono::Utc;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tauri::ipc::Channel;
 use tauri::{Emitter, Manager, State};
+use tokio::sync::MutexGuard;
 use tokio::time::{timeout, Duration};
 
 #[tauri::command]
@@ -132,37 +140,63 @@ pub async fn create_task(
         .map_err(|e| format!("创建任务失败: {}", e))
 }
 
-// #[tauri::command]
-// pub async fn get_task_progress(state: State<'_, Arc<AppState>>) -> Result<TaskProgress, String> {
-//     let current_task_id = state.current_task_id.read().await;
-//     if let Some(task_id) = *current_task_id {
-//         let task = state
-//             .db
-//             .get_task_by_id(task_id)
-//             .await
-//             .map_err(|e| format!("获取任务失败: {}", e))?
-//             .ok_or("任务不存在")?;
-
-//         let completed_count = state
-//             .db
-//             .get_analysis_results_by_task(task_id)
-//             .await
-//             .map_err(|e| format!("获取分析结果失败: {}", e))?
-//             .len() as u32;
-
-//         Ok(TaskProgress {
-//             value: task.task_progress.unwrap_or(0.0),
-//             current: completed_count,
-//             total: task.test_samples_ids.len() as u32,
-//         })
-//     } else {
-//         Ok(TaskProgress {
-//             value: 0.0,
-//             current: 0,
-//             total: 0,
-//         })
-//     }
-// }
+/// 获取样本测试进度，`task_id` 为空时取当前任务。
+/// 任务本次运行期间上报过进度时直接返回（含当前环节和剩余时间估算），否则按运行状态和分析结果汇总
+#[tauri::command]
+pub async fn get_task_progress(
+    state: State<'_, Arc<AppState>>,
+    task_id: Option<u32>,
+) -> Result<TaskProgress, String> {
+    let task_id = match task_id {
+        Some(task_id) => task_id as i64,
+        None => match *state.current_task_id.read().await {
+            Some(task_id) => task_id,
+            None => return Ok(TaskProgress::default()),
+        },
+    };
+    if let Some(progress) = state.task_progress.read().await.as_ref() {
+        if progress.task_id == task_id {
+            return Ok(progress.clone());
+        }
+    }
+
+    let task = state
+        .db
+        .get_task_by_id(task_id)
+        .await
+        .map_err(|e| format!("获取任务失败: {}", e))?
+        .ok_or("任务不存在")?;
+    let run_states = state
+        .db
+        .list_sample_run_states(task_id)
+        .await
+        .map_err(|e| format!("获取运行状态失败: {}", e))?;
+    let results = state
+        .db
+        .list_analysis_results_by_task(task_id)
+        .await
+        .map_err(|e| format!("获取分析结果失败: {}", e))?;
+
+    let done: HashSet<SampleRunKey> = run_states
+        .iter()
+        .filter(|run| run.status == SampleRunStatus::Done)
+        .map(|run| run.key())
+        .collect();
+    let passed = results
+        .iter()
+        .filter(|(key, result)| done.contains(key) && result.assessment.valid)
+        .count() as u32;
+    Ok(TaskProgress {
+        task_id,
+        value: task.task_progress.unwrap_or(0.0) * 100.0,
+        current_sample: done.len() as u32,
+        current_stage: None,
+        total: run_states.len() as u32,
+        passed,
+        failed: done.len() as u32 - passed,
+        ..Default::default()
+    })
+}
 
 #[tauri::command]
 pub async fn get_analysis_results(
@@ -245,6 +279,20 @@ pub async fn create_sample(
         .map_err(|e| format!("创建样本失败: {}", e))
 }
 
+/// 设置样本在测试中的重复执行次数
+#[tauri::command]
+pub async fn update_sample_repeats(
+    state: State<'_, Arc<AppState>>,
+    sample_id: u32,
+    repeats: u32,
+) -> Result<(), String> {
+    state
+        .db
+        .update_sample_repeats(sample_id as i64, repeats)
+        .await
+        .map_err(|e| format!("更新样本重复次数失败: {}", e))
+}
+
 #[tauri::command]
 pub async fn create_wake_word(
     state: State<'_, Arc<AppState>>,
@@ -317,6 +365,271 @@ pub async fn update_task_status(
         .map_err(|e| format!("更新任务状态失败: {}", e))
 }
 
+#[tauri::command]
+pub async fn get_task_llm_settings(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+) -> Result<LlmOverrides, String> {
+    state
+        .db
+        .get_task_llm_settings(task_id as i64)
+        .await
+        .map_err(|e| format!("获取任务大模型配置失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_task_llm_settings(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    settings: LlmOverrides,
+) -> Result<(), String> {
+    state
+        .db
+        .update_task_llm_settings(task_id as i64, &settings)
+        .await
+        .map_err(|e| format!("更新任务大模型配置失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_task_wake_word_strategy(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+) -> Result<WakeWordStrategy, String> {
+    state
+        .db
+        .get_task_wake_word_strategy(task_id as i64)
+        .await
+        .map_err(|e| format!("获取唤醒词策略失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn update_task_wake_word_strategy(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    strategy: WakeWordStrategy,
+) -> Result<(), String> {
+    if let WakeWordStrategy::Fixed { wake_word_id: Some(wid) } = &strategy {
+        let task = state
+            .db
+            .get_task_by_id(task_id as i64)
+            .await
+            .map_err(|e| format!("获取任务失败: {}", e))?
+            .ok_or("任务不存在")?;
+        if !task.wake_word_ids.contains(wid) {
+            return Err("指定的唤醒词不属于当前任务".to_string());
+        }
+    }
+    state
+        .db
+        .update_task_wake_word_strategy(task_id as i64, &strategy)
+        .await
+        .map_err(|e| format!("更新唤醒词策略失败: {}", e))
+}
+
+/// 列出可用的样本流水线（内置 + 数据目录中的自定义流水线）
+#[tauri::command]
+pub async fn list_pipelines() -> Result<Vec<PipelineDefinition>, String> {
+    pipeline::list_pipelines().map_err(|e| format!("加载流水线失败: {}", e))
+}
+
+/// 获取任务选择的流水线名称，未选择时返回默认流水线
+#[tauri::command]
+pub async fn get_task_pipeline(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+) -> Result<String, String> {
+    let name = state
+        .db
+        .get_task_pipeline(task_id as i64)
+        .await
+        .map_err(|e| format!("获取任务流水线失败: {}", e))?;
+    Ok(name.unwrap_or_else(|| DEFAULT_PIPELINE.to_string()))
+}
+
+/// 设置任务使用的流水线，None 表示恢复默认流水线
+#[tauri::command]
+pub async fn update_task_pipeline(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    pipeline: Option<String>,
+) -> Result<(), String> {
+    if let Some(name) = &pipeline {
+        pipeline::load_pipeline(name).map_err(|e| format!("加载流水线失败: {}", e))?;
+    }
+    state
+        .db
+        .update_task_pipeline(task_id as i64, pipeline.as_deref())
+        .await
+        .map_err(|e| format!("更新任务流水线失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_prompt_templates(
+    state: State<'_, Arc<AppState>>,
+) -> Result<Vec<PromptTemplate>, String> {
+    state
+        .db
+        .list_prompt_templates()
+        .await
+        .map_err(|e| format!("获取提示词模板失败: {}", e))
+}
+
+/// 保存提示词模板，同名模板会生成新版本
+#[tauri::command]
+pub async fn save_prompt_template(
+    state: State<'_, Arc<AppState>>,
+    name: String,
+    content: String,
+    description: Option<String>,
+) -> Result<PromptTemplate, String> {
+    if name.trim().is_empty() {
+        return Err("模板名称不能为空".to_string());
+    }
+    if !content.contains("{{response}}") && !content.contains("{{ response }}") {
+        return Err("模板必须包含 {{response}} 占位符".to_string());
+    }
+
+    state
+        .db
+        .create_prompt_template_version(name.trim(), &content, description.as_deref())
+        .await
+        .map_err(|e| format!("保存提示词模板失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_task_prompt_template(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+) -> Result<PromptTemplate, String> {
+    state
+        .db
+        .get_task_prompt_template(task_id as i64)
+        .await
+        .map_err(|e| format!("获取任务提示词模板失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_task_prompt_template(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    template_id: Option<i64>,
+) -> Result<(), String> {
+    if let Some(id) = template_id {
+        state
+            .db
+            .get_prompt_template_by_id(id)
+            .await
+            .map_err(|e| format!("获取提示词模板失败: {}", e))?
+            .ok_or("提示词模板不存在")?;
+    }
+
+    state
+        .db
+        .set_task_prompt_template(task_id as i64, template_id)
+        .await
+        .map_err(|e| format!("设置任务提示词模板失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn list_rubrics(state: State<'_, Arc<AppState>>) -> Result<Vec<Rubric>, String> {
+    state
+        .db
+        .list_rubrics()
+        .await
+        .map_err(|e| format!("获取评分标准失败: {}", e))
+}
+
+/// 新建（id 为 0）或更新评分标准
+#[tauri::command]
+pub async fn save_rubric(state: State<'_, Arc<AppState>>, rubric: Rubric) -> Result<i64, String> {
+    if rubric.name.trim().is_empty() {
+        return Err("评分标准名称不能为空".to_string());
+    }
+    if rubric.dimensions.is_empty() {
+        return Err("评分标准至少需要一个维度".to_string());
+    }
+    if !(0.0..=1.0).contains(&rubric.pass_threshold) {
+        return Err("通过阈值必须在0到1之间".to_string());
+    }
+    let mut keys = std::collections::HashSet::new();
+    for dimension in &rubric.dimensions {
+        let valid_key = !dimension.key.is_empty()
+            && dimension
+                .key
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid_key {
+            return Err(format!("维度标识 '{}' 只能包含小写字母、数字和下划线", dimension.key));
+        }
+        // 这些字段名被 assessment 本身占用
+        if ["overall_score", "valid", "suggestions"].contains(&dimension.key.as_str()) {
+            return Err(format!("维度标识 '{}' 为保留字段", dimension.key));
+        }
+        if !keys.insert(dimension.key.as_str()) {
+            return Err(format!("维度标识 '{}' 重复", dimension.key));
+        }
+        if dimension.weight < 0.0 {
+            return Err(format!("维度 '{}' 的权重不能为负数", dimension.key));
+        }
+    }
+
+    state
+        .db
+        .save_rubric(&rubric)
+        .await
+        .map_err(|e| format!("保存评分标准失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_rubric(state: State<'_, Arc<AppState>>, rubric_id: i64) -> Result<(), String> {
+    let rubric = state
+        .db
+        .get_rubric_by_id(rubric_id)
+        .await
+        .map_err(|e| format!("获取评分标准失败: {}", e))?
+        .ok_or("评分标准不存在")?;
+    if rubric.name == "default" {
+        return Err("不能删除默认评分标准".to_string());
+    }
+
+    state
+        .db
+        .delete_rubric(rubric_id)
+        .await
+        .map_err(|e| format!("删除评分标准失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_task_rubric(state: State<'_, Arc<AppState>>, task_id: u32) -> Result<Rubric, String> {
+    state
+        .db
+        .get_task_rubric(task_id as i64)
+        .await
+        .map_err(|e| format!("获取任务评分标准失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn set_task_rubric(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    rubric_id: Option<i64>,
+) -> Result<(), String> {
+    if let Some(id) = rubric_id {
+        state
+            .db
+            .get_rubric_by_id(id)
+            .await
+            .map_err(|e| format!("获取评分标准失败: {}", e))?
+            .ok_or("评分标准不存在")?;
+    }
+
+    state
+        .db
+        .set_task_rubric(task_id as i64, rubric_id)
+        .await
+        .map_err(|e| format!("设置任务评分标准失败: {}", e))
+}
+
 #[tauri::command]
 pub async fn delete_task(state: State<'_, Arc<AppState>>, task_id: u32) -> Result<(), String> {
     // 如果删除的是当前任务，清除当前任务ID
@@ -662,74 +975,347 @@ pub async fn new_meta_workflow(
     frame_rate: Option<u32>, // 可选的帧率，默认10
     threshold: Option<f64>, // 可选的阈值，默认0.5
     max_detection_time_secs: Option<u64>, // 可选的最大检测时间，默认30秒
+    wake_word_strategy: Option<WakeWordStrategy>, // 可选的唤醒词策略，未提供时沿用任务上次保存的策略
 ) -> Result<(), String> {
     // 1. 获取任务ID
     let task_id = state.current_task_id.read().await.ok_or("没有设置当前任务ID")?;
+    let workflow_handle_guard = lock_idle_workflow_handle(&state).await?;
+
+    // 2. 按策略为每个样本分配唤醒词
+    // 兼容旧调用方式：只传 wake_word_id 时等同于固定唤醒词
+    let strategy = match (wake_word_strategy, wake_word_id) {
+        (Some(strategy), _) => strategy,
+        (None, Some(wid)) => WakeWordStrategy::Fixed { wake_word_id: Some(wid) },
+        (None, None) => state.db.get_task_wake_word_strategy(task_id)
+            .await
+            .map_err(|e| format!("获取唤醒词策略失败: {}", e))?,
+    };
+
+    let runs = prepare_task_runs(&state.db, task_id, &strategy).await?;
+
+    // 3. 创建视觉配置
+    let visual_config = VisualWakeConfig {
+        template_data: template_data.unwrap_or_else(|| vec![]), // 如果没有提供模板，使用空列表
+        frame_rate: frame_rate.unwrap_or(10),
+        threshold: threshold.unwrap_or(0.5),
+        max_detection_time_secs: Some(max_detection_time_secs.unwrap_or(5)), // 提供默认值5秒
+    };
+
+    start_meta_executor(state.inner(), workflow_handle_guard, app_handle, task_id, runs, visual_config, HashSet::new()).await
+}
+
+/// 从第一个未完成的样本运行继续执行中断（程序退出、车机重启或手动停止）的任务。
+/// 已完成的运行直接跳过，失败和未执行的运行重新执行；视觉参数与 `new_meta_workflow` 相同
+#[tauri::command]
+pub async fn resume_task(
+    state: State<'_, Arc<AppState>>,
+    app_handle: tauri::AppHandle,
+    task_id: u32,
+    template_data: Option<Vec<(String, String)>>,
+    frame_rate: Option<u32>,
+    threshold: Option<f64>,
+    max_detection_time_secs: Option<u64>,
+) -> Result<(), String> {
+    let task_id = task_id as i64;
+    let workflow_handle_guard = lock_idle_workflow_handle(&state).await?;
+    let (runs, completed_runs) = prepare_resumed_runs(&state.db, task_id).await?;
+
+    *state.current_task_id.write().await = Some(task_id);
+    let visual_config = VisualWakeConfig {
+        template_data: template_data.unwrap_or_default(),
+        frame_rate: frame_rate.unwrap_or(10),
+        threshold: threshold.unwrap_or(0.5),
+        max_detection_time_secs: Some(max_detection_time_secs.unwrap_or(5)),
+    };
+
+    start_meta_executor(state.inner(), workflow_handle_guard, app_handle, task_id, runs, visual_config, completed_runs).await
+}
+
+/// 获取任务每次样本运行的执行状态
+#[tauri::command]
+pub async fn get_sample_run_states(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+) -> Result<Vec<SampleRunState>, String> {
+    state
+        .db
+        .list_sample_run_states(task_id as i64)
+        .await
+        .map_err(|e| format!("获取运行状态失败: {}", e))
+}
 
-    // 2. 从数据库一次性获取所有需要的数据
-    let task_samples = state.db.get_samples_by_task_id(task_id)
+/// 获取任务的工作流执行记录（每次样本运行中各任务节点的状态、耗时、重试次数和输出摘要），
+/// 可按样本筛选
+#[tauri::command]
+pub async fn get_workflow_traces(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    sample_id: Option<u32>,
+) -> Result<Vec<WorkflowRunRecord>, String> {
+    state
+        .db
+        .list_workflow_runs(task_id as i64, sample_id.map(i64::from))
+        .await
+        .map_err(|e| format!("获取执行记录失败: {}", e))
+}
+
+/// 将任务的全部工作流执行记录导出为 JSON 文件，返回写入的文件路径
+#[tauri::command]
+pub async fn export_workflow_traces(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    file_path: String,
+) -> Result<String, String> {
+    let mut path = std::path::PathBuf::from(&file_path);
+    if path.extension().map_or(true, |ext| ext != "json") {
+        path.set_extension("json");
+    }
+    let runs = state
+        .db
+        .list_workflow_runs(task_id as i64, None)
+        .await
+        .map_err(|e| format!("获取执行记录失败: {}", e))?;
+    let json = serde_json::to_string_pretty(&runs).map_err(|e| format!("导出执行记录失败: {}", e))?;
+    std::fs::write(&path, json).map_err(|e| format!("导出执行记录失败: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// 已有工作流（手动启动或来自测试队列）在运行时拒绝启动新的，避免覆盖正在运行的工作流的控制句柄。
+/// 返回的锁要一直持有到新的控制句柄存入为止，期间测试队列无法取出任务
+async fn lock_idle_workflow_handle(
+    state: &AppState,
+) -> Result<MutexGuard<'_, Option<ControlHandle>>, String> {
+    let workflow_handle_guard = state.workflow_handle.lock().await;
+    if workflow_handle_guard.as_ref().is_some_and(|handle| handle.is_running()) {
+        return Err("已有测试正在运行，请等待结束后再启动，或将任务加入测试队列".to_string());
+    }
+    Ok(workflow_handle_guard)
+}
+
+/// 创建样本测试的元任务并在后台运行，控制句柄存入 `workflow_handle_guard`
+async fn start_meta_executor(
+    state: &Arc<AppState>,
+    mut workflow_handle_guard: MutexGuard<'_, Option<ControlHandle>>,
+    app_handle: tauri::AppHandle,
+    task_id: i64,
+    runs: Vec<SampleRun>,
+    visual_config: VisualWakeConfig,
+    completed_runs: HashSet<SampleRunKey>,
+) -> Result<(), String> {
+    // 4. 创建主工作流
+    let (mut main_workflow, _) = Workflow::new();
+
+    // 5. 创建元任务，传入视觉配置
+    let multi_sample_executor =
+        build_meta_executor(state, task_id, runs, visual_config, completed_runs).await?;
+
+    // 6. 将元任务作为唯一任务添加到主工作流
+    main_workflow.add_task(multi_sample_executor);
+
+    // 7. 运行主工作流，获取总控制句柄
+    let handle = main_workflow.run(Arc::new(app_handle)).await;
+
+    // 8. 将总控制句柄存入全局状态
+    *workflow_handle_guard = Some(handle);
+
+    Ok(())
+}
+
+/// 重新开始任务：按唤醒词策略生成运行计划，保存策略并清除上次的运行状态
+pub(crate) async fn prepare_task_runs(
+    db: &DatabaseService,
+    task_id: i64,
+    strategy: &WakeWordStrategy,
+) -> Result<Vec<SampleRun>, String> {
+    let runs = plan_task_runs(db, task_id, strategy).await?;
+    db.update_task_wake_word_strategy(task_id, strategy)
+        .await
+        .map_err(|e| format!("保存唤醒词策略失败: {}", e))?;
+    log::info!(
+        "[NEW_META_WORKFLOW] Task {} planned {} runs with wake word strategy {:?}",
+        task_id,
+        runs.len(),
+        strategy
+    );
+
+    // 重新开始时清除上次的运行状态
+    let run_keys: Vec<_> = runs.iter().map(|run| run.key(task_id)).collect();
+    db.reset_sample_run_states(task_id, &run_keys)
+        .await
+        .map_err(|e| format!("保存运行计划失败: {}", e))?;
+    Ok(runs)
+}
+
+/// 恢复任务：沿用保存的唤醒词策略重新生成运行计划，返回计划和其中已完成的运行
+pub(crate) async fn prepare_resumed_runs(
+    db: &DatabaseService,
+    task_id: i64,
+) -> Result<(Vec<SampleRun>, HashSet<SampleRunKey>), String> {
+    let states = db.list_sample_run_states(task_id)
+        .await
+        .map_err(|e| format!("获取运行状态失败: {}", e))?;
+    if states.is_empty() {
+        return Err("任务没有可恢复的运行记录，请重新开始测试".to_string());
+    }
+    remaining_runs(db, task_id, &states)
+        .await?
+        .ok_or_else(|| "任务的所有样本运行均已完成".to_string())
+}
+
+/// 按已保存的运行状态生成继续执行的计划，所有运行均已完成时返回 None
+pub(crate) async fn remaining_runs(
+    db: &DatabaseService,
+    task_id: i64,
+    states: &[SampleRunState],
+) -> Result<Option<(Vec<SampleRun>, HashSet<SampleRunKey>)>, String> {
+    // 沿用任务保存的唤醒词策略，相同的样本和策略得到相同的运行计划
+    let strategy = db.get_task_wake_word_strategy(task_id)
+        .await
+        .map_err(|e| format!("获取唤醒词策略失败: {}", e))?;
+    let runs = plan_task_runs(db, task_id, &strategy).await?;
+
+    let completed_runs: HashSet<SampleRunKey> = states
+        .iter()
+        .filter(|s| s.status == SampleRunStatus::Done)
+        .map(|s| s.key())
+        .collect();
+    let run_keys: Vec<_> = runs.iter().map(|run| run.key(task_id)).collect();
+    let remaining = run_keys.iter().filter(|key| !completed_runs.contains(key)).count();
+    if remaining == 0 {
+        return Ok(None);
+    }
+    db.add_missing_sample_run_states(&run_keys)
+        .await
+        .map_err(|e| format!("保存运行计划失败: {}", e))?;
+    log::info!(
+        "[RESUME_TASK] Task {} resuming with {} of {} runs remaining",
+        task_id,
+        remaining,
+        runs.len()
+    );
+    Ok(Some((runs, completed_runs)))
+}
+
+/// 创建执行样本运行计划的元任务，流水线定义有误时在开始测试前就报错，而不是等到第一个样本
+pub(crate) async fn build_meta_executor(
+    state: &Arc<AppState>,
+    task_id: i64,
+    runs: Vec<SampleRun>,
+    visual_config: VisualWakeConfig,
+    completed_runs: HashSet<SampleRunKey>,
+) -> Result<MetaTaskExecutor, String> {
+    let analysis_settings = load_analysis_settings(&state.db, task_id).await?;
+    let pipeline = load_task_pipeline(&state.db, task_id).await?;
+    let first_run = runs[0].clone();
+
+    let executor = MetaTaskExecutor::new(
+        &format!("multi_sample_task_{}", task_id),
+        task_id,
+        runs,
+        visual_config, // 传入视觉配置
+        analysis_settings,
+        pipeline,
+        state.clone(),
+    )
+    .with_completed_runs(completed_runs);
+    executor.build_sample_workflow(0, &first_run).validate()?;
+    Ok(executor)
+}
+
+/// 加载任务的样本和唤醒词，按策略展开样本运行
+async fn plan_task_runs(
+    db: &DatabaseService,
+    task_id: i64,
+    strategy: &WakeWordStrategy,
+) -> Result<Vec<SampleRun>, String> {
+    let task_samples = db.get_samples_by_task_id(task_id)
         .await
         .map_err(|e| e.to_string())?;
-    
     if task_samples.is_empty() {
         return Err("任务样本列表为空".to_string());
     }
 
-    let task = state.db.get_task_by_id(task_id)
+    let task = db.get_task_by_id(task_id)
         .await
         .map_err(|e| e.to_string())?
         .ok_or("任务不存在")?;
-    
-    // 3. 选择唤醒词
-    let selected_wake_word_id = if let Some(wid) = wake_word_id {
-        // 验证提供的唤醒词ID是否属于当前任务
-        if !task.wake_word_ids.contains(&wid) {
-            return Err("指定的唤醒词不属于当前任务".to_string());
-        }
-        wid
-    } else if let Some(first_wake_word_id) = task.wake_word_ids.first() {
-        *first_wake_word_id
-    } else {
-        return Err("任务没有关联的唤醒词".to_string());
-    };
 
-    let wakeword = state.db.get_wake_word_by_id(selected_wake_word_id)
+    let mut wake_words = Vec::with_capacity(task.wake_word_ids.len());
+    for wid in &task.wake_word_ids {
+        let wakeword = db.get_wake_word_by_id(*wid)
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or("唤醒词不存在")?;
+        wake_words.push(wakeword);
+    }
+
+    plan_sample_runs(&task_samples, &wake_words, strategy)
+}
+
+/// 加载任务选择的流水线，未选择时使用默认流水线
+async fn load_task_pipeline(db: &DatabaseService, task_id: i64) -> Result<PipelineDefinition, String> {
+    let name = db.get_task_pipeline(task_id)
         .await
         .map_err(|e| e.to_string())?
-        .ok_or("唤醒词不存在")?;
+        .unwrap_or_else(|| DEFAULT_PIPELINE.to_string());
+    pipeline::load_pipeline(&name).map_err(|e| format!("加载流水线失败: {}", e))
+}
 
-    // 4. 创建视觉配置
-    let visual_config = VisualWakeConfig {
-        template_data: template_data.unwrap_or_else(|| vec![]), // 如果没有提供模板，使用空列表
-        frame_rate: frame_rate.unwrap_or(10),
-        threshold: threshold.unwrap_or(0.5),
-        max_detection_time_secs: Some(max_detection_time_secs.unwrap_or(5)), // 提供默认值5秒
-    };
+/// 任务级别的大模型参数、提示词模板和评分标准
+async fn load_analysis_settings(db: &DatabaseService, task_id: i64) -> Result<AnalysisSettings, String> {
+    Ok(AnalysisSettings {
+        llm_overrides: db.get_task_llm_settings(task_id)
+            .await
+            .map_err(|e| e.to_string())?,
+        prompt_template: db.get_task_prompt_template(task_id)
+            .await
+            .map_err(|e| e.to_string())?,
+        rubric: db.get_task_rubric(task_id)
+            .await
+            .map_err(|e| e.to_string())?,
+    })
+}
 
-    // 5. 创建主工作流
-    let (mut main_workflow, _) = Workflow::new();
+/// 导出任务第一次样本运行的子工作流图（校验通过后），用于查看和文档。
+/// `format` 为 "mermaid"（默认）或 "dot"
+#[tauri::command]
+pub async fn get_sample_workflow_graph(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    format: Option<String>,
+) -> Result<String, String> {
+    let task_id = task_id as i64;
+    let strategy = state.db.get_task_wake_word_strategy(task_id)
+        .await
+        .map_err(|e| format!("获取唤醒词策略失败: {}", e))?;
+    let runs = plan_task_runs(&state.db, task_id, &strategy).await?;
+    let analysis_settings = load_analysis_settings(&state.db, task_id).await?;
+    let pipeline = load_task_pipeline(&state.db, task_id).await?;
 
-    // 6. 创建元任务，传入视觉配置
-    let multi_sample_executor = MetaTaskExecutor::new(
+    // 图结构与视觉模板无关，使用与 new_meta_workflow 相同的默认参数
+    let visual_config = VisualWakeConfig {
+        template_data: vec![],
+        frame_rate: 10,
+        threshold: 0.5,
+        max_detection_time_secs: Some(5),
+    };
+    let executor = MetaTaskExecutor::new(
         &format!("multi_sample_task_{}", task_id),
         task_id,
-        task_samples,
-        wakeword,
-        visual_config, // 传入视觉配置
+        Vec::new(),
+        visual_config,
+        analysis_settings,
+        pipeline,
         state.inner().clone(),
     );
+    let workflow = executor.build_sample_workflow(0, &runs[0]);
+    workflow.validate()?;
 
-    // 7. 将元任务作为唯一任务添加到主工作流
-    main_workflow.add_task(multi_sample_executor);
-
-    // 8. 运行主工作流，获取总控制句柄
-    let handle = main_workflow.run(app_handle).await;
-
-    // 9. 将总控制句柄存入全局状态
-    let mut workflow_handle_guard = state.workflow_handle.lock().await;
-    *workflow_handle_guard = Some(handle);
-
-    Ok(())
+    match format.as_deref().unwrap_or("mermaid") {
+        "mermaid" => Ok(workflow.to_mermaid()),
+        "dot" => Ok(workflow.to_dot()),
+        other => Err(format!("不支持的图格式: {}", other)),
+    }
 }
 
 #[tauri::command]
@@ -744,6 +1330,60 @@ pub async fn get_timing_data_by_task(
         .map_err(|e| format!("获取时间参数失败: {}", e))
 }
 
+/// 获取已保存的任务统计，任务尚未运行过时返回 None
+#[tauri::command]
+pub async fn get_task_statistics(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+) -> Result<Option<TaskStatistics>, String> {
+    state
+        .db
+        .get_task_statistics(task_id as i64)
+        .await
+        .map_err(|e| format!("获取任务统计失败: {}", e))
+}
+
+/// 根据当前已保存的结果重新计算任务统计
+#[tauri::command]
+pub async fn refresh_task_statistics(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+) -> Result<TaskStatistics, String> {
+    crate::services::task_statistics::refresh_task_statistics(&state.db, task_id as i64)
+        .await
+        .map_err(|e| format!("计算任务统计失败: {}", e))
+}
+
+/// 按样本汇总多次运行的稳定性（通过比例、耗时方差）
+#[tauri::command]
+pub async fn get_sample_stability(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+) -> Result<Vec<SampleStability>, String> {
+    crate::services::task_statistics::compute_sample_stability(&state.db, task_id as i64)
+        .await
+        .map_err(|e| format!("计算样本稳定性失败: {}", e))
+}
+
+/// 导出一个或多个任务的测试报告（xlsx），返回写入的文件路径
+#[tauri::command]
+pub async fn export_task_report(
+    state: State<'_, Arc<AppState>>,
+    task_ids: Vec<u32>,
+    file_path: String,
+) -> Result<String, String> {
+    let mut path = std::path::PathBuf::from(&file_path);
+    if path.extension().map_or(true, |ext| ext != "xlsx") {
+        path.set_extension("xlsx");
+    }
+    let task_ids: Vec<i64> = task_ids.into_iter().map(i64::from).collect();
+
+    crate::services::report_export::export_task_report(&state.db, &task_ids, &path)
+        .await
+        .map_err(|e| format!("导出测试报告失败: {}", e))?;
+    Ok(path.to_string_lossy().to_string())
+}
+
 #[tauri::command]
 pub async fn start_wake_detection_workflow(
     state: State<'_, Arc<AppState>>,
@@ -755,6 +1395,7 @@ pub async fn start_wake_detection_workflow(
 ) -> Result<(), String> {
     // 1. 获取当前任务ID
     let task_id = state.current_task_id.read().await.ok_or("没有设置当前任务ID")?;
+    let mut workflow_handle_guard = lock_idle_workflow_handle(&state).await?;
 
     // 2. 从数据库获取任务信息
     let task = state.db.get_task_by_id(task_id)
@@ -790,15 +1431,241 @@ pub async fn start_wake_detection_workflow(
     main_workflow.add_task(wake_detection_executor);
 
     // 7. 运行主工作流，获取总控制句柄
-    let handle = main_workflow.run(app_handle).await;
+    let handle = main_workflow.run(Arc::new(app_handle)).await;
 
     // 8. 将总控制句柄存入全局状态
-    let mut workflow_handle_guard = state.workflow_handle.lock().await;
     *workflow_handle_guard = Some(handle);
 
     Ok(())
 }
 
+// ==================== 测试队列相关命令 ====================
+
+/// 将任务加入测试队列，返回队列项ID。`priority` 越大越先执行，相同优先级按入队顺序执行
+#[tauri::command]
+pub async fn enqueue_job(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    spec: JobSpec,
+    priority: Option<i64>,
+) -> Result<i64, String> {
+    let task = state
+        .db
+        .get_task_by_id(task_id as i64)
+        .await
+        .map_err(|e| format!("获取任务失败: {}", e))?
+        .ok_or("任务不存在")?;
+    match &spec {
+        JobSpec::SampleTest { .. } if task.test_samples_ids.is_empty() => {
+            return Err("任务没有关联的测试样本".to_string());
+        }
+        JobSpec::WakeTest { .. } if task.wake_word_ids.is_empty() => {
+            return Err("任务没有关联的唤醒词".to_string());
+        }
+        _ => {}
+    }
+
+    let job_id = state
+        .db
+        .enqueue_job(task_id as i64, &spec, priority.unwrap_or(0))
+        .await
+        .map_err(|e| format!("加入测试队列失败: {}", e))?;
+    state.job_queue_notify.notify_one();
+    Ok(job_id)
+}
+
+/// 按执行顺序列出测试队列（含已结束的任务）
+#[tauri::command]
+pub async fn list_jobs(state: State<'_, Arc<AppState>>) -> Result<Vec<QueuedJob>, String> {
+    state
+        .db
+        .list_jobs()
+        .await
+        .map_err(|e| format!("获取测试队列失败: {}", e))
+}
+
+/// 暂停排队中的任务，恢复前不会被执行。正在执行的任务请使用 `pause_workflow`
+#[tauri::command]
+pub async fn pause_job(state: State<'_, Arc<AppState>>, job_id: u32) -> Result<(), String> {
+    let paused = state
+        .db
+        .transition_job(job_id as i64, &[JobStatus::Queued], JobStatus::Paused, None)
+        .await
+        .map_err(|e| format!("暂停队列任务失败: {}", e))?;
+    if !paused {
+        return Err("只能暂停排队中的任务".to_string());
+    }
+    Ok(())
+}
+
+/// 恢复已暂停的任务，或重新执行失败、已取消的任务（样本测试从第一个未完成的运行继续）
+#[tauri::command]
+pub async fn resume_job(state: State<'_, Arc<AppState>>, job_id: u32) -> Result<(), String> {
+    let resumed = state
+        .db
+        .transition_job(
+            job_id as i64,
+            &[JobStatus::Paused, JobStatus::Failed, JobStatus::Cancelled],
+            JobStatus::Queued,
+            None,
+        )
+        .await
+        .map_err(|e| format!("恢复队列任务失败: {}", e))?;
+    if !resumed {
+        return Err("只能恢复已暂停、失败或已取消的任务".to_string());
+    }
+    state.job_queue_notify.notify_one();
+    Ok(())
+}
+
+/// 取消队列中的任务。正在执行的任务收到停止信号，保存当前进度后结束
+#[tauri::command]
+pub async fn cancel_job(state: State<'_, Arc<AppState>>, job_id: u32) -> Result<(), String> {
+    let job_id = job_id as i64;
+    let cancelled = state
+        .db
+        .transition_job(job_id, &[JobStatus::Queued, JobStatus::Paused], JobStatus::Cancelled, None)
+        .await
+        .map_err(|e| format!("取消队列任务失败: {}", e))?;
+    if cancelled {
+        return Ok(());
+    }
+
+    let job = state
+        .db
+        .get_job(job_id)
+        .await
+        .map_err(|e| format!("获取队列任务失败: {}", e))?
+        .ok_or("队列任务不存在")?;
+    if job.status != JobStatus::Running {
+        return Err("任务已结束".to_string());
+    }
+    // 同一时间只有一个工作流在运行，执行中的队列任务即当前工作流
+    if let Some(handle) = state.workflow_handle.lock().await.as_ref() {
+        handle.stop();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn set_job_priority(
+    state: State<'_, Arc<AppState>>,
+    job_id: u32,
+    priority: i64,
+) -> Result<(), String> {
+    state
+        .db
+        .update_job_priority(job_id as i64, priority)
+        .await
+        .map_err(|e| format!("更新队列任务优先级失败: {}", e))
+}
+
+/// 按给定顺序重新排列队列任务（同一优先级内生效），未列出的任务位置不变
+#[tauri::command]
+pub async fn reorder_jobs(state: State<'_, Arc<AppState>>, job_ids: Vec<u32>) -> Result<(), String> {
+    let job_ids: Vec<i64> = job_ids.into_iter().map(i64::from).collect();
+    state
+        .db
+        .reorder_jobs(&job_ids)
+        .await
+        .map_err(|e| format!("调整队列顺序失败: {}", e))
+}
+
+// ==================== 定时执行相关命令 ====================
+
+/// 为任务创建定时执行计划。`spec` 为空时按任务保存的设置执行样本测试
+#[tauri::command]
+pub async fn create_schedule(
+    state: State<'_, Arc<AppState>>,
+    task_id: u32,
+    rule: ScheduleRule,
+    spec: Option<JobSpec>,
+    priority: Option<i64>,
+    missed_run_policy: Option<MissedRunPolicy>,
+) -> Result<TaskSchedule, String> {
+    state
+        .db
+        .get_task_by_id(task_id as i64)
+        .await
+        .map_err(|e| format!("获取任务失败: {}", e))?
+        .ok_or("任务不存在")?;
+    let next_run_at = scheduler::next_run_at(&rule, chrono::Local::now())?
+        .ok_or("该规则没有可执行的时间")?;
+    let spec = spec.unwrap_or(JobSpec::SampleTest {
+        wake_word_strategy: None,
+        visual: JobVisualSettings::default(),
+    });
+
+    let schedule_id = state
+        .db
+        .create_schedule(
+            task_id as i64,
+            &rule,
+            &spec,
+            priority.unwrap_or(0),
+            missed_run_policy.unwrap_or_default(),
+            Some(&next_run_at),
+        )
+        .await
+        .map_err(|e| format!("创建定时计划失败: {}", e))?;
+    state
+        .db
+        .get_schedule(schedule_id)
+        .await
+        .map_err(|e| format!("获取定时计划失败: {}", e))?
+        .ok_or_else(|| "定时计划不存在".to_string())
+}
+
+#[tauri::command]
+pub async fn list_schedules(state: State<'_, Arc<AppState>>) -> Result<Vec<TaskSchedule>, String> {
+    state
+        .db
+        .list_schedules()
+        .await
+        .map_err(|e| format!("获取定时计划失败: {}", e))
+}
+
+/// 启用或停用定时计划。重新启用时从现在开始计算下一次执行时间，停用期间错过的不再补执行
+#[tauri::command]
+pub async fn set_schedule_enabled(
+    state: State<'_, Arc<AppState>>,
+    schedule_id: u32,
+    enabled: bool,
+) -> Result<(), String> {
+    let schedule = state
+        .db
+        .get_schedule(schedule_id as i64)
+        .await
+        .map_err(|e| format!("获取定时计划失败: {}", e))?
+        .ok_or("定时计划不存在")?;
+    let next_run_at = if enabled {
+        scheduler::next_run_at(&schedule.rule, chrono::Local::now())?
+    } else {
+        schedule.next_run_at
+    };
+    state
+        .db
+        .set_schedule_enabled(schedule.id, enabled, next_run_at.as_deref())
+        .await
+        .map_err(|e| format!("更新定时计划失败: {}", e))
+}
+
+#[tauri::command]
+pub async fn delete_schedule(state: State<'_, Arc<AppState>>, schedule_id: u32) -> Result<(), String> {
+    state
+        .db
+        .delete_schedule(schedule_id as i64)
+        .await
+        .map_err(|e| format!("删除定时计划失败: {}", e))
+}
+
+/// 预览规则接下来的执行时间（UTC，默认 5 次），用于在保存前检查 cron 表达式
+#[tauri::command]
+pub async fn preview_schedule(rule: ScheduleRule, count: Option<u32>) -> Result<Vec<String>, String> {
+    let runs = scheduler::upcoming_runs(&rule, chrono::Local::now(), count.unwrap_or(5).min(100) as usize)?;
+    Ok(runs.iter().map(scheduler::format_time).collect())
+}
+
 // ==================== 视觉唤醒检测相关命令 ====================
 
 /// 启动视觉唤醒检测
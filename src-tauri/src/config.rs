@@ -12,6 +12,8 @@ pub struct AppConfig {
     pub openrouter: OpenRouterConfig,
     #[serde(default)]
     pub app: AppSettings,
+    #[serde(default)]
+    pub asr: AsrConfig,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -24,18 +26,58 @@ pub struct XunfeiConfig {
     pub api_secret: String,
 }
 
+/// 大模型评估服务配置，支持任意 OpenAI 兼容接口（OpenRouter、vLLM、llama.cpp 等）
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct OpenRouterConfig {
     #[serde(default)]
     pub api_key: String,
     #[serde(default)]
     pub base_url: String,
+    #[serde(default)]
+    pub model: String,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    /// 单次请求超时，0 表示使用 app.timeout_seconds
+    #[serde(default)]
+    pub timeout_seconds: u64,
+    /// 是否请求 `response_format: json_object`，部分本地服务不支持
+    #[serde(default = "default_json_mode")]
+    pub json_mode: bool,
+    /// 遇到 429/5xx 或网络错误时的最大重试次数
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_json_mode() -> bool {
+    true
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+/// 语音识别后端配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AsrConfig {
+    /// "xunfei"（讯飞云端）、"local"（本地离线引擎）或 "mock"（回放/模拟）
+    #[serde(default)]
+    pub provider: String,
+    /// 本地离线引擎的 WebSocket 地址
+    #[serde(default)]
+    pub local_url: String,
+    /// mock 模式下的回放文件，格式为 {"期望文本": "识别结果"}
+    #[serde(default)]
+    pub replay_file: String,
+    /// mock 模式下返回结果前的模拟延迟
+    #[serde(default)]
+    pub mock_delay_ms: u64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct AppSettings {
     #[serde(default)]
     pub log_level: String,
+    /// 每次样本运行中同时执行的任务数上限，0 表示不限制
     #[serde(default)]
     pub max_concurrent_tasks: usize,
     #[serde(default)]
@@ -48,6 +90,7 @@ impl Default for AppConfig {
             xunfei: XunfeiConfig::default(),
             openrouter: OpenRouterConfig::default(),
             app: AppSettings::default(),
+            asr: AsrConfig::default(),
         }
     }
 }
@@ -66,7 +109,23 @@ impl Default for OpenRouterConfig {
     fn default() -> Self {
         Self {
             api_key: String::new(),
-            base_url: String::new(),
+            base_url: "https://openrouter.ai/api/v1".to_string(),
+            model: "google/gemini-2.5-flash".to_string(),
+            temperature: None,
+            timeout_seconds: 0,
+            json_mode: true,
+            max_retries: default_max_retries(),
+        }
+    }
+}
+
+impl Default for AsrConfig {
+    fn default() -> Self {
+        Self {
+            provider: "xunfei".to_string(),
+            local_url: "ws://127.0.0.1:2700".to_string(),
+            replay_file: String::new(),
+            mock_delay_ms: 1500,
         }
     }
 }
@@ -129,6 +188,17 @@ impl AppConfig {
         if let Ok(api_key) = env::var("OPENROUTER_API_KEY") {
             config.openrouter.api_key = api_key;
         }
+        if let Ok(base_url) = env::var("LLM_BASE_URL") {
+            config.openrouter.base_url = base_url;
+        }
+        if let Ok(model) = env::var("LLM_MODEL") {
+            config.openrouter.model = model;
+        }
+
+        // ASR backend from environment
+        if let Ok(provider) = env::var("ASR_PROVIDER") {
+            config.asr.provider = provider;
+        }
 
         // App settings from environment
         if let Ok(log_level) = env::var("LOG_LEVEL") {
@@ -44,6 +44,33 @@ pub async fn update_app_config(
                 match update.key.as_str() {
                     "api_key" => config.openrouter.api_key = update.value.as_str().unwrap_or_default().to_string(),
                     "base_url" => config.openrouter.base_url = update.value.as_str().unwrap_or_default().to_string(),
+                    "model" => config.openrouter.model = update.value.as_str().unwrap_or_default().to_string(),
+                    "temperature" => {
+                        config.openrouter.temperature = update.value.as_f64()
+                            .or_else(|| update.value.as_str().and_then(|v| v.parse().ok()))
+                            .map(|v| v as f32);
+                    },
+                    "timeout_seconds" => {
+                        if let Some(value) = update.value.as_u64() {
+                            config.openrouter.timeout_seconds = value;
+                        } else if let Some(value) = update.value.as_str().and_then(|v| v.parse().ok()) {
+                            config.openrouter.timeout_seconds = value;
+                        }
+                    },
+                    "json_mode" => {
+                        if let Some(value) = update.value.as_bool() {
+                            config.openrouter.json_mode = value;
+                        } else if let Some(value) = update.value.as_str().and_then(|v| v.parse().ok()) {
+                            config.openrouter.json_mode = value;
+                        }
+                    },
+                    "max_retries" => {
+                        if let Some(value) = update.value.as_u64() {
+                            config.openrouter.max_retries = value as u32;
+                        } else if let Some(value) = update.value.as_str().and_then(|v| v.parse().ok()) {
+                            config.openrouter.max_retries = value;
+                        }
+                    },
                     _ => return Err(format!("Invalid openrouter config key: {}", update.key)),
                 }
             },
@@ -63,6 +90,21 @@ pub async fn update_app_config(
                     _ => return Err(format!("Invalid app config key: {}", update.key)),
                 }
             },
+            "asr" => {
+                match update.key.as_str() {
+                    "provider" => config.asr.provider = update.value.as_str().unwrap_or_default().to_string(),
+                    "local_url" => config.asr.local_url = update.value.as_str().unwrap_or_default().to_string(),
+                    "replay_file" => config.asr.replay_file = update.value.as_str().unwrap_or_default().to_string(),
+                    "mock_delay_ms" => {
+                        if let Some(value) = update.value.as_u64() {
+                            config.asr.mock_delay_ms = value;
+                        } else if let Some(value) = update.value.as_str().and_then(|v| v.parse().ok()) {
+                            config.asr.mock_delay_ms = value;
+                        }
+                    },
+                    _ => return Err(format!("Invalid asr config key: {}", update.key)),
+                }
+            },
             _ => return Err(format!("Invalid config section: {}", update.section)),
         }
     }
@@ -131,17 +173,31 @@ pub async fn validate_config() -> Result<ConfigResponse, String> {
 
     let mut errors = Vec::new();
 
-    if config.xunfei.appid.is_empty() {
-        errors.push("Xunfei APPID is required");
-    }
-    if config.xunfei.api_key.is_empty() {
-        errors.push("Xunfei API Key is required");
-    }
-    if config.xunfei.api_secret.is_empty() {
-        errors.push("Xunfei API Secret is required");
+    match config.asr.provider.as_str() {
+        "xunfei" | "" => {
+            if config.xunfei.appid.is_empty() {
+                errors.push("Xunfei APPID is required");
+            }
+            if config.xunfei.api_key.is_empty() {
+                errors.push("Xunfei API Key is required");
+            }
+            if config.xunfei.api_secret.is_empty() {
+                errors.push("Xunfei API Secret is required");
+            }
+        }
+        "local" => {
+            if config.asr.local_url.is_empty() {
+                errors.push("Local ASR engine URL is required");
+            }
+        }
+        "mock" => {}
+        _ => errors.push("Unknown ASR provider (expected xunfei, local or mock)"),
     }
 
-    if config.openrouter.api_key.is_empty() {
+    // 只有使用 OpenRouter 云端服务时才需要密钥，本地部署的兼容服务可以留空
+    if config.openrouter.api_key.is_empty()
+        && (config.openrouter.base_url.is_empty() || config.openrouter.base_url.contains("openrouter.ai"))
+    {
         errors.push("OpenRouter API Key is recommended");
     }
 
@@ -176,6 +232,11 @@ pub async fn get_config_value(section: String, key: String) -> Result<String, St
         "openrouter" => match key.as_str() {
             "api_key" => &config.openrouter.api_key,
             "base_url" => &config.openrouter.base_url,
+            "model" => &config.openrouter.model,
+            "temperature" => return Ok(config.openrouter.temperature.map(|v| v.to_string()).unwrap_or_default()),
+            "timeout_seconds" => return Ok(config.openrouter.timeout_seconds.to_string()),
+            "json_mode" => return Ok(config.openrouter.json_mode.to_string()),
+            "max_retries" => return Ok(config.openrouter.max_retries.to_string()),
             _ => return Err(format!("Invalid openrouter key: {}", key)),
         },
         "app" => match key.as_str() {
@@ -184,6 +245,13 @@ pub async fn get_config_value(section: String, key: String) -> Result<String, St
             "timeout_seconds" => return Ok(config.app.timeout_seconds.to_string()),
             _ => return Err(format!("Invalid app key: {}", key)),
         },
+        "asr" => match key.as_str() {
+            "provider" => &config.asr.provider,
+            "local_url" => &config.asr.local_url,
+            "replay_file" => &config.asr.replay_file,
+            "mock_delay_ms" => return Ok(config.asr.mock_delay_ms.to_string()),
+            _ => return Err(format!("Invalid asr key: {}", key)),
+        },
         _ => return Err(format!("Invalid section: {}", section)),
     };
 
@@ -1,8 +1,11 @@
 use crate::models::*;
+use crate::services::alignment::AsrMetricsSummary;
+use crate::services::llm_provider::LlmOverrides;
+use crate::services::run_plan::WakeWordStrategy;
 use anyhow::Result;
 use chrono::Utc;
 use sqlx::{Row, SqlitePool};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Debug)]
 pub struct DatabaseService {
@@ -210,19 +213,279 @@ impl DatabaseService {
         .execute(pool)
         .await?;
 
-        // 创建分析结果表
+        // 创建分析结果表（各维度得分存放在 analysis_dimension_scores 子表中）
+        sqlx::query(&Self::analysis_results_table_sql("analysis_results"))
+            .execute(pool)
+            .await?;
+
+        // 创建唤醒检测结果表
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS wake_detection_results (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id INTEGER NOT NULL,
+                wake_word_id INTEGER NOT NULL,
+                success BOOLEAN NOT NULL,
+                confidence REAL,
+                timestamp INTEGER NOT NULL,
+                duration_ms INTEGER NOT NULL,
+                asr_result TEXT,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+                FOREIGN KEY (wake_word_id) REFERENCES wake_words(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建时间参数表
+        sqlx::query(&Self::timing_data_table_sql("timing_data"))
+            .execute(pool)
+            .await?;
+
+        // 创建任务统计表，每个任务一行，由统计服务整体覆盖写入
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS task_statistics (
+                task_id INTEGER PRIMARY KEY,
+                total_samples INTEGER NOT NULL DEFAULT 0,
+                completed_samples INTEGER NOT NULL DEFAULT 0,
+                passed_samples INTEGER NOT NULL DEFAULT 0,
+                evaluation_errors INTEGER NOT NULL DEFAULT 0,
+                pass_rate REAL,
+                average_score REAL,
+                recognition_success_rate REAL,
+                wake_attempts INTEGER NOT NULL DEFAULT 0,
+                wake_successes INTEGER NOT NULL DEFAULT 0,
+                wake_rate REAL,
+                recognition_min_ms REAL,
+                recognition_max_ms REAL,
+                recognition_avg_ms REAL,
+                recognition_p50_ms REAL,
+                recognition_p90_ms REAL,
+                recognition_p99_ms REAL,
+                response_avg_ms REAL,
+                response_p50_ms REAL,
+                response_p90_ms REAL,
+                response_p99_ms REAL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建样本运行状态表，记录每次样本运行的执行进度，用于中断后续跑
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS sample_run_states (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id INTEGER NOT NULL,
+                sample_id INTEGER NOT NULL,
+                wake_word_id INTEGER NOT NULL DEFAULT 0,
+                run_index INTEGER NOT NULL DEFAULT 0,
+                run_order INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                error TEXT,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+                UNIQUE(task_id, sample_id, wake_word_id, run_index)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建工作流执行记录表，每次样本子工作流运行一条，任务节点的记录见 workflow_task_traces
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS workflow_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id INTEGER NOT NULL,
+                sample_id INTEGER NOT NULL DEFAULT 0,
+                wake_word_id INTEGER NOT NULL DEFAULT 0,
+                run_index INTEGER NOT NULL DEFAULT 0,
+                pipeline TEXT NOT NULL,
+                status TEXT NOT NULL,
+                error TEXT,
+                started_at TEXT NOT NULL,
+                finished_at TEXT NOT NULL,
+                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS workflow_task_traces (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                run_id INTEGER NOT NULL,
+                node_id TEXT NOT NULL,
+                status TEXT NOT NULL,
+                started_at TEXT,
+                finished_at TEXT,
+                duration_ms INTEGER,
+                attempts INTEGER NOT NULL DEFAULT 0,
+                message TEXT,
+                outputs TEXT NOT NULL DEFAULT '[]',
+                FOREIGN KEY (run_id) REFERENCES workflow_runs(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建测试队列表，程序重启后继续执行排队的任务
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS job_queue (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id INTEGER NOT NULL,
+                spec TEXT NOT NULL,
+                priority INTEGER NOT NULL DEFAULT 0,
+                position INTEGER NOT NULL,
+                status TEXT NOT NULL DEFAULT 'queued',
+                error TEXT,
+                created_at TEXT NOT NULL,
+                started_at TEXT,
+                finished_at TEXT,
+                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建定时执行计划表
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS task_schedules (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id INTEGER NOT NULL,
+                rule TEXT NOT NULL,
+                spec TEXT NOT NULL,
+                priority INTEGER NOT NULL DEFAULT 0,
+                missed_run_policy TEXT NOT NULL DEFAULT 'run_once',
+                enabled BOOLEAN NOT NULL DEFAULT 1,
+                last_run_at TEXT,
+                next_run_at TEXT,
+                last_task_id INTEGER,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建车机响应表
+        sqlx::query(&Self::machine_responses_table_sql("machine_responses"))
+            .execute(pool)
+            .await?;
+
+        // 创建评估提示词模板表
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS prompt_templates (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                content TEXT NOT NULL,
+                description TEXT,
+                created_at TEXT NOT NULL,
+                UNIQUE(name, version)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建评分标准表
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS rubrics (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL UNIQUE,
+                description TEXT,
+                pass_threshold REAL NOT NULL DEFAULT 0.6,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建评分维度表
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS rubric_dimensions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                rubric_id INTEGER NOT NULL,
+                dimension_key TEXT NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                weight REAL NOT NULL DEFAULT 1.0,
+                position INTEGER NOT NULL DEFAULT 0,
+                FOREIGN KEY (rubric_id) REFERENCES rubrics(id) ON DELETE CASCADE,
+                UNIQUE(rubric_id, dimension_key)
+            )
+            "#,
+        )
+        .execute(pool)
+        .await?;
+
+        // 创建分析结果维度得分表
+        sqlx::query(&Self::analysis_dimension_scores_table_sql("analysis_dimension_scores"))
+            .execute(pool)
+            .await?;
+
+        // 旧数据库补充新增列
+        Self::ensure_column(pool, "tasks", "llm_settings", "TEXT").await?;
+        Self::ensure_column(pool, "tasks", "wake_word_strategy", "TEXT").await?;
+        Self::ensure_column(pool, "tasks", "prompt_template_id", "INTEGER").await?;
+        Self::ensure_column(pool, "tasks", "pipeline", "TEXT").await?;
+        Self::ensure_column(pool, "tasks", "rubric_id", "INTEGER").await?;
+        Self::ensure_column(pool, "analysis_results", "prompt_template_id", "INTEGER").await?;
+        Self::ensure_column(pool, "analysis_results", "prompt_template_version", "INTEGER").await?;
+        Self::ensure_column(pool, "analysis_results", "rubric_id", "INTEGER").await?;
+
+        // 旧版分析结果表的三个固定维度列迁移到维度得分表
+        Self::migrate_fixed_assessment_columns(pool).await?;
+
+        // 结果表按唤醒词和重复序号区分同一样本的多次运行（run_index 是最新加入的键列，
+        // 缺少它的表一律按最新结构重建，更早缺少 wake_word_id 的表也一并补齐）
+        Self::rebuild_table_with_column(pool, "analysis_results", "run_index", Self::analysis_results_table_sql).await?;
+        Self::rebuild_table_with_column(pool, "analysis_dimension_scores", "run_index", Self::analysis_dimension_scores_table_sql).await?;
+        Self::rebuild_table_with_column(pool, "machine_responses", "run_index", Self::machine_responses_table_sql).await?;
+        Self::rebuild_table_with_column(pool, "timing_data", "run_index", Self::timing_data_table_sql).await?;
+
+        // 内置默认评分标准
+        Self::seed_default_rubric(pool).await?;
+
+        // 内置默认模板
         sqlx::query(
+            "INSERT OR IGNORE INTO prompt_templates (name, version, content, description, created_at) VALUES ('default', 1, ?, ?, ?)",
+        )
+        .bind(DEFAULT_PROMPT_TEMPLATE)
+        .bind("内置默认评估模板")
+        .bind(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+
+    fn analysis_results_table_sql(table_name: &str) -> String {
+        format!(
             r#"
-            CREATE TABLE IF NOT EXISTS analysis_results (
+            CREATE TABLE IF NOT EXISTS {} (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 task_id INTEGER NOT NULL,
                 sample_id INTEGER NOT NULL,
-                semantic_correctness_score REAL NOT NULL,
-                semantic_correctness_comment TEXT,
-                state_change_score REAL NOT NULL,
-                state_change_comment TEXT,
-                unambiguous_score REAL NOT NULL,
-                unambiguous_comment TEXT,
                 overall_score REAL NOT NULL,
                 is_valid BOOLEAN NOT NULL,
                 suggestions TEXT,
@@ -245,43 +508,72 @@ impl DatabaseService {
                 recognition_time REAL,
                 response_time REAL,
                 created_at TEXT NOT NULL,
+                prompt_template_id INTEGER,
+                prompt_template_version INTEGER,
+                rubric_id INTEGER,
+                wake_word_id INTEGER NOT NULL DEFAULT 0,
+                run_index INTEGER NOT NULL DEFAULT 0,
                 FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
                 FOREIGN KEY (sample_id) REFERENCES test_samples(id) ON DELETE CASCADE,
-                UNIQUE(task_id, sample_id)
+                UNIQUE(task_id, sample_id, wake_word_id, run_index)
             )
             "#,
+            table_name
         )
-        .execute(pool)
-        .await?;
+    }
 
-        // 创建唤醒检测结果表
-        sqlx::query(
+    fn analysis_dimension_scores_table_sql(table_name: &str) -> String {
+        format!(
             r#"
-            CREATE TABLE IF NOT EXISTS wake_detection_results (
+            CREATE TABLE IF NOT EXISTS {} (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 task_id INTEGER NOT NULL,
-                wake_word_id INTEGER NOT NULL,
-                success BOOLEAN NOT NULL,
-                confidence REAL,
-                timestamp INTEGER NOT NULL,
-                duration_ms INTEGER NOT NULL,
-                asr_result TEXT,
+                sample_id INTEGER NOT NULL,
+                wake_word_id INTEGER NOT NULL DEFAULT 0,
+                run_index INTEGER NOT NULL DEFAULT 0,
+                dimension_key TEXT NOT NULL,
+                score REAL NOT NULL,
+                comment TEXT,
                 created_at TEXT NOT NULL,
                 FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
-                FOREIGN KEY (wake_word_id) REFERENCES wake_words(id) ON DELETE CASCADE
+                FOREIGN KEY (sample_id) REFERENCES test_samples(id) ON DELETE CASCADE,
+                UNIQUE(task_id, sample_id, wake_word_id, run_index, dimension_key)
             )
             "#,
+            table_name
         )
-        .execute(pool)
-        .await?;
+    }
 
-        // 创建时间参数表
-        sqlx::query(
+    fn machine_responses_table_sql(table_name: &str) -> String {
+        format!(
+            r#"
+            CREATE TABLE IF NOT EXISTS {} (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id INTEGER NOT NULL,
+                sample_id INTEGER NOT NULL,
+                wake_word_id INTEGER NOT NULL DEFAULT 0,
+                run_index INTEGER NOT NULL DEFAULT 0,
+                text TEXT NOT NULL,
+                connected BOOLEAN NOT NULL DEFAULT true,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
+                FOREIGN KEY (sample_id) REFERENCES test_samples(id) ON DELETE CASCADE,
+                UNIQUE(task_id, sample_id, wake_word_id, run_index)
+            )
+            "#,
+            table_name
+        )
+    }
+
+    fn timing_data_table_sql(table_name: &str) -> String {
+        format!(
             r#"
-            CREATE TABLE IF NOT EXISTS timing_data (
+            CREATE TABLE IF NOT EXISTS {} (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 task_id INTEGER NOT NULL,
                 sample_id INTEGER NOT NULL,
+                wake_word_id INTEGER NOT NULL DEFAULT 0,
+                run_index INTEGER NOT NULL DEFAULT 0,
                 voice_command_start_time TEXT,
                 first_char_appear_time TEXT,
                 voice_command_end_time TEXT,
@@ -294,32 +586,171 @@ impl DatabaseService {
                 created_at TEXT NOT NULL,
                 FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
                 FOREIGN KEY (sample_id) REFERENCES test_samples(id) ON DELETE CASCADE,
-                UNIQUE(task_id, sample_id)
+                UNIQUE(task_id, sample_id, wake_word_id, run_index)
             )
             "#,
+            table_name
         )
-        .execute(pool)
+    }
+
+    /// 表中缺少 `column` 时按最新结构重建（唯一约束变化无法通过 ALTER TABLE 完成），
+    /// 新旧结构共有的列原样复制，新增列取默认值
+    async fn rebuild_table_with_column(
+        pool: &SqlitePool,
+        table: &str,
+        column: &str,
+        table_sql: fn(&str) -> String,
+    ) -> Result<()> {
+        if Self::column_exists(pool, table, column).await? {
+            return Ok(());
+        }
+        log::info!("[DB_SERVICE] Rebuilding table {} to add column {}", table, column);
+
+        let old_columns: Vec<String> = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect();
+        let new_table = format!("{}_new", table);
+
+        let mut tx = pool.begin().await?;
+        sqlx::query(&table_sql(&new_table)).execute(&mut *tx).await?;
+        let common_columns: Vec<String> = sqlx::query(&format!("PRAGMA table_info({})", new_table))
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .filter(|name| old_columns.contains(name))
+            .collect();
+
+        // 与旧版字段迁移一致，跳过已删除任务/样本的残留行
+        sqlx::query(&format!(
+            "INSERT INTO {new} ({cols}) SELECT {cols} FROM {old} \
+             WHERE task_id IN (SELECT id FROM tasks) AND sample_id IN (SELECT id FROM test_samples)",
+            new = new_table,
+            old = table,
+            cols = common_columns.join(", ")
+        ))
+        .execute(&mut *tx)
         .await?;
+        sqlx::query(&format!("DROP TABLE {}", table))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!("ALTER TABLE {} RENAME TO {}", new_table, table))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
 
-        // 创建车机响应表
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS machine_responses (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                task_id INTEGER NOT NULL,
-                sample_id INTEGER NOT NULL,
-                text TEXT NOT NULL,
-                connected BOOLEAN NOT NULL DEFAULT true,
-                created_at TEXT NOT NULL,
-                FOREIGN KEY (task_id) REFERENCES tasks(id) ON DELETE CASCADE,
-                FOREIGN KEY (sample_id) REFERENCES test_samples(id) ON DELETE CASCADE,
-                UNIQUE(task_id, sample_id)
-            )
-            "#,
+    /// 把旧表中 semantic_correctness / state_change / unambiguous 三组固定列
+    /// 转存到 analysis_dimension_scores，然后重建不含这些列的 analysis_results
+    async fn migrate_fixed_assessment_columns(pool: &SqlitePool) -> Result<()> {
+        if !Self::column_exists(pool, "analysis_results", "semantic_correctness_score").await? {
+            return Ok(());
+        }
+        log::info!("[DB_SERVICE] Migrating fixed assessment columns to analysis_dimension_scores");
+
+        // 早期版本未开启外键约束，可能残留已删除任务/样本的结果，迁移时跳过
+        let live_rows = "task_id IN (SELECT id FROM tasks) AND sample_id IN (SELECT id FROM test_samples)";
+
+        let mut tx = pool.begin().await?;
+
+        for (key, score_column, comment_column) in [
+            ("semantic_correctness", "semantic_correctness_score", "semantic_correctness_comment"),
+            ("state_change_confirmation", "state_change_score", "state_change_comment"),
+            ("unambiguous_expression", "unambiguous_score", "unambiguous_comment"),
+        ] {
+            sqlx::query(&format!(
+                "INSERT OR IGNORE INTO analysis_dimension_scores (task_id, sample_id, dimension_key, score, comment, created_at) \
+                 SELECT task_id, sample_id, ?, {}, {}, created_at FROM analysis_results WHERE {}",
+                score_column, comment_column, live_rows
+            ))
+            .bind(key)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let columns = "id, task_id, sample_id, overall_score, is_valid, suggestions, llm_title, llm_content, \
+            llm_context, llm_multi_round, test_time, audio_file, recognition_file, device, recognition_result, \
+            insertion_errors, deletion_errors, substitution_errors, total_words, reference_text, recognized_text, \
+            result_status, recognition_time, response_time, created_at, prompt_template_id, prompt_template_version, rubric_id";
+
+        sqlx::query(&Self::analysis_results_table_sql("analysis_results_new"))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!(
+            "INSERT INTO analysis_results_new ({0}) SELECT {0} FROM analysis_results WHERE {1}",
+            columns, live_rows
+        ))
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DROP TABLE analysis_results")
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("ALTER TABLE analysis_results_new RENAME TO analysis_results")
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        log::info!("[DB_SERVICE] Assessment column migration completed");
+        Ok(())
+    }
+
+    async fn seed_default_rubric(pool: &SqlitePool) -> Result<()> {
+        let existing: Option<i64> = sqlx::query_scalar("SELECT id FROM rubrics WHERE name = 'default'")
+            .fetch_optional(pool)
+            .await?;
+        if existing.is_some() {
+            return Ok(());
+        }
+
+        let mut tx = pool.begin().await?;
+        let result = sqlx::query(
+            "INSERT INTO rubrics (name, description, pass_threshold, created_at) VALUES ('default', ?, 0.6, ?)",
         )
-        .execute(pool)
+        .bind("内置默认评分标准")
+        .bind(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(&mut *tx)
         .await?;
+        let rubric_id = result.last_insert_rowid();
+
+        for (position, dimension) in default_rubric_dimensions().iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO rubric_dimensions (rubric_id, dimension_key, name, description, weight, position) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(rubric_id)
+            .bind(&dimension.key)
+            .bind(&dimension.name)
+            .bind(&dimension.description)
+            .bind(dimension.weight)
+            .bind(position as i64)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn column_exists(pool: &SqlitePool, table: &str, column: &str) -> Result<bool> {
+        let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(pool)
+            .await?;
+        Ok(columns
+            .iter()
+            .any(|row| row.get::<String, _>("name") == column))
+    }
 
+    /// 如果表中缺少指定列则添加（SQLite 不支持 ADD COLUMN IF NOT EXISTS）
+    async fn ensure_column(pool: &SqlitePool, table: &str, column: &str, definition: &str) -> Result<()> {
+        if !Self::column_exists(pool, table, column).await? {
+            log::info!("[DB_SERVICE] Adding column {}.{}", table, column);
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+        }
         Ok(())
     }
 
@@ -361,142 +792,1086 @@ impl DatabaseService {
         }
 
         tx.commit().await?;
-        Ok(task_id)
+        Ok(task_id)
+    }
+
+    pub async fn get_task_by_id(&self, task_id: i64) -> Result<Option<Task>> {
+        // 获取任务基本信息
+        let task_row = sqlx::query_as::<_, TaskRow>("SELECT * FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(task_row) = task_row else {
+            return Ok(None);
+        };
+
+        // 获取关联的样本ID
+        let sample_rows = sqlx::query("SELECT sample_id FROM task_samples WHERE task_id = ?")
+            .bind(task_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let sample_ids: Vec<u32> = sample_rows
+            .into_iter()
+            .map(|row| row.get::<i64, _>("sample_id") as u32)
+            .collect();
+
+        // 获取关联的唤醒词ID
+        let wake_word_rows = sqlx::query("SELECT wake_word_id FROM task_wake_words WHERE task_id = ?")
+            .bind(task_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        let wake_word_ids: Vec<u32> = wake_word_rows
+            .into_iter()
+            .map(|row| row.get::<i64, _>("wake_word_id") as u32)
+            .collect();
+
+        // 获取车机响应
+        let machine_responses = self.get_machine_responses_by_task(task_id).await?;
+
+        // 获取测试结果
+        let test_results = self.get_analysis_results_by_task(task_id).await?;
+
+        Ok(Some(Task {
+            id: task_row.id as u32,
+            name: task_row.name,
+            test_samples_ids: sample_ids,
+            wake_word_ids: wake_word_ids,
+            machine_response: if machine_responses.is_empty() {
+                None
+            } else {
+                Some(machine_responses)
+            },
+            test_result: if test_results.is_empty() {
+                None
+            } else {
+                Some(test_results)
+            },
+            task_status: task_row.task_status,
+            task_progress: task_row.task_progress.map(|p| p as f32),
+            created_at: task_row.created_at,
+            audio_type: task_row.audio_type,
+            audio_file: task_row.audio_file,
+            audio_duration: task_row.audio_duration,
+            audio_category: task_row.audio_category,
+            test_collection: task_row.test_collection,
+            test_duration: task_row.test_duration,
+            sentence_accuracy: task_row.sentence_accuracy.map(|v| v as f32),
+            word_accuracy: task_row.word_accuracy.map(|v| v as f32),
+            character_error_rate: task_row.character_error_rate.map(|v| v as f32),
+            recognition_success_rate: task_row.recognition_success_rate.map(|v| v as f32),
+            total_words: task_row.total_words.map(|v| v as u32),
+            insertion_errors: task_row.insertion_errors.map(|v| v as u32),
+            deletion_errors: task_row.deletion_errors.map(|v| v as u32),
+            substitution_errors: task_row.substitution_errors.map(|v| v as u32),
+            fastest_recognition_time: task_row.fastest_recognition_time.map(|v| v as f32),
+            slowest_recognition_time: task_row.slowest_recognition_time.map(|v| v as f32),
+            average_recognition_time: task_row.average_recognition_time.map(|v| v as f32),
+            completed_samples: task_row.completed_samples.map(|v| v as u32),
+        }))
+    }
+
+    pub async fn get_all_tasks(&self) -> Result<Vec<Task>> {
+        let task_rows =
+            sqlx::query_as::<_, TaskRow>("SELECT * FROM tasks ORDER BY created_at DESC")
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut tasks = Vec::new();
+        for task_row in task_rows {
+            if let Some(task) = self.get_task_by_id(task_row.id).await? {
+                tasks.push(task);
+            }
+        }
+
+        Ok(tasks)
+    }
+
+    pub async fn delete_task(&self, task_id: i64) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        // 删除任务-样本关联
+        sqlx::query("DELETE FROM task_samples WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // 删除分析结果
+        sqlx::query("DELETE FROM analysis_results WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM analysis_dimension_scores WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // 删除车机响应
+        sqlx::query("DELETE FROM machine_responses WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // 删除任务统计
+        sqlx::query("DELETE FROM task_statistics WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("DELETE FROM sample_run_states WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // 删除工作流执行记录
+        sqlx::query(
+            "DELETE FROM workflow_task_traces WHERE run_id IN (SELECT id FROM workflow_runs WHERE task_id = ?)",
+        )
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM workflow_runs WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // 删除队列中的任务和定时计划
+        sqlx::query("DELETE FROM job_queue WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM task_schedules WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+
+        // 删除任务本身
+        sqlx::query("DELETE FROM tasks WHERE id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn update_task_status(&self, task_id: i64, status: &str) -> Result<()> {
+        sqlx::query("UPDATE tasks SET task_status = ? WHERE id = ?")
+            .bind(status)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 写入任务级别的识别指标汇总（字错误率、词/句准确率及增删替换错误数）
+    pub async fn update_task_asr_metrics(&self, task_id: i64, summary: &AsrMetricsSummary) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE tasks SET
+                sentence_accuracy = ?, word_accuracy = ?, character_error_rate = ?,
+                total_words = ?, insertion_errors = ?, deletion_errors = ?, substitution_errors = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(summary.sentence_accuracy())
+        .bind(summary.word_accuracy())
+        .bind(summary.character_error_rate())
+        .bind(summary.total_chars as i64)
+        .bind(summary.char_insertions as i64)
+        .bind(summary.char_deletions as i64)
+        .bind(summary.char_substitutions as i64)
+        .bind(task_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 重新开始测试时写入新的运行计划，之前的运行状态全部清除
+    pub async fn reset_sample_run_states(&self, task_id: i64, keys: &[SampleRunKey]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM sample_run_states WHERE task_id = ?")
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+        Self::insert_sample_run_states(&mut tx, keys, false).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 恢复测试时补充计划中新增的运行（例如中断后又添加了样本），已有的状态保持不变
+    pub async fn add_missing_sample_run_states(&self, keys: &[SampleRunKey]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        Self::insert_sample_run_states(&mut tx, keys, true).await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    async fn insert_sample_run_states(
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        keys: &[SampleRunKey],
+        keep_existing: bool,
+    ) -> Result<()> {
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let sql = if keep_existing {
+            "INSERT OR IGNORE INTO sample_run_states (task_id, sample_id, wake_word_id, run_index, run_order, status, updated_at) VALUES (?, ?, ?, ?, ?, 'pending', ?)"
+        } else {
+            "INSERT INTO sample_run_states (task_id, sample_id, wake_word_id, run_index, run_order, status, updated_at) VALUES (?, ?, ?, ?, ?, 'pending', ?)"
+        };
+        for (order, key) in keys.iter().enumerate() {
+            sqlx::query(sql)
+                .bind(key.task_id)
+                .bind(key.sample_id)
+                .bind(key.wake_word_id)
+                .bind(key.run_index)
+                .bind(order as i64)
+                .bind(&now)
+                .execute(&mut **tx)
+                .await?;
+        }
+        Ok(())
+    }
+
+    pub async fn set_sample_run_status(
+        &self,
+        key: &SampleRunKey,
+        status: SampleRunStatus,
+        error: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE sample_run_states SET status = ?, error = ?, updated_at = ?
+            WHERE task_id = ? AND sample_id = ? AND wake_word_id = ? AND run_index = ?
+            "#,
+        )
+        .bind(status.as_str())
+        .bind(error)
+        .bind(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .bind(key.task_id)
+        .bind(key.sample_id)
+        .bind(key.wake_word_id)
+        .bind(key.run_index)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list_sample_run_states(&self, task_id: i64) -> Result<Vec<SampleRunState>> {
+        let rows = sqlx::query_as::<_, SampleRunStateRow>(
+            "SELECT * FROM sample_run_states WHERE task_id = ? ORDER BY run_order",
+        )
+        .bind(task_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SampleRunState {
+                task_id: row.task_id,
+                sample_id: row.sample_id,
+                wake_word_id: row.wake_word_id,
+                run_index: row.run_index,
+                run_order: row.run_order,
+                status: SampleRunStatus::parse(&row.status),
+                error: row.error,
+                updated_at: row.updated_at,
+            })
+            .collect())
+    }
+
+    /// 将任务加入测试队列末尾，返回队列项ID
+    pub async fn enqueue_job(&self, task_id: i64, spec: &JobSpec, priority: i64) -> Result<i64> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO job_queue (task_id, spec, priority, position, status, created_at)
+            VALUES (?, ?, ?, (SELECT COALESCE(MAX(position), 0) + 1 FROM job_queue), 'queued', ?)
+            "#,
+        )
+        .bind(task_id)
+        .bind(serde_json::to_string(spec)?)
+        .bind(priority)
+        .bind(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    /// 按执行顺序列出队列中的全部任务（含已结束的）
+    pub async fn list_jobs(&self) -> Result<Vec<QueuedJob>> {
+        let rows = sqlx::query_as::<_, QueuedJobRow>(
+            "SELECT * FROM job_queue ORDER BY priority DESC, position, id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter().map(Self::queued_job_from_row).collect()
+    }
+
+    pub async fn get_job(&self, job_id: i64) -> Result<Option<QueuedJob>> {
+        let row = sqlx::query_as::<_, QueuedJobRow>("SELECT * FROM job_queue WHERE id = ?")
+            .bind(job_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(Self::queued_job_from_row).transpose()
+    }
+
+    /// 下一个要执行的排队任务
+    pub async fn next_queued_job(&self) -> Result<Option<QueuedJob>> {
+        let row = sqlx::query_as::<_, QueuedJobRow>(
+            "SELECT * FROM job_queue WHERE status = 'queued' ORDER BY priority DESC, position, id LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(Self::queued_job_from_row).transpose()
+    }
+
+    /// 任务当前处于 `from` 中的某个状态时改为 `to`，返回是否更新。
+    /// 开始执行时记录首次开始时间，结束时记录结束时间
+    pub async fn transition_job(
+        &self,
+        job_id: i64,
+        from: &[JobStatus],
+        to: JobStatus,
+        error: Option<&str>,
+    ) -> Result<bool> {
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let finished = matches!(to, JobStatus::Done | JobStatus::Failed | JobStatus::Cancelled);
+        let placeholders = vec!["?"; from.len()].join(", ");
+        let sql = format!(
+            r#"
+            UPDATE job_queue SET status = ?, error = ?,
+                started_at = CASE WHEN ? THEN COALESCE(started_at, ?) ELSE started_at END,
+                finished_at = CASE WHEN ? THEN ? ELSE NULL END
+            WHERE id = ? AND status IN ({})
+            "#,
+            placeholders
+        );
+        let mut query = sqlx::query(&sql)
+            .bind(to.as_str())
+            .bind(error)
+            .bind(to == JobStatus::Running)
+            .bind(&now)
+            .bind(finished)
+            .bind(&now)
+            .bind(job_id);
+        for status in from {
+            query = query.bind(status.as_str());
+        }
+        Ok(query.execute(&self.pool).await?.rows_affected() > 0)
+    }
+
+    pub async fn update_job_priority(&self, job_id: i64, priority: i64) -> Result<()> {
+        sqlx::query("UPDATE job_queue SET priority = ? WHERE id = ?")
+            .bind(priority)
+            .bind(job_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 按给定顺序重新排列队列项：这些项原有的位置按新顺序重新分配，其他项的位置不变
+    pub async fn reorder_jobs(&self, job_ids: &[i64]) -> Result<()> {
+        if job_ids.is_empty() {
+            return Ok(());
+        }
+        let mut tx = self.pool.begin().await?;
+        let sql = format!(
+            "SELECT position FROM job_queue WHERE id IN ({}) ORDER BY position",
+            vec!["?"; job_ids.len()].join(", ")
+        );
+        let mut query = sqlx::query_scalar::<_, i64>(&sql);
+        for job_id in job_ids {
+            query = query.bind(job_id);
+        }
+        let positions = query.fetch_all(&mut *tx).await?;
+        for (job_id, position) in job_ids.iter().zip(positions) {
+            sqlx::query("UPDATE job_queue SET position = ? WHERE id = ?")
+                .bind(position)
+                .bind(job_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 程序退出时仍在执行的任务重新排队，返回更新的数量
+    pub async fn requeue_running_jobs(&self) -> Result<u64> {
+        let result = sqlx::query("UPDATE job_queue SET status = 'queued' WHERE status = 'running'")
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+
+    fn queued_job_from_row(row: QueuedJobRow) -> Result<QueuedJob> {
+        Ok(QueuedJob {
+            id: row.id,
+            task_id: row.task_id,
+            spec: serde_json::from_str(&row.spec)?,
+            priority: row.priority,
+            position: row.position,
+            status: JobStatus::parse(&row.status),
+            error: row.error,
+            created_at: row.created_at,
+            started_at: row.started_at,
+            finished_at: row.finished_at,
+        })
+    }
+
+    /// 复制任务的配置（样本、唤醒词、评估和流水线设置）创建新任务，不复制测试结果，返回新任务ID
+    pub async fn duplicate_task(&self, task_id: i64, name: &str) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            r#"
+            INSERT INTO tasks (name, task_status, task_progress, created_at, audio_type, audio_file, audio_duration,
+                               audio_category, test_collection, llm_settings, wake_word_strategy,
+                               prompt_template_id, pipeline, rubric_id)
+            SELECT ?, 'pending', 0.0, ?, audio_type, audio_file, audio_duration,
+                   audio_category, test_collection, llm_settings, wake_word_strategy,
+                   prompt_template_id, pipeline, rubric_id
+            FROM tasks WHERE id = ?
+            "#,
+        )
+        .bind(name)
+        .bind(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .bind(task_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("任务 {} 不存在", task_id));
+        }
+        let new_task_id = result.last_insert_rowid();
+
+        sqlx::query("INSERT INTO task_samples (task_id, sample_id) SELECT ?, sample_id FROM task_samples WHERE task_id = ?")
+            .bind(new_task_id)
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO task_wake_words (task_id, wake_word_id) SELECT ?, wake_word_id FROM task_wake_words WHERE task_id = ?")
+            .bind(new_task_id)
+            .bind(task_id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(new_task_id)
+    }
+
+    pub async fn create_schedule(
+        &self,
+        task_id: i64,
+        rule: &ScheduleRule,
+        spec: &JobSpec,
+        priority: i64,
+        missed_run_policy: MissedRunPolicy,
+        next_run_at: Option<&str>,
+    ) -> Result<i64> {
+        let id = sqlx::query(
+            r#"
+            INSERT INTO task_schedules (task_id, rule, spec, priority, missed_run_policy, enabled, next_run_at, created_at)
+            VALUES (?, ?, ?, ?, ?, 1, ?, ?)
+            "#,
+        )
+        .bind(task_id)
+        .bind(serde_json::to_string(rule)?)
+        .bind(serde_json::to_string(spec)?)
+        .bind(priority)
+        .bind(missed_run_policy.as_str())
+        .bind(next_run_at)
+        .bind(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string())
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+        Ok(id)
+    }
+
+    pub async fn list_schedules(&self) -> Result<Vec<TaskSchedule>> {
+        let rows = sqlx::query_as::<_, TaskScheduleRow>("SELECT * FROM task_schedules ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.into_iter().map(Self::schedule_from_row).collect()
+    }
+
+    pub async fn get_schedule(&self, schedule_id: i64) -> Result<Option<TaskSchedule>> {
+        let row = sqlx::query_as::<_, TaskScheduleRow>("SELECT * FROM task_schedules WHERE id = ?")
+            .bind(schedule_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        row.map(Self::schedule_from_row).transpose()
+    }
+
+    /// 启用或停用计划，同时更新下一次执行时间
+    pub async fn set_schedule_enabled(&self, schedule_id: i64, enabled: bool, next_run_at: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE task_schedules SET enabled = ?, next_run_at = ? WHERE id = ?")
+            .bind(enabled)
+            .bind(next_run_at)
+            .bind(schedule_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 记录一次到点处理：`run` 为本次加入队列的时间和创建的任务（被跳过时为 None），并更新下一次执行时间
+    pub async fn record_schedule_run(
+        &self,
+        schedule_id: i64,
+        run: Option<(&str, i64)>,
+        next_run_at: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            UPDATE task_schedules SET next_run_at = ?,
+                last_run_at = COALESCE(?, last_run_at),
+                last_task_id = COALESCE(?, last_task_id)
+            WHERE id = ?
+            "#,
+        )
+        .bind(next_run_at)
+        .bind(run.map(|(run_at, _)| run_at))
+        .bind(run.map(|(_, task_id)| task_id))
+        .bind(schedule_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_schedule(&self, schedule_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM task_schedules WHERE id = ?")
+            .bind(schedule_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn schedule_from_row(row: TaskScheduleRow) -> Result<TaskSchedule> {
+        Ok(TaskSchedule {
+            id: row.id,
+            task_id: row.task_id,
+            rule: serde_json::from_str(&row.rule)?,
+            spec: serde_json::from_str(&row.spec)?,
+            priority: row.priority,
+            missed_run_policy: MissedRunPolicy::parse(&row.missed_run_policy),
+            enabled: row.enabled,
+            last_run_at: row.last_run_at,
+            next_run_at: row.next_run_at,
+            last_task_id: row.last_task_id,
+            created_at: row.created_at,
+        })
+    }
+
+    /// 保存一次子工作流运行及其任务节点的执行记录，返回运行记录ID（`run.id` 被忽略）
+    pub async fn save_workflow_run(&self, run: &WorkflowRunRecord) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+        let run_id = sqlx::query(
+            r#"
+            INSERT INTO workflow_runs (task_id, sample_id, wake_word_id, run_index, pipeline, status, error, started_at, finished_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(run.task_id)
+        .bind(run.sample_id)
+        .bind(run.wake_word_id)
+        .bind(run.run_index)
+        .bind(&run.pipeline)
+        .bind(&run.status)
+        .bind(&run.error)
+        .bind(&run.started_at)
+        .bind(&run.finished_at)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        for task in &run.tasks {
+            sqlx::query(
+                r#"
+                INSERT INTO workflow_task_traces (run_id, node_id, status, started_at, finished_at, duration_ms, attempts, message, outputs)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(run_id)
+            .bind(&task.node_id)
+            .bind(&task.status)
+            .bind(&task.started_at)
+            .bind(&task.finished_at)
+            .bind(task.duration_ms)
+            .bind(task.attempts)
+            .bind(&task.message)
+            .bind(task.outputs.to_string())
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(run_id)
+    }
+
+    /// 按运行顺序列出任务的执行记录，`sample_id` 不为空时只返回该样本的运行
+    pub async fn list_workflow_runs(&self, task_id: i64, sample_id: Option<i64>) -> Result<Vec<WorkflowRunRecord>> {
+        let runs = sqlx::query_as::<_, WorkflowRunRow>(
+            "SELECT * FROM workflow_runs WHERE task_id = ? AND (? IS NULL OR sample_id = ?) ORDER BY id",
+        )
+        .bind(task_id)
+        .bind(sample_id)
+        .bind(sample_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut records = Vec::with_capacity(runs.len());
+        for run in runs {
+            let traces = sqlx::query_as::<_, WorkflowTaskTraceRow>(
+                "SELECT * FROM workflow_task_traces WHERE run_id = ? ORDER BY id",
+            )
+            .bind(run.id)
+            .fetch_all(&self.pool)
+            .await?;
+
+            records.push(WorkflowRunRecord {
+                id: run.id,
+                task_id: run.task_id,
+                sample_id: run.sample_id,
+                wake_word_id: run.wake_word_id,
+                run_index: run.run_index,
+                pipeline: run.pipeline,
+                status: run.status,
+                error: run.error,
+                started_at: run.started_at,
+                finished_at: run.finished_at,
+                tasks: traces
+                    .into_iter()
+                    .map(|row| WorkflowTaskRecord {
+                        node_id: row.node_id,
+                        status: row.status,
+                        started_at: row.started_at,
+                        finished_at: row.finished_at,
+                        duration_ms: row.duration_ms,
+                        attempts: row.attempts,
+                        message: row.message,
+                        outputs: serde_json::from_str(&row.outputs).unwrap_or(serde_json::Value::Null),
+                    })
+                    .collect(),
+            });
+        }
+        Ok(records)
+    }
+
+    /// 启动时检测上次退出时仍在运行的任务：有运行中的样本，或任务处于进行中且还有未执行的样本。
+    /// 这些任务标记为 interrupted，运行中的样本恢复为 pending，以便 `resume_task` 继续执行。
+    /// 只在桌面应用启动时调用：无界面运行器与桌面应用共用数据库，调用会把桌面应用中正在运行的任务标记为中断。
+    /// 返回被标记的任务ID
+    pub async fn recover_interrupted_runs(&self) -> Result<Vec<i64>> {
+        let mut tx = self.pool.begin().await?;
+        let task_ids: Vec<i64> = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT s.task_id FROM sample_run_states s
+            JOIN tasks t ON t.id = s.task_id
+            WHERE s.status = 'running' OR (t.task_status = 'in_progress' AND s.status = 'pending')
+            ORDER BY s.task_id
+            "#,
+        )
+        .fetch_all(&mut *tx)
+        .await?;
+
+        for task_id in &task_ids {
+            sqlx::query("UPDATE sample_run_states SET status = 'pending' WHERE task_id = ? AND status = 'running'")
+                .bind(task_id)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("UPDATE tasks SET task_status = 'interrupted' WHERE id = ?")
+                .bind(task_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(task_ids)
+    }
+
+    pub async fn get_task_statistics(&self, task_id: i64) -> Result<Option<TaskStatistics>> {
+        let stats = sqlx::query_as::<_, TaskStatistics>("SELECT * FROM task_statistics WHERE task_id = ?")
+            .bind(task_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(stats)
+    }
+
+    /// 保存任务统计，同时回写 tasks 表中对应的汇总列
+    pub async fn save_task_statistics(&self, stats: &TaskStatistics) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO task_statistics (
+                task_id, total_samples, completed_samples, passed_samples, evaluation_errors,
+                pass_rate, average_score, recognition_success_rate,
+                wake_attempts, wake_successes, wake_rate,
+                recognition_min_ms, recognition_max_ms, recognition_avg_ms,
+                recognition_p50_ms, recognition_p90_ms, recognition_p99_ms,
+                response_avg_ms, response_p50_ms, response_p90_ms, response_p99_ms,
+                updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(stats.task_id)
+        .bind(stats.total_samples)
+        .bind(stats.completed_samples)
+        .bind(stats.passed_samples)
+        .bind(stats.evaluation_errors)
+        .bind(stats.pass_rate)
+        .bind(stats.average_score)
+        .bind(stats.recognition_success_rate)
+        .bind(stats.wake_attempts)
+        .bind(stats.wake_successes)
+        .bind(stats.wake_rate)
+        .bind(stats.recognition_min_ms)
+        .bind(stats.recognition_max_ms)
+        .bind(stats.recognition_avg_ms)
+        .bind(stats.recognition_p50_ms)
+        .bind(stats.recognition_p90_ms)
+        .bind(stats.recognition_p99_ms)
+        .bind(stats.response_avg_ms)
+        .bind(stats.response_p50_ms)
+        .bind(stats.response_p90_ms)
+        .bind(stats.response_p99_ms)
+        .bind(&stats.updated_at)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query(
+            r#"
+            UPDATE tasks SET
+                fastest_recognition_time = ?, slowest_recognition_time = ?, average_recognition_time = ?,
+                recognition_success_rate = ?, completed_samples = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(stats.recognition_min_ms)
+        .bind(stats.recognition_max_ms)
+        .bind(stats.recognition_avg_ms)
+        .bind(stats.recognition_success_rate)
+        .bind(stats.completed_samples)
+        .bind(stats.task_id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 获取任务的大模型覆盖参数，未设置时返回默认值
+    pub async fn get_task_llm_settings(&self, task_id: i64) -> Result<LlmOverrides> {
+        let raw: Option<Option<String>> =
+            sqlx::query_scalar("SELECT llm_settings FROM tasks WHERE id = ?")
+                .bind(task_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        match raw.flatten() {
+            Some(json) if !json.is_empty() => Ok(serde_json::from_str(&json)?),
+            _ => Ok(LlmOverrides::default()),
+        }
+    }
+
+    pub async fn update_task_llm_settings(&self, task_id: i64, settings: &LlmOverrides) -> Result<()> {
+        sqlx::query("UPDATE tasks SET llm_settings = ? WHERE id = ?")
+            .bind(serde_json::to_string(settings)?)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_task_wake_word_strategy(&self, task_id: i64) -> Result<WakeWordStrategy> {
+        let raw: Option<Option<String>> =
+            sqlx::query_scalar("SELECT wake_word_strategy FROM tasks WHERE id = ?")
+                .bind(task_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        match raw.flatten() {
+            Some(json) if !json.is_empty() => Ok(serde_json::from_str(&json)?),
+            _ => Ok(WakeWordStrategy::default()),
+        }
+    }
+
+    pub async fn update_task_wake_word_strategy(&self, task_id: i64, strategy: &WakeWordStrategy) -> Result<()> {
+        sqlx::query("UPDATE tasks SET wake_word_strategy = ? WHERE id = ?")
+            .bind(serde_json::to_string(strategy)?)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 任务选择的流水线名称，None 表示使用默认流水线
+    pub async fn get_task_pipeline(&self, task_id: i64) -> Result<Option<String>> {
+        let name: Option<Option<String>> =
+            sqlx::query_scalar("SELECT pipeline FROM tasks WHERE id = ?")
+                .bind(task_id)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(name.flatten().filter(|n| !n.is_empty()))
+    }
+
+    pub async fn update_task_pipeline(&self, task_id: i64, pipeline: Option<&str>) -> Result<()> {
+        sqlx::query("UPDATE tasks SET pipeline = ? WHERE id = ?")
+            .bind(pipeline)
+            .bind(task_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // 提示词模板相关操作
+    pub async fn list_prompt_templates(&self) -> Result<Vec<PromptTemplate>> {
+        let templates = sqlx::query_as::<_, PromptTemplate>(
+            "SELECT * FROM prompt_templates ORDER BY name, version DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(templates)
+    }
+
+    pub async fn get_prompt_template_by_id(&self, template_id: i64) -> Result<Option<PromptTemplate>> {
+        let template = sqlx::query_as::<_, PromptTemplate>("SELECT * FROM prompt_templates WHERE id = ?")
+            .bind(template_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(template)
+    }
+
+    pub async fn get_latest_prompt_template(&self, name: &str) -> Result<Option<PromptTemplate>> {
+        let template = sqlx::query_as::<_, PromptTemplate>(
+            "SELECT * FROM prompt_templates WHERE name = ? ORDER BY version DESC LIMIT 1",
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(template)
+    }
+
+    /// 保存模板的新版本，旧版本保留以便追溯历史结果
+    pub async fn create_prompt_template_version(
+        &self,
+        name: &str,
+        content: &str,
+        description: Option<&str>,
+    ) -> Result<PromptTemplate> {
+        let mut tx = self.pool.begin().await?;
+
+        let current_version: Option<i64> =
+            sqlx::query_scalar("SELECT MAX(version) FROM prompt_templates WHERE name = ?")
+                .bind(name)
+                .fetch_one(&mut *tx)
+                .await?;
+        let version = current_version.unwrap_or(0) + 1;
+        let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+
+        let result = sqlx::query(
+            "INSERT INTO prompt_templates (name, version, content, description, created_at) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(name)
+        .bind(version)
+        .bind(content)
+        .bind(description)
+        .bind(&now)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        log::info!("[DB_SERVICE] Created prompt template '{}' v{}", name, version);
+        Ok(PromptTemplate {
+            id: result.last_insert_rowid(),
+            name: name.to_string(),
+            version,
+            content: content.to_string(),
+            description: description.map(|d| d.to_string()),
+            created_at: now,
+        })
     }
 
-    pub async fn get_task_by_id(&self, task_id: i64) -> Result<Option<Task>> {
-        // 获取任务基本信息
-        let task_row = sqlx::query_as::<_, TaskRow>("SELECT * FROM tasks WHERE id = ?")
+    /// 设置任务使用的模板版本，None 表示使用默认模板的最新版本
+    pub async fn set_task_prompt_template(&self, task_id: i64, template_id: Option<i64>) -> Result<()> {
+        sqlx::query("UPDATE tasks SET prompt_template_id = ? WHERE id = ?")
+            .bind(template_id)
             .bind(task_id)
-            .fetch_optional(&self.pool)
+            .execute(&self.pool)
             .await?;
+        Ok(())
+    }
 
-        let Some(task_row) = task_row else {
-            return Ok(None);
-        };
+    /// 获取任务实际使用的模板
+    pub async fn get_task_prompt_template(&self, task_id: i64) -> Result<PromptTemplate> {
+        let template_id: Option<Option<i64>> =
+            sqlx::query_scalar("SELECT prompt_template_id FROM tasks WHERE id = ?")
+                .bind(task_id)
+                .fetch_optional(&self.pool)
+                .await?;
 
-        // 获取关联的样本ID
-        let sample_rows = sqlx::query("SELECT sample_id FROM task_samples WHERE task_id = ?")
-            .bind(task_id)
-            .fetch_all(&self.pool)
-            .await?;
+        if let Some(template_id) = template_id.flatten() {
+            if let Some(template) = self.get_prompt_template_by_id(template_id).await? {
+                return Ok(template);
+            }
+            log::warn!(
+                "[DB_SERVICE] Prompt template {} for task {} not found, falling back to default",
+                template_id,
+                task_id
+            );
+        }
 
-        let sample_ids: Vec<u32> = sample_rows
-            .into_iter()
-            .map(|row| row.get::<i64, _>("sample_id") as u32)
-            .collect();
+        self.get_latest_prompt_template("default")
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("默认提示词模板不存在"))
+    }
 
-        // 获取关联的唤醒词ID
-        let wake_word_rows = sqlx::query("SELECT wake_word_id FROM task_wake_words WHERE task_id = ?")
-            .bind(task_id)
-            .fetch_all(&self.pool)
-            .await?;
+    // 评分标准相关操作
+    async fn load_rubric(&self, row: RubricRow) -> Result<Rubric> {
+        let dimension_rows = sqlx::query_as::<_, RubricDimensionRow>(
+            "SELECT rubric_id, dimension_key, name, description, weight FROM rubric_dimensions WHERE rubric_id = ? ORDER BY position, id",
+        )
+        .bind(row.id)
+        .fetch_all(&self.pool)
+        .await?;
 
-        let wake_word_ids: Vec<u32> = wake_word_rows
-            .into_iter()
-            .map(|row| row.get::<i64, _>("wake_word_id") as u32)
-            .collect();
+        Ok(Rubric {
+            id: row.id,
+            name: row.name,
+            description: row.description,
+            pass_threshold: row.pass_threshold,
+            dimensions: dimension_rows
+                .into_iter()
+                .map(|d| RubricDimension {
+                    key: d.dimension_key,
+                    name: d.name,
+                    description: d.description.unwrap_or_default(),
+                    weight: d.weight,
+                })
+                .collect(),
+            created_at: row.created_at,
+        })
+    }
 
-        // 获取车机响应
-        let machine_responses = self.get_machine_responses_by_task(task_id).await?;
+    pub async fn list_rubrics(&self) -> Result<Vec<Rubric>> {
+        let rows = sqlx::query_as::<_, RubricRow>("SELECT * FROM rubrics ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
 
-        // 获取测试结果
-        let test_results = self.get_analysis_results_by_task(task_id).await?;
+        let mut rubrics = Vec::new();
+        for row in rows {
+            rubrics.push(self.load_rubric(row).await?);
+        }
+        Ok(rubrics)
+    }
 
-        Ok(Some(Task {
-            id: task_row.id as u32,
-            name: task_row.name,
-            test_samples_ids: sample_ids,
-            wake_word_ids: wake_word_ids,
-            machine_response: if machine_responses.is_empty() {
-                None
-            } else {
-                Some(machine_responses)
-            },
-            test_result: if test_results.is_empty() {
-                None
-            } else {
-                Some(test_results)
-            },
-            task_status: task_row.task_status,
-            task_progress: task_row.task_progress.map(|p| p as f32),
-            created_at: task_row.created_at,
-            audio_type: task_row.audio_type,
-            audio_file: task_row.audio_file,
-            audio_duration: task_row.audio_duration,
-            audio_category: task_row.audio_category,
-            test_collection: task_row.test_collection,
-            test_duration: task_row.test_duration,
-            sentence_accuracy: task_row.sentence_accuracy.map(|v| v as f32),
-            word_accuracy: task_row.word_accuracy.map(|v| v as f32),
-            character_error_rate: task_row.character_error_rate.map(|v| v as f32),
-            recognition_success_rate: task_row.recognition_success_rate.map(|v| v as f32),
-            total_words: task_row.total_words.map(|v| v as u32),
-            insertion_errors: task_row.insertion_errors.map(|v| v as u32),
-            deletion_errors: task_row.deletion_errors.map(|v| v as u32),
-            substitution_errors: task_row.substitution_errors.map(|v| v as u32),
-            fastest_recognition_time: task_row.fastest_recognition_time.map(|v| v as f32),
-            slowest_recognition_time: task_row.slowest_recognition_time.map(|v| v as f32),
-            average_recognition_time: task_row.average_recognition_time.map(|v| v as f32),
-            completed_samples: task_row.completed_samples.map(|v| v as u32),
-        }))
+    pub async fn get_rubric_by_id(&self, rubric_id: i64) -> Result<Option<Rubric>> {
+        let row = sqlx::query_as::<_, RubricRow>("SELECT * FROM rubrics WHERE id = ?")
+            .bind(rubric_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        match row {
+            Some(row) => Ok(Some(self.load_rubric(row).await?)),
+            None => Ok(None),
+        }
     }
 
-    pub async fn get_all_tasks(&self) -> Result<Vec<Task>> {
-        let task_rows =
-            sqlx::query_as::<_, TaskRow>("SELECT * FROM tasks ORDER BY created_at DESC")
-                .fetch_all(&self.pool)
+    /// 新建（id 为 0）或更新评分标准，维度整体替换
+    pub async fn save_rubric(&self, rubric: &Rubric) -> Result<i64> {
+        let mut tx = self.pool.begin().await?;
+
+        let rubric_id = if rubric.id > 0 {
+            sqlx::query("UPDATE rubrics SET name = ?, description = ?, pass_threshold = ? WHERE id = ?")
+                .bind(&rubric.name)
+                .bind(&rubric.description)
+                .bind(rubric.pass_threshold)
+                .bind(rubric.id)
+                .execute(&mut *tx)
                 .await?;
+            sqlx::query("DELETE FROM rubric_dimensions WHERE rubric_id = ?")
+                .bind(rubric.id)
+                .execute(&mut *tx)
+                .await?;
+            rubric.id
+        } else {
+            let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            sqlx::query("INSERT INTO rubrics (name, description, pass_threshold, created_at) VALUES (?, ?, ?, ?)")
+                .bind(&rubric.name)
+                .bind(&rubric.description)
+                .bind(rubric.pass_threshold)
+                .bind(now)
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid()
+        };
 
-        let mut tasks = Vec::new();
-        for task_row in task_rows {
-            if let Some(task) = self.get_task_by_id(task_row.id).await? {
-                tasks.push(task);
-            }
+        for (position, dimension) in rubric.dimensions.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO rubric_dimensions (rubric_id, dimension_key, name, description, weight, position) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(rubric_id)
+            .bind(&dimension.key)
+            .bind(&dimension.name)
+            .bind(&dimension.description)
+            .bind(dimension.weight)
+            .bind(position as i64)
+            .execute(&mut *tx)
+            .await?;
         }
 
-        Ok(tasks)
+        tx.commit().await?;
+        Ok(rubric_id)
     }
 
-    pub async fn delete_task(&self, task_id: i64) -> Result<()> {
+    pub async fn delete_rubric(&self, rubric_id: i64) -> Result<()> {
         let mut tx = self.pool.begin().await?;
-
-        // 删除任务-样本关联
-        sqlx::query("DELETE FROM task_samples WHERE task_id = ?")
-            .bind(task_id)
+        // 使用该评分标准的任务回退到默认评分标准
+        sqlx::query("UPDATE tasks SET rubric_id = NULL WHERE rubric_id = ?")
+            .bind(rubric_id)
             .execute(&mut *tx)
             .await?;
-
-        // 删除分析结果
-        sqlx::query("DELETE FROM analysis_results WHERE task_id = ?")
-            .bind(task_id)
+        sqlx::query("DELETE FROM rubric_dimensions WHERE rubric_id = ?")
+            .bind(rubric_id)
             .execute(&mut *tx)
             .await?;
-
-        // 删除车机响应
-        sqlx::query("DELETE FROM machine_responses WHERE task_id = ?")
-            .bind(task_id)
+        sqlx::query("DELETE FROM rubrics WHERE id = ?")
+            .bind(rubric_id)
             .execute(&mut *tx)
             .await?;
+        tx.commit().await?;
+        Ok(())
+    }
 
-        // 删除任务本身
-        sqlx::query("DELETE FROM tasks WHERE id = ?")
+    /// 设置任务使用的评分标准，None 表示使用默认评分标准
+    pub async fn set_task_rubric(&self, task_id: i64, rubric_id: Option<i64>) -> Result<()> {
+        sqlx::query("UPDATE tasks SET rubric_id = ? WHERE id = ?")
+            .bind(rubric_id)
             .bind(task_id)
-            .execute(&mut *tx)
+            .execute(&self.pool)
             .await?;
-
-        tx.commit().await?;
         Ok(())
     }
 
-    pub async fn update_task_status(&self, task_id: i64, status: &str) -> Result<()> {
-        sqlx::query("UPDATE tasks SET task_status = ? WHERE id = ?")
-            .bind(status)
+    /// 获取任务实际使用的评分标准
+    pub async fn get_task_rubric(&self, task_id: i64) -> Result<Rubric> {
+        let rubric_id: Option<Option<i64>> = sqlx::query_scalar("SELECT rubric_id FROM tasks WHERE id = ?")
             .bind(task_id)
-            .execute(&self.pool)
+            .fetch_optional(&self.pool)
             .await?;
 
-        Ok(())
+        if let Some(rubric_id) = rubric_id.flatten() {
+            if let Some(rubric) = self.get_rubric_by_id(rubric_id).await? {
+                return Ok(rubric);
+            }
+            log::warn!(
+                "[DB_SERVICE] Rubric {} for task {} not found, falling back to default",
+                rubric_id,
+                task_id
+            );
+        }
+
+        let row = sqlx::query_as::<_, RubricRow>("SELECT * FROM rubrics WHERE name = 'default'")
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("默认评分标准不存在"))?;
+        self.load_rubric(row).await
     }
 
     pub async fn update_task_progress(&self, task_id: i64, progress: f64) -> Result<()> {
@@ -686,6 +2061,18 @@ impl DatabaseService {
                 );
                 anyhow::Error::new(e).context("Failed to delete from analysis_results")
             })?;
+        sqlx::query("DELETE FROM analysis_dimension_scores WHERE sample_id = ?")
+            .bind(sample_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| {
+                log::error!(
+                    "[DB_SERVICE] Failed to delete from analysis_dimension_scores for sample {}: {}",
+                    sample_id,
+                    e
+                );
+                anyhow::Error::new(e).context("Failed to delete from analysis_dimension_scores")
+            })?;
         log::debug!(
             "[DB_SERVICE] Successfully deleted from analysis_results for sample_id: {}",
             sample_id
@@ -906,6 +2293,19 @@ impl DatabaseService {
         Ok(())
     }
 
+    /// 设置样本在测试中的重复执行次数（至少1次）
+    pub async fn update_sample_repeats(&self, sample_id: i64, repeats: u32) -> Result<()> {
+        let result = sqlx::query("UPDATE test_samples SET repeats = ? WHERE id = ?")
+            .bind(repeats.max(1) as i64)
+            .bind(sample_id)
+            .execute(&self.pool)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("样本 {} 不存在", sample_id));
+        }
+        Ok(())
+    }
+
     // 唤醒词相关操作
     pub async fn get_all_wake_words(&self) -> Result<Vec<WakeWord>> {
         let rows = sqlx::query_as::<_, WakeWordRow>("SELECT * FROM wake_words ORDER BY id")
@@ -1141,33 +2541,28 @@ impl DatabaseService {
     // 分析结果相关操作
     pub async fn save_analysis_result(
         &self,
-        task_id: i64,
-        sample_id: i64,
+        key: &SampleRunKey,
         result: &AnalysisResult,
     ) -> Result<()> {
         let suggestions_json = serde_json::to_string(&result.assessment.suggestions)?;
         let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
 
+        let mut tx = self.pool.begin().await?;
+
         sqlx::query(
             r#"
             INSERT OR REPLACE INTO analysis_results (
-                task_id, sample_id, semantic_correctness_score, semantic_correctness_comment,
-                state_change_score, state_change_comment, unambiguous_score, unambiguous_comment,
-                overall_score, is_valid, suggestions, llm_title, llm_content, llm_context, llm_multi_round,
+                task_id, sample_id, overall_score, is_valid, suggestions,
+                llm_title, llm_content, llm_context, llm_multi_round,
                 test_time, audio_file, recognition_file, device, recognition_result,
                 insertion_errors, deletion_errors, substitution_errors, total_words,
-                reference_text, recognized_text, result_status, recognition_time, response_time, created_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                reference_text, recognized_text, result_status, recognition_time, response_time, created_at,
+                prompt_template_id, prompt_template_version, rubric_id, wake_word_id, run_index
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind(task_id)
-        .bind(sample_id)
-        .bind(result.assessment.semantic_correctness.score)
-        .bind(&result.assessment.semantic_correctness.comment)
-        .bind(result.assessment.state_change_confirmation.score)
-        .bind(&result.assessment.state_change_confirmation.comment)
-        .bind(result.assessment.unambiguous_expression.score)
-        .bind(&result.assessment.unambiguous_expression.comment)
+        .bind(key.task_id)
+        .bind(key.sample_id)
         .bind(result.assessment.overall_score)
         .bind(result.assessment.valid)
         .bind(suggestions_json)
@@ -1189,26 +2584,118 @@ impl DatabaseService {
         .bind(&result.result_status)
         .bind(result.recognition_time.map(|v| v as f64))
         .bind(result.response_time.map(|v| v as f64))
-        .bind(now)
-        .execute(&self.pool)
+        .bind(&now)
+        .bind(result.prompt_template_id)
+        .bind(result.prompt_template_version)
+        .bind(result.rubric_id)
+        .bind(key.wake_word_id)
+        .bind(key.run_index)
+        .execute(&mut *tx)
+        .await?;
+
+        // 维度得分整体替换，避免残留旧评分标准的维度
+        sqlx::query(
+            "DELETE FROM analysis_dimension_scores WHERE task_id = ? AND sample_id = ? AND wake_word_id = ? AND run_index = ?",
+        )
+        .bind(key.task_id)
+        .bind(key.sample_id)
+        .bind(key.wake_word_id)
+        .bind(key.run_index)
+        .execute(&mut *tx)
         .await?;
 
+        for (dimension_key, item) in &result.assessment.dimensions {
+            sqlx::query(
+                "INSERT INTO analysis_dimension_scores (task_id, sample_id, wake_word_id, run_index, dimension_key, score, comment, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(key.task_id)
+            .bind(key.sample_id)
+            .bind(key.wake_word_id)
+            .bind(key.run_index)
+            .bind(dimension_key)
+            .bind(item.score)
+            .bind(&item.comment)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 
+    /// 按样本汇总的分析结果，同一样本有多次运行时取最后保存的一次
     pub async fn get_analysis_results_by_task(
         &self,
         task_id: i64,
     ) -> Result<HashMap<u32, AnalysisResult>> {
+        Ok(self
+            .list_analysis_results_by_task(task_id)
+            .await?
+            .into_iter()
+            .map(|(key, result)| (key.sample_id as u32, result))
+            .collect())
+    }
+
+    /// 一次运行的评估结论（是否通过），尚无分析结果时返回 None
+    pub async fn get_analysis_verdict(&self, key: &SampleRunKey) -> Result<Option<bool>> {
+        let valid = sqlx::query_scalar::<_, bool>(
+            r#"
+            SELECT is_valid FROM analysis_results
+            WHERE task_id = ? AND sample_id = ? AND wake_word_id = ? AND run_index = ?
+            ORDER BY id DESC LIMIT 1
+            "#,
+        )
+        .bind(key.task_id)
+        .bind(key.sample_id)
+        .bind(key.wake_word_id)
+        .bind(key.run_index)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(valid)
+    }
+
+    /// 任务的全部分析结果，每次样本运行一条，按保存顺序排列
+    pub async fn list_analysis_results_by_task(
+        &self,
+        task_id: i64,
+    ) -> Result<Vec<(SampleRunKey, AnalysisResult)>> {
         let rows = sqlx::query_as::<_, AnalysisResultRow>(
-            "SELECT * FROM analysis_results WHERE task_id = ?",
+            "SELECT * FROM analysis_results WHERE task_id = ? ORDER BY id",
+        )
+        .bind(task_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let dimension_rows = sqlx::query_as::<_, AnalysisDimensionScoreRow>(
+            "SELECT sample_id, wake_word_id, run_index, dimension_key, score, comment FROM analysis_dimension_scores WHERE task_id = ?",
         )
         .bind(task_id)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut results = HashMap::new();
+        let mut dimensions_by_run: HashMap<(i64, i64, i64), BTreeMap<String, AssessmentItem>> = HashMap::new();
+        for row in dimension_rows {
+            dimensions_by_run
+                .entry((row.sample_id, row.wake_word_id, row.run_index))
+                .or_default()
+                .insert(
+                    row.dimension_key,
+                    AssessmentItem {
+                        score: row.score,
+                        comment: row.comment.unwrap_or_default(),
+                    },
+                );
+        }
+
+        let mut results = Vec::with_capacity(rows.len());
         for row in rows {
+            let key = SampleRunKey {
+                task_id,
+                sample_id: row.sample_id,
+                wake_word_id: row.wake_word_id,
+                run_index: row.run_index,
+            };
             let suggestions: Vec<String> = if let Some(suggestions_str) = &row.suggestions {
                 serde_json::from_str(suggestions_str).unwrap_or_default()
             } else {
@@ -1228,18 +2715,9 @@ impl DatabaseService {
 
             let analysis_result = AnalysisResult {
                 assessment: Assessment {
-                    semantic_correctness: AssessmentItem {
-                        score: row.semantic_correctness_score,
-                        comment: row.semantic_correctness_comment.unwrap_or_default(),
-                    },
-                    state_change_confirmation: AssessmentItem {
-                        score: row.state_change_score,
-                        comment: row.state_change_comment.unwrap_or_default(),
-                    },
-                    unambiguous_expression: AssessmentItem {
-                        score: row.unambiguous_score,
-                        comment: row.unambiguous_comment.unwrap_or_default(),
-                    },
+                    dimensions: dimensions_by_run
+                        .remove(&(row.sample_id, row.wake_word_id, row.run_index))
+                        .unwrap_or_default(),
                     overall_score: row.overall_score,
                     valid: row.is_valid,
                     suggestions,
@@ -1259,9 +2737,14 @@ impl DatabaseService {
                 result_status: row.result_status,
                 recognition_time: row.recognition_time.map(|v| v as f32),
                 response_time: row.response_time.map(|v| v as f32),
+                prompt_template_id: row.prompt_template_id,
+                prompt_template_version: row.prompt_template_version,
+                rubric_id: row.rubric_id,
+                wake_word_id: (row.wake_word_id > 0).then_some(row.wake_word_id as u32),
+                run_index: row.run_index as u32,
             };
 
-            results.insert(row.sample_id as u32, analysis_result);
+            results.push((key, analysis_result));
         }
 
         Ok(results)
@@ -1372,19 +2855,20 @@ impl DatabaseService {
     // 车机响应相关操作
     pub async fn save_machine_response(
         &self,
-        task_id: i64,
-        sample_id: i64,
+        key: &SampleRunKey,
         response: &MachineResponseData,
     ) -> Result<()> {
         let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
         sqlx::query(
             r#"
-            INSERT OR REPLACE INTO machine_responses (task_id, sample_id, text, connected, created_at)
-            VALUES (?, ?, ?, ?, ?)
+            INSERT OR REPLACE INTO machine_responses (task_id, sample_id, wake_word_id, run_index, text, connected, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind(task_id)
-        .bind(sample_id)
+        .bind(key.task_id)
+        .bind(key.sample_id)
+        .bind(key.wake_word_id)
+        .bind(key.run_index)
         .bind(&response.text)
         .bind(response.connected)
         .bind(now)
@@ -1394,36 +2878,53 @@ impl DatabaseService {
         Ok(())
     }
 
+    /// 按样本汇总的车机响应，同一样本有多次运行时取最后保存的一次
     pub async fn get_machine_responses_by_task(
         &self,
         task_id: i64,
     ) -> Result<HashMap<u32, MachineResponseData>> {
+        Ok(self
+            .list_machine_responses_by_task(task_id)
+            .await?
+            .into_iter()
+            .map(|(key, response)| (key.sample_id as u32, response))
+            .collect())
+    }
+
+    pub async fn list_machine_responses_by_task(
+        &self,
+        task_id: i64,
+    ) -> Result<Vec<(SampleRunKey, MachineResponseData)>> {
         let rows = sqlx::query_as::<_, MachineResponseRow>(
-            "SELECT * FROM machine_responses WHERE task_id = ?",
+            "SELECT * FROM machine_responses WHERE task_id = ? ORDER BY id",
         )
         .bind(task_id)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut responses = HashMap::new();
-        for row in rows {
-            responses.insert(
-                row.sample_id as u32,
-                MachineResponseData {
-                    text: row.text,
-                    connected: row.connected,
-                },
-            );
-        }
-
-        Ok(responses)
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    SampleRunKey {
+                        task_id: row.task_id,
+                        sample_id: row.sample_id,
+                        wake_word_id: row.wake_word_id,
+                        run_index: row.run_index,
+                    },
+                    MachineResponseData {
+                        text: row.text,
+                        connected: row.connected,
+                    },
+                )
+            })
+            .collect())
     }
 
     // 时间数据相关操作
     pub async fn save_timing_data(
         &self,
-        task_id: i64,
-        sample_id: i64,
+        key: &SampleRunKey,
         timing: &TimingData,
     ) -> Result<()> {
         let now = Utc::now().format("%Y-%m-%d %H:%M:%S").to_string();
@@ -1434,12 +2935,12 @@ impl DatabaseService {
                 task_id, sample_id, voice_command_start_time, first_char_appear_time,
                 voice_command_end_time, full_text_appear_time, action_start_time,
                 tts_first_frame_time, voice_recognition_time_ms, interaction_response_time_ms,
-                tts_response_time_ms, created_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                tts_response_time_ms, created_at, wake_word_id, run_index
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind(task_id)
-        .bind(sample_id)
+        .bind(key.task_id)
+        .bind(key.sample_id)
         .bind(timing.voice_command_start_time.map(|t| t.to_rfc3339()))
         .bind(timing.first_char_appear_time.map(|t| t.to_rfc3339()))
         .bind(timing.voice_command_end_time.map(|t| t.to_rfc3339()))
@@ -1450,19 +2951,36 @@ impl DatabaseService {
         .bind(timing.interaction_response_time_ms)
         .bind(timing.tts_response_time_ms)
         .bind(now)
+        .bind(key.wake_word_id)
+        .bind(key.run_index)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
+    /// 按样本汇总的时间参数，同一样本有多次运行时取最后保存的一次
     pub async fn get_timing_data_by_task(
         &self,
         task_id: i64,
     ) -> Result<HashMap<u32, TimingData>> {
+        Ok(self
+            .list_timing_data_by_task(task_id)
+            .await?
+            .into_iter()
+            .map(|(key, timing)| (key.sample_id as u32, timing))
+            .collect())
+    }
+
+    pub async fn list_timing_data_by_task(
+        &self,
+        task_id: i64,
+    ) -> Result<Vec<(SampleRunKey, TimingData)>> {
         #[derive(sqlx::FromRow)]
         struct TimingRow {
             sample_id: i64,
+            wake_word_id: i64,
+            run_index: i64,
             voice_command_start_time: Option<String>,
             first_char_appear_time: Option<String>,
             voice_command_end_time: Option<String>,
@@ -1478,6 +2996,8 @@ impl DatabaseService {
             r#"
             SELECT
                 sample_id,
+                wake_word_id,
+                run_index,
                 voice_command_start_time,
                 first_char_appear_time,
                 voice_command_end_time,
@@ -1489,6 +3009,7 @@ impl DatabaseService {
                 tts_response_time_ms
             FROM timing_data
             WHERE task_id = ?
+            ORDER BY id
             "#,
         )
         .bind(task_id)
@@ -1497,8 +3018,14 @@ impl DatabaseService {
 
         log::info!("[DB_SERVICE] Found {} timing data rows for task_id: {}", rows.len(), task_id);
 
-        let mut results = HashMap::new();
+        let mut results = Vec::with_capacity(rows.len());
         for row in rows {
+            let key = SampleRunKey {
+                task_id,
+                sample_id: row.sample_id,
+                wake_word_id: row.wake_word_id,
+                run_index: row.run_index,
+            };
             let mut timing = TimingData::new();
             
             // 更健壮的时间解析，记录解析错误但不中断处理
@@ -1585,7 +3112,7 @@ impl DatabaseService {
                 timing.tts_response_time_ms.unwrap_or(-1)
             );
             
-            results.insert(row.sample_id as u32, timing);
+            results.push((key, timing));
         }
 
         log::info!("[DB_SERVICE] Successfully processed {} timing data entries for task_id: {}", results.len(), task_id);
@@ -1649,3 +3176,96 @@ impl DatabaseService {
 
 
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 内存数据库，带 `count` 个空任务（队列项引用任务ID）
+    async fn test_db(count: usize) -> (DatabaseService, Vec<i64>) {
+        let db = DatabaseService::new("sqlite::memory:").await.unwrap();
+        let mut task_ids = Vec::new();
+        for i in 0..count {
+            let id = sqlx::query("INSERT INTO tasks (name, task_status, created_at) VALUES (?, 'pending', '')")
+                .bind(format!("task {}", i))
+                .execute(&db.pool)
+                .await
+                .unwrap()
+                .last_insert_rowid();
+            task_ids.push(id);
+        }
+        (db, task_ids)
+    }
+
+    fn sample_test() -> JobSpec {
+        JobSpec::SampleTest {
+            wake_word_strategy: None,
+            visual: JobVisualSettings::default(),
+        }
+    }
+
+    async fn queue_order(db: &DatabaseService) -> Vec<i64> {
+        db.list_jobs().await.unwrap().iter().map(|job| job.id).collect()
+    }
+
+    #[tokio::test]
+    async fn test_job_transitions() {
+        let (db, tasks) = test_db(1).await;
+        let job = db.enqueue_job(tasks[0], &sample_test(), 0).await.unwrap();
+
+        // 只有当前状态在 `from` 中时才更新
+        assert!(!db.transition_job(job, &[JobStatus::Paused], JobStatus::Queued, None).await.unwrap());
+        assert!(db.transition_job(job, &[JobStatus::Queued], JobStatus::Paused, None).await.unwrap());
+        assert!(db.transition_job(job, &[JobStatus::Paused], JobStatus::Queued, None).await.unwrap());
+        assert!(db.get_job(job).await.unwrap().unwrap().started_at.is_none());
+
+        assert!(db.transition_job(job, &[JobStatus::Queued], JobStatus::Running, None).await.unwrap());
+        let running = db.get_job(job).await.unwrap().unwrap();
+        assert_eq!(running.status, JobStatus::Running);
+        assert!(running.started_at.is_some());
+        assert!(running.finished_at.is_none());
+
+        // 执行中的任务不能暂停或取消
+        let cancellable = [JobStatus::Queued, JobStatus::Paused];
+        assert!(!db.transition_job(job, &cancellable, JobStatus::Cancelled, None).await.unwrap());
+
+        assert!(db.transition_job(job, &[JobStatus::Running], JobStatus::Failed, Some("boom")).await.unwrap());
+        let failed = db.get_job(job).await.unwrap().unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert_eq!(failed.error.as_deref(), Some("boom"));
+        assert_eq!(failed.started_at, running.started_at);
+        assert!(failed.finished_at.is_some());
+        assert!(!db.transition_job(job, &[JobStatus::Running], JobStatus::Done, None).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_claim_reorder_and_cancel_jobs() {
+        let (db, tasks) = test_db(3).await;
+        let a = db.enqueue_job(tasks[0], &sample_test(), 0).await.unwrap();
+        let b = db.enqueue_job(tasks[1], &sample_test(), 0).await.unwrap();
+        let c = db.enqueue_job(tasks[2], &sample_test(), 5).await.unwrap();
+
+        // 优先级高的先执行，同优先级按排列顺序
+        assert_eq!(queue_order(&db).await, vec![c, a, b]);
+        db.reorder_jobs(&[b, a]).await.unwrap();
+        assert_eq!(queue_order(&db).await, vec![c, b, a]);
+
+        // 取出时标记为执行中，之后不再被取出
+        let next = db.next_queued_job().await.unwrap().unwrap();
+        assert_eq!(next.id, c);
+        assert!(db.transition_job(c, &[JobStatus::Queued], JobStatus::Running, None).await.unwrap());
+        assert_eq!(db.next_queued_job().await.unwrap().unwrap().id, b);
+
+        // 取消和暂停的任务跳过
+        let cancellable = [JobStatus::Queued, JobStatus::Paused];
+        assert!(db.transition_job(b, &cancellable, JobStatus::Cancelled, None).await.unwrap());
+        assert!(db.transition_job(a, &[JobStatus::Queued], JobStatus::Paused, None).await.unwrap());
+        assert!(db.next_queued_job().await.unwrap().is_none());
+
+        // 程序重启后执行中的任务重新排队，保留首次开始时间
+        assert_eq!(db.requeue_running_jobs().await.unwrap(), 1);
+        let requeued = db.next_queued_job().await.unwrap().unwrap();
+        assert_eq!(requeued.id, c);
+        assert!(requeued.started_at.is_some());
+    }
+}
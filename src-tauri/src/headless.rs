@@ -0,0 +1,499 @@
+//! 无界面命令行运行器
+//!
+//! 打开与桌面应用相同的 SQLite 数据库，按任务ID执行样本测试或唤醒检测，
+//! 供 CI 测试台和远程实验机通过 SSH 启动测试。进度输出到控制台或以 JSON Lines 写入文件，
+//! 测试失败时以非零退出码结束。
+
+use crate::commands::{build_meta_executor, prepare_resumed_runs, prepare_task_runs};
+use crate::config_manager;
+use crate::models::TaskProgress;
+use crate::services::active_task::VisualWakeConfig;
+use crate::services::event_sink::{EventSink, Events};
+use crate::services::pipeline;
+use crate::services::run_plan::WakeWordStrategy;
+use crate::services::wake_detection_meta_executor::wake_detection_meta_executor;
+use crate::services::workflow::{ControlSignal, Workflow};
+use crate::state::AppState;
+use base64::Engine;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// 与 tauri.conf.json 中的 identifier 一致，桌面应用的数据目录以它命名
+const APP_IDENTIFIER: &str = "default";
+const DATABASE_FILE: &str = "llm_analysis.db";
+
+const EXIT_SUCCESS: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_INTERRUPTED: i32 = 130;
+
+const USAGE: &str = "\
+用法: headless --task <ID> [选项]
+
+选项:
+  --task <ID>                  要执行的任务ID（必填）
+  --mode <samples|wake>        执行样本测试（默认）或唤醒检测
+  --db <PATH>                  数据库文件，默认使用桌面应用的数据库
+  --resume                     从上次中断处继续样本测试
+  --strategy <STRATEGY>        唤醒词策略: fixed / round-robin / cross-product / random:<种子>，
+                               默认沿用任务上次保存的策略
+  --wake-word <ID>             固定使用的唤醒词，等同于 fixed 策略
+  --pipeline <NAME>            执行前把任务的流水线设置为 NAME
+  --template <PATH>            视觉唤醒模板图片，可重复指定
+  --frame-rate <N>             视觉检测帧率，默认 10
+  --threshold <X>              视觉检测阈值，默认 0.5
+  --max-detection-secs <N>     视觉检测最长时间（秒），默认 5
+  --expected-response <TEXT>   唤醒检测的预期回复，可重复指定
+  --tessdata <DIR>             OCR 使用的 tessdata 目录，默认读取 TESSDATA_PREFIX
+  --json-out <PATH>            以 JSON Lines 把所有事件写入 PATH（每行 {\"event\", \"payload\"}），
+                               此时运行器自身的提示写到标准错误
+  -h, --help                   显示帮助
+
+无界面运行时没有视频画面，视觉检测只会等待超时；建议样本测试使用不含 visual_detection 步骤的流水线（如 asr_only）。
+任务内部的调试输出写到标准输出，日志（级别取自 RUST_LOG，默认 info）写到标准错误。
+
+退出码: 0 成功，1 执行失败，2 参数错误，130 被 Ctrl+C 中断";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Samples,
+    Wake,
+}
+
+#[derive(Debug, PartialEq)]
+struct Options {
+    task_id: i64,
+    mode: Mode,
+    db_path: Option<PathBuf>,
+    resume: bool,
+    strategy: Option<WakeWordStrategy>,
+    pipeline: Option<String>,
+    templates: Vec<PathBuf>,
+    frame_rate: u32,
+    threshold: f64,
+    max_detection_secs: u64,
+    expected_responses: Vec<String>,
+    tessdata_dir: Option<PathBuf>,
+    json_out: Option<PathBuf>,
+}
+
+/// 解析命令行参数，返回 None 表示只需显示帮助
+fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Option<Options>, String> {
+    let mut args = args.into_iter();
+    let mut task_id = None;
+    let mut options = Options {
+        task_id: 0,
+        mode: Mode::Samples,
+        db_path: None,
+        resume: false,
+        strategy: None,
+        pipeline: None,
+        templates: Vec::new(),
+        frame_rate: 10,
+        threshold: 0.5,
+        max_detection_secs: 5,
+        expected_responses: Vec::new(),
+        tessdata_dir: None,
+        json_out: None,
+    };
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} 缺少参数值", name));
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--task" => task_id = Some(parse_number(&arg, &value(&arg)?)?),
+            "--mode" => {
+                options.mode = match value(&arg)?.as_str() {
+                    "samples" => Mode::Samples,
+                    "wake" => Mode::Wake,
+                    other => return Err(format!("未知的执行模式: {}", other)),
+                }
+            }
+            "--db" => options.db_path = Some(PathBuf::from(value(&arg)?)),
+            "--resume" => options.resume = true,
+            "--strategy" => options.strategy = Some(parse_strategy(&value(&arg)?)?),
+            "--wake-word" => {
+                let wake_word_id = parse_number(&arg, &value(&arg)?)?;
+                options.strategy = Some(WakeWordStrategy::Fixed { wake_word_id: Some(wake_word_id) });
+            }
+            "--pipeline" => options.pipeline = Some(value(&arg)?),
+            "--template" => options.templates.push(PathBuf::from(value(&arg)?)),
+            "--frame-rate" => options.frame_rate = parse_number(&arg, &value(&arg)?)?,
+            "--threshold" => options.threshold = parse_number(&arg, &value(&arg)?)?,
+            "--max-detection-secs" => options.max_detection_secs = parse_number(&arg, &value(&arg)?)?,
+            "--expected-response" => options.expected_responses.push(value(&arg)?),
+            "--tessdata" => options.tessdata_dir = Some(PathBuf::from(value(&arg)?)),
+            "--json-out" => options.json_out = Some(PathBuf::from(value(&arg)?)),
+            other => return Err(format!("未知参数: {}", other)),
+        }
+    }
+
+    options.task_id = task_id.ok_or("缺少 --task 参数")?;
+    if options.mode == Mode::Wake && (options.resume || options.strategy.is_some()) {
+        return Err("--resume、--strategy 和 --wake-word 只适用于样本测试".to_string());
+    }
+    if options.resume && options.strategy.is_some() {
+        return Err("恢复执行时沿用任务保存的唤醒词策略，不能同时指定 --strategy 或 --wake-word".to_string());
+    }
+    Ok(Some(options))
+}
+
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("{} 的参数值无效: {}", name, value))
+}
+
+fn parse_strategy(value: &str) -> Result<WakeWordStrategy, String> {
+    match value {
+        "fixed" => Ok(WakeWordStrategy::Fixed { wake_word_id: None }),
+        "round-robin" => Ok(WakeWordStrategy::RoundRobin),
+        "cross-product" => Ok(WakeWordStrategy::CrossProduct),
+        _ => match value.strip_prefix("random:") {
+            Some(seed) => Ok(WakeWordStrategy::Random {
+                seed: parse_number("--strategy", seed)?,
+            }),
+            None => Err(format!("未知的唤醒词策略: {}", value)),
+        },
+    }
+}
+
+/// 桌面应用使用的数据库位置（应用本地数据目录）
+fn default_db_path() -> Option<PathBuf> {
+    dirs::data_local_dir().map(|dir| dir.join(APP_IDENTIFIER).join(DATABASE_FILE))
+}
+
+/// 输出运行器自身的提示。事件以 JSON Lines 输出时写到标准错误，标准输出只有任务内部的调试输出
+macro_rules! note {
+    ($json:expr, $($arg:tt)*) => {
+        if $json {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// 把日志写到标准错误。本程序的日志按 RUST_LOG 的级别（默认 info）输出，依赖库只输出警告和错误
+struct StderrLogger;
+
+impl log::Log for StderrLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.level() <= log::max_level()
+            && (metadata.target().starts_with("app_lib") || metadata.level() <= log::Level::Warn)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{}] {}: {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+fn init_logger() {
+    let level = std::env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse().ok())
+        .unwrap_or(log::LevelFilter::Info);
+    if log::set_logger(&StderrLogger).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// 把事件输出到控制台：默认只打印进度和元任务消息；指定 `json_out` 时每个事件写一行 JSON 到该文件
+struct ConsoleEventSink {
+    json_out: Option<Mutex<File>>,
+}
+
+impl EventSink for ConsoleEventSink {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) -> Result<(), String> {
+        if let Some(file) = &self.json_out {
+            let line = serde_json::json!({ "event": event, "payload": payload }).to_string();
+            let mut file = file.lock().map_err(|e| e.to_string())?;
+            writeln!(file, "{}", line).map_err(|e| e.to_string())?;
+            return file.flush().map_err(|e| e.to_string());
+        }
+        let line = match event {
+            "progress_update" => {
+                let progress: TaskProgress =
+                    serde_json::from_value(payload).map_err(|e| e.to_string())?;
+                format_progress(&progress)
+            }
+            "meta_task_update"
+            | "meta_task_error"
+            | "wake_detection_meta_update"
+            | "wake_detection_meta_error"
+            | "workflow_task_event"
+            | "workflow_cancelled" => match payload {
+                serde_json::Value::String(message) => format!("[{}] {}", event, message),
+                other => format!("[{}] {}", event, other),
+            },
+            _ => return Ok(()),
+        };
+        let mut stdout = std::io::stdout().lock();
+        writeln!(stdout, "{}", line).map_err(|e| e.to_string())?;
+        stdout.flush().map_err(|e| e.to_string())
+    }
+}
+
+fn format_progress(progress: &TaskProgress) -> String {
+    let mut line = format!(
+        "[进度] {}/{} ({:.1}%) 通过 {} 未通过 {}",
+        progress.current_sample, progress.total, progress.value, progress.passed, progress.failed
+    );
+    if let Some(stage) = &progress.current_stage {
+        line.push_str(&format!(" 当前环节 {}", stage));
+    }
+    if let Some(eta_ms) = progress.eta_ms {
+        line.push_str(&format!(" 预计剩余 {}s", eta_ms / 1000));
+    }
+    line
+}
+
+/// 读取模板图片并编码为 Base64，与前端上传的模板格式相同
+fn load_templates(paths: &[PathBuf]) -> Result<Vec<(String, String)>, String> {
+    paths
+        .iter()
+        .map(|path| {
+            let data = std::fs::read(path)
+                .map_err(|e| format!("读取模板 {} 失败: {}", path.display(), e))?;
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string());
+            Ok((name, base64::engine::general_purpose::STANDARD.encode(data)))
+        })
+        .collect()
+}
+
+/// 命令行入口，返回进程退出码
+pub fn main() -> i32 {
+    let options = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return EXIT_SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+
+    init_logger();
+    config_manager::init_config_system();
+    if let Err(e) = dotenv::dotenv() {
+        log::warn!("无法加载.env文件: {}", e);
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("创建运行时失败: {}", e);
+            return EXIT_FAILURE;
+        }
+    };
+    runtime.block_on(async {
+        match run(options).await {
+            Ok(code) => code,
+            Err(e) => {
+                eprintln!("[HEADLESS] {}", e);
+                EXIT_FAILURE
+            }
+        }
+    })
+}
+
+async fn run(options: Options) -> Result<i32, String> {
+    let db_path = options
+        .db_path
+        .clone()
+        .or_else(default_db_path)
+        .ok_or("无法确定数据库位置，请使用 --db 指定")?;
+    if !db_path.exists() {
+        return Err(format!("数据库不存在: {}", db_path.display()));
+    }
+    let database_url = format!("sqlite:{}?mode=rw", db_path.to_string_lossy());
+    let json = options.json_out.is_some();
+    note!(json, "[HEADLESS] 数据库: {}", db_path.display());
+
+    let mut state = AppState::new(&database_url)
+        .await
+        .map_err(|e| format!("数据库初始化失败: {}", e))?;
+    state.tessdata_dir = options
+        .tessdata_dir
+        .clone()
+        .or_else(|| std::env::var_os("TESSDATA_PREFIX").map(PathBuf::from));
+    let state = Arc::new(state);
+
+    let task_id = options.task_id;
+    let task = state
+        .db
+        .get_task_by_id(task_id)
+        .await
+        .map_err(|e| format!("获取任务失败: {}", e))?
+        .ok_or_else(|| format!("任务 {} 不存在", task_id))?;
+    // 桌面应用与本程序共用数据库，不能同时执行同一个任务
+    if task.task_status == "in_progress" {
+        return Err(format!(
+            "任务 {} 正在执行中，请等待桌面应用中的测试结束；如果桌面应用已异常退出，重新打开后即可继续",
+            task_id
+        ));
+    }
+    *state.current_task_id.write().await = Some(task_id);
+
+    if let Some(name) = &options.pipeline {
+        pipeline::load_pipeline(name).map_err(|e| format!("加载流水线失败: {}", e))?;
+        state
+            .db
+            .update_task_pipeline(task_id, Some(name.as_str()))
+            .await
+            .map_err(|e| format!("更新任务流水线失败: {}", e))?;
+    }
+
+    let visual_config = VisualWakeConfig {
+        template_data: load_templates(&options.templates)?,
+        frame_rate: options.frame_rate,
+        threshold: options.threshold,
+        max_detection_time_secs: Some(options.max_detection_secs),
+    };
+
+    let (mut workflow, _) = Workflow::new();
+    match options.mode {
+        Mode::Samples => {
+            let (runs, completed_runs) = if options.resume {
+                prepare_resumed_runs(&state.db, task_id).await?
+            } else {
+                let strategy = match options.strategy.clone() {
+                    Some(strategy) => strategy,
+                    None => state
+                        .db
+                        .get_task_wake_word_strategy(task_id)
+                        .await
+                        .map_err(|e| format!("获取唤醒词策略失败: {}", e))?,
+                };
+                (prepare_task_runs(&state.db, task_id, &strategy).await?, HashSet::new())
+            };
+            note!(
+                json,
+                "[HEADLESS] 任务 {} '{}'：共 {} 次运行，{} 次已完成",
+                task_id,
+                task.name,
+                runs.len(),
+                completed_runs.len()
+            );
+            workflow.add_task(
+                build_meta_executor(&state, task_id, runs, visual_config, completed_runs).await?,
+            );
+        }
+        Mode::Wake => {
+            if task.wake_word_ids.is_empty() {
+                return Err("任务没有关联的唤醒词".to_string());
+            }
+            note!(
+                json,
+                "[HEADLESS] 任务 {} '{}'：唤醒检测，共 {} 个唤醒词",
+                task_id,
+                task.name,
+                task.wake_word_ids.len()
+            );
+            workflow.add_task(wake_detection_meta_executor::new(
+                &format!("wake_detection_task_{}", task_id),
+                task_id,
+                visual_config,
+                state.clone(),
+                options.expected_responses.clone(),
+            ));
+        }
+    }
+
+    // Ctrl+C 发送停止信号，元任务保存当前进度后退出，之后可以用 --resume 继续
+    let (control_tx, control_rx) = watch::channel(ControlSignal::Running);
+    let json_out = match &options.json_out {
+        Some(path) => Some(Mutex::new(
+            File::create(path).map_err(|e| format!("创建 {} 失败: {}", path.display(), e))?,
+        )),
+        None => None,
+    };
+    let events: Events = Arc::new(ConsoleEventSink { json_out });
+    let execution = workflow.run_and_wait(events, control_rx);
+    tokio::pin!(execution);
+    let mut interrupted = false;
+    let result = loop {
+        tokio::select! {
+            result = &mut execution => break result,
+            signal = tokio::signal::ctrl_c(), if !interrupted => {
+                if signal.is_ok() {
+                    eprintln!("[HEADLESS] 收到中断信号，正在停止...");
+                    interrupted = true;
+                    control_tx.send(ControlSignal::Stopped).ok();
+                }
+            }
+        }
+    };
+
+    if let Some(progress) = state.task_progress.read().await.as_ref() {
+        if progress.task_id == task_id {
+            note!(
+                json,
+                "[HEADLESS] 已完成 {}/{}，通过 {}，未通过 {}",
+                progress.current_sample, progress.total, progress.passed, progress.failed
+            );
+        }
+    }
+
+    if interrupted {
+        eprintln!("[HEADLESS] 任务 {} 已中断", task_id);
+        return Ok(EXIT_INTERRUPTED);
+    }
+    match result {
+        Ok(_) => {
+            note!(json, "[HEADLESS] 任务 {} 执行完成", task_id);
+            Ok(EXIT_SUCCESS)
+        }
+        Err(e) => {
+            eprintln!("[HEADLESS] 任务 {} 执行失败: {}", task_id, e);
+            Ok(EXIT_FAILURE)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn parses_sample_run_options() {
+        let options = parse_args(args(&[
+            "--task", "7", "--strategy", "random:42", "--pipeline", "asr_only", "--json-out", "events.jsonl",
+        ]))
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.task_id, 7);
+        assert_eq!(options.mode, Mode::Samples);
+        assert_eq!(options.strategy, Some(WakeWordStrategy::Random { seed: 42 }));
+        assert_eq!(options.pipeline.as_deref(), Some("asr_only"));
+        assert_eq!(options.json_out, Some(PathBuf::from("events.jsonl")));
+        assert_eq!(options.frame_rate, 10);
+    }
+
+    #[test]
+    fn rejects_invalid_combinations() {
+        assert!(parse_args(args(&["--mode", "wake"])).is_err());
+        assert!(parse_args(args(&["--task", "1", "--mode", "wake", "--resume"])).is_err());
+        assert!(parse_args(args(&["--task", "1", "--resume", "--wake-word", "2"])).is_err());
+        assert!(parse_args(args(&["--task", "x"])).is_err());
+        assert!(parse_args(args(&["--task", "1", "--strategy", "sometimes"])).is_err());
+        assert_eq!(parse_args(args(&["--task", "1", "--help"])).unwrap(), None);
+    }
+}
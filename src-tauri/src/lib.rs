@@ -5,6 +5,7 @@ mod services;
 mod commands;
 mod config;
 mod config_manager;
+pub mod headless;
 
 use state::AppState;
 use std::sync::Arc;
@@ -55,13 +56,33 @@ pub fn run() {
             
             // 在 setup 中同步初始化数据库
             let result = tauri::async_runtime::block_on(async {
-                AppState::new(&database_url).await
+                let state = AppState::new(&database_url).await?;
+                let interrupted = state.db.recover_interrupted_runs().await?;
+                if !interrupted.is_empty() {
+                    log::warn!(
+                        "[DB_SERVICE] Tasks {:?} were still running when the app last exited, marked as interrupted",
+                        interrupted
+                    );
+                }
+                anyhow::Ok(state)
             });
             
             match result {
-                Ok(state) => {
-                    app_handle.manage(Arc::new(state));
+                Ok(mut state) => {
+                    state.tessdata_dir = app
+                        .path()
+                        .resolve("tessdata", tauri::path::BaseDirectory::Resource)
+                        .ok();
+                    let state = Arc::new(state);
+                    app_handle.manage(state.clone());
                     log::info!("数据库初始化成功: {}", database_url);
+
+                    // 在后台依次执行测试队列中的任务，定时计划到点后加入队列
+                    tauri::async_runtime::spawn(services::job_queue::run_job_queue(
+                        state.clone(),
+                        Arc::new(app_handle.clone()),
+                    ));
+                    tauri::async_runtime::spawn(services::scheduler::run_scheduler(state));
                 }
                 Err(e) => {
                     log::error!("数据库初始化失败: {}", e);
@@ -84,14 +105,32 @@ pub fn run() {
             commands::get_all_wake_words,
             commands::get_all_wake_words_raw,
             commands::create_task,
+            commands::get_task_progress,
             commands::get_analysis_results,
             commands::get_machine_responses,
             commands::get_wake_detection_results,
             commands::check_wake_detection_results_exist,
             commands::delete_wake_detection_results_by_task,
             commands::create_sample,
+            commands::update_sample_repeats,
             commands::create_wake_word,
             commands::update_task_status,
+            commands::get_task_llm_settings,
+            commands::update_task_llm_settings,
+            commands::get_task_wake_word_strategy,
+            commands::update_task_wake_word_strategy,
+            commands::list_pipelines,
+            commands::get_task_pipeline,
+            commands::update_task_pipeline,
+            commands::list_prompt_templates,
+            commands::save_prompt_template,
+            commands::get_task_prompt_template,
+            commands::set_task_prompt_template,
+            commands::list_rubrics,
+            commands::save_rubric,
+            commands::delete_rubric,
+            commands::get_task_rubric,
+            commands::set_task_rubric,
             commands::delete_task,
             commands::is_testing,
             commands::stop_testing,
@@ -113,8 +152,17 @@ pub fn run() {
             commands::push_video_frame,
             commands::get_ocr_task_status,
             commands::new_meta_workflow,
+            commands::resume_task,
+            commands::get_sample_run_states,
+            commands::get_workflow_traces,
+            commands::export_workflow_traces,
+            commands::get_sample_workflow_graph,
             commands::delete_wake_word_safe,
             commands::get_timing_data_by_task,
+            commands::get_task_statistics,
+            commands::refresh_task_statistics,
+            commands::get_sample_stability,
+            commands::export_task_report,
             commands::start_visual_wake_detection,
             commands::start_visual_wake_detection_with_data,
             commands::stop_visual_wake_detection,
@@ -126,6 +174,18 @@ pub fn run() {
             commands::get_templates_from_folder,
             commands::load_template_from_folder,
             commands::start_wake_detection_workflow,
+            commands::enqueue_job,
+            commands::list_jobs,
+            commands::pause_job,
+            commands::resume_job,
+            commands::cancel_job,
+            commands::set_job_priority,
+            commands::reorder_jobs,
+            commands::create_schedule,
+            commands::list_schedules,
+            commands::set_schedule_enabled,
+            commands::delete_schedule,
+            commands::preview_schedule,
             commands::delete_template_from_folder,
             commands::import_task_package,
             // Configuration commands
@@ -1,10 +1,12 @@
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 // 导入时间数据模块
 use chrono::{DateTime, Utc, Duration};
 
+use crate::services::run_plan::WakeWordStrategy;
+
 // 前端兼容的数据结构
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TestSample {
@@ -63,14 +65,168 @@ pub struct AssessmentItem {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Assessment {
-    pub semantic_correctness: AssessmentItem,
-    pub state_change_confirmation: AssessmentItem,
-    pub unambiguous_expression: AssessmentItem,
+    /// 各评分维度，键为评分标准中的维度标识（如 semantic_correctness），
+    /// 序列化时展开到 assessment 对象上，与旧的固定字段格式保持兼容
+    #[serde(flatten)]
+    pub dimensions: BTreeMap<String, AssessmentItem>,
+    /// 加权平均分，由评分标准计算，不采用大模型给出的值
+    #[serde(default)]
     pub overall_score: f64,
+    #[serde(default)]
     pub valid: bool,
+    #[serde(default)]
     pub suggestions: Vec<String>,
 }
 
+/// 评分标准中的一个维度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RubricDimension {
+    /// 输出 JSON 中的字段名
+    pub key: String,
+    /// 显示名称
+    pub name: String,
+    /// 提供给大模型的评分说明
+    pub description: String,
+    pub weight: f64,
+}
+
+/// 评分标准：一组带权重的评分维度和通过阈值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rubric {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub pass_threshold: f64,
+    pub dimensions: Vec<RubricDimension>,
+    pub created_at: String,
+}
+
+/// 内置默认评分标准的维度，与最初的三个固定维度一致
+pub fn default_rubric_dimensions() -> Vec<RubricDimension> {
+    vec![
+        RubricDimension {
+            key: "semantic_correctness".to_string(),
+            name: "语义正确性".to_string(),
+            description: "响应是否正确理解并执行了指令的语义".to_string(),
+            weight: 1.0,
+        },
+        RubricDimension {
+            key: "state_change_confirmation".to_string(),
+            name: "状态变更确认".to_string(),
+            description: "响应是否明确确认了车辆状态的变化".to_string(),
+            weight: 1.0,
+        },
+        RubricDimension {
+            key: "unambiguous_expression".to_string(),
+            name: "表达无歧义性".to_string(),
+            description: "响应表达是否清晰、没有歧义".to_string(),
+            weight: 1.0,
+        },
+    ]
+}
+
+impl Rubric {
+    /// 按权重计算总分；权重之和为0时退化为算术平均
+    pub fn weighted_score(&self, dimensions: &BTreeMap<String, AssessmentItem>) -> f64 {
+        if self.dimensions.is_empty() {
+            return 0.0;
+        }
+        let score_of = |key: &str| dimensions.get(key).map(|item| item.score).unwrap_or(0.0);
+        let total_weight: f64 = self.dimensions.iter().map(|d| d.weight.max(0.0)).sum();
+        if total_weight <= 0.0 {
+            let sum: f64 = self.dimensions.iter().map(|d| score_of(&d.key)).sum();
+            return sum / self.dimensions.len() as f64;
+        }
+        self.dimensions
+            .iter()
+            .map(|d| d.weight.max(0.0) * score_of(&d.key))
+            .sum::<f64>()
+            / total_weight
+    }
+
+    /// 只保留评分标准中的维度（缺失的记0分，分数限制在0-1），并重新计算总分和是否通过
+    pub fn finalize(&self, assessment: &mut Assessment) {
+        let mut dimensions = BTreeMap::new();
+        for dimension in &self.dimensions {
+            let mut item = assessment
+                .dimensions
+                .remove(&dimension.key)
+                .unwrap_or_else(|| AssessmentItem {
+                    score: 0.0,
+                    comment: "模型未返回该维度的评分".to_string(),
+                });
+            item.score = if item.score.is_finite() {
+                item.score.clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            dimensions.insert(dimension.key.clone(), item);
+        }
+        assessment.dimensions = dimensions;
+        assessment.overall_score = self.weighted_score(&assessment.dimensions);
+        assessment.valid = assessment.overall_score >= self.pass_threshold;
+    }
+
+    /// 生成所有维度均为0分的评估结果，用于唤醒失败、超时等无法评估的情况
+    pub fn failed_assessment(&self, reason: &str, suggestions: Vec<String>) -> Assessment {
+        let dimensions = self
+            .dimensions
+            .iter()
+            .map(|d| {
+                (
+                    d.key.clone(),
+                    AssessmentItem {
+                        score: 0.0,
+                        comment: format!("{}，无法进行{}评估", reason, d.name),
+                    },
+                )
+            })
+            .collect();
+        Assessment {
+            dimensions,
+            overall_score: 0.0,
+            valid: false,
+            suggestions,
+        }
+    }
+
+    /// 提示词中的维度说明，对应模板占位符 {{dimensions}}
+    pub fn prompt_dimensions(&self) -> String {
+        self.dimensions
+            .iter()
+            .enumerate()
+            .map(|(i, d)| {
+                format!(
+                    "{}. {}（{}，权重{}）: {}，评分0-1和评估意见",
+                    i + 1,
+                    d.key,
+                    d.name,
+                    d.weight,
+                    d.description
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// 提示词中要求的输出结构，对应模板占位符 {{output_schema}}
+    pub fn output_schema(&self) -> String {
+        let mut lines = vec!["{".to_string(), "  \"assessment\": {".to_string()];
+        for d in &self.dimensions {
+            lines.push(format!(
+                "    \"{}\": {{\"score\": 0.0, \"comment\": \"...\"}},",
+                d.key
+            ));
+        }
+        lines.push("    \"overall_score\": 0.0,".to_string());
+        lines.push("    \"valid\": false,".to_string());
+        lines.push("    \"suggestions\": [\"...\", \"...\"]".to_string());
+        lines.push("  }".to_string());
+        lines.push("}".to_string());
+        lines.join("\n")
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LlmAnalysis {
     pub title: String,
@@ -97,6 +253,316 @@ pub struct AnalysisResult {
     pub result_status: Option<String>,
     pub recognition_time: Option<f32>,
     pub response_time: Option<f32>,
+    /// 生成该结果的提示词模板
+    #[serde(default)]
+    pub prompt_template_id: Option<i64>,
+    #[serde(default)]
+    pub prompt_template_version: Option<i64>,
+    /// 使用的评分标准
+    #[serde(default)]
+    pub rubric_id: Option<i64>,
+    /// 本次运行使用的唤醒词
+    #[serde(default)]
+    pub wake_word_id: Option<u32>,
+    /// 样本重复执行时的第几次（从0开始）
+    #[serde(default)]
+    pub run_index: u32,
+}
+
+/// 一次样本运行在结果表中的标识。
+/// 同一样本可能以不同唤醒词、多次重复执行，结果按 (task_id, sample_id, wake_word_id, run_index) 区分；
+/// wake_word_id 为 0 表示未记录唤醒词（旧数据），run_index 从0开始。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SampleRunKey {
+    pub task_id: i64,
+    pub sample_id: i64,
+    pub wake_word_id: i64,
+    pub run_index: i64,
+}
+
+impl SampleRunKey {
+    pub fn new(task_id: i64, sample_id: i64, wake_word_id: u32, run_index: u32) -> Self {
+        Self {
+            task_id,
+            sample_id,
+            wake_word_id: wake_word_id as i64,
+            run_index: run_index as i64,
+        }
+    }
+}
+
+/// 样本运行的执行状态，测试中断（程序崩溃、车机重启）后据此从第一个未完成的运行继续
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleRunStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+impl SampleRunStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SampleRunStatus::Pending => "pending",
+            SampleRunStatus::Running => "running",
+            SampleRunStatus::Done => "done",
+            SampleRunStatus::Failed => "failed",
+        }
+    }
+
+    /// 无法识别的状态按未执行处理，恢复时会重新执行
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "running" => SampleRunStatus::Running,
+            "done" => SampleRunStatus::Done,
+            "failed" => SampleRunStatus::Failed,
+            _ => SampleRunStatus::Pending,
+        }
+    }
+}
+
+/// 一次样本运行的持久化状态，`run_order` 为运行在计划中的序号
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleRunState {
+    pub task_id: i64,
+    pub sample_id: i64,
+    pub wake_word_id: i64,
+    pub run_index: i64,
+    pub run_order: i64,
+    pub status: SampleRunStatus,
+    pub error: Option<String>,
+    pub updated_at: String,
+}
+
+impl SampleRunState {
+    pub fn key(&self) -> SampleRunKey {
+        SampleRunKey {
+            task_id: self.task_id,
+            sample_id: self.sample_id,
+            wake_word_id: self.wake_word_id,
+            run_index: self.run_index,
+        }
+    }
+}
+
+/// 一次样本子工作流运行的执行记录，用于定位失败发生在哪个环节（音频、识别、大模型、视觉检测）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowRunRecord {
+    pub id: i64,
+    pub task_id: i64,
+    pub sample_id: i64,
+    pub wake_word_id: i64,
+    pub run_index: i64,
+    /// 子工作流使用的流水线名称
+    pub pipeline: String,
+    /// completed / failed / cancelled
+    pub status: String,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: String,
+    pub tasks: Vec<WorkflowTaskRecord>,
+}
+
+/// 子工作流中单个任务节点的执行记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkflowTaskRecord {
+    pub node_id: String,
+    /// completed / declined / skipped / failed / cancelled
+    pub status: String,
+    /// 被跳过的任务没有开始和结束时间
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub duration_ms: Option<i64>,
+    /// 执行次数（含重试）
+    pub attempts: i64,
+    /// 失败原因、否决原因或取消说明
+    pub message: Option<String>,
+    /// 任务输出摘要 [{ key, type_name, value }]
+    pub outputs: serde_json::Value,
+}
+
+/// 队列中测试任务的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    /// 排队等待执行
+    Queued,
+    /// 暂不执行，恢复后重新排队
+    Paused,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Paused => "paused",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+
+    /// 无法识别的状态按已取消处理，不会被执行
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "queued" => JobStatus::Queued,
+            "paused" => JobStatus::Paused,
+            "running" => JobStatus::Running,
+            "done" => JobStatus::Done,
+            "failed" => JobStatus::Failed,
+            _ => JobStatus::Cancelled,
+        }
+    }
+}
+
+/// 视觉唤醒检测参数，未提供的项使用与手动启动相同的默认值
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobVisualSettings {
+    #[serde(default)]
+    pub template_data: Vec<(String, String)>,
+    pub frame_rate: Option<u32>,
+    pub threshold: Option<f64>,
+    pub max_detection_time_secs: Option<u64>,
+}
+
+/// 队列中测试任务的类型和启动参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum JobSpec {
+    /// 样本测试，参数同 `new_meta_workflow`；未指定唤醒词策略时沿用任务保存的策略
+    SampleTest {
+        #[serde(default)]
+        wake_word_strategy: Option<WakeWordStrategy>,
+        #[serde(default)]
+        visual: JobVisualSettings,
+    },
+    /// 唤醒测试，参数同 `start_wake_detection_workflow`
+    WakeTest {
+        #[serde(default)]
+        visual: JobVisualSettings,
+        #[serde(default)]
+        expected_responses: Vec<String>,
+    },
+}
+
+/// 定时执行的时间规则，按本地时间计算
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduleRule {
+    /// 5 段 cron 表达式（分 时 日 月 周）
+    Cron { expression: String },
+    /// 每天的固定时间，格式 HH:MM
+    Daily { time: String },
+}
+
+/// 程序关闭期间错过执行时间的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRunPolicy {
+    /// 启动后补执行一次（错过多次也只执行一次）
+    #[default]
+    RunOnce,
+    /// 跳过，等待下一次执行时间
+    Skip,
+}
+
+impl MissedRunPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MissedRunPolicy::RunOnce => "run_once",
+            MissedRunPolicy::Skip => "skip",
+        }
+    }
+
+    pub fn parse(policy: &str) -> Self {
+        match policy {
+            "skip" => MissedRunPolicy::Skip,
+            _ => MissedRunPolicy::RunOnce,
+        }
+    }
+}
+
+/// 任务的定时执行计划。每次到点复制一份任务（名称附加执行时间，结果单独保存）并加入测试队列
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskSchedule {
+    pub id: i64,
+    pub task_id: i64,
+    pub rule: ScheduleRule,
+    /// 加入队列时使用的启动参数
+    pub spec: JobSpec,
+    pub priority: i64,
+    pub missed_run_policy: MissedRunPolicy,
+    pub enabled: bool,
+    /// 最近一次加入队列的时间（UTC）
+    pub last_run_at: Option<String>,
+    /// 下一次执行时间（UTC），规则不会再触发时为 None
+    pub next_run_at: Option<String>,
+    /// 最近一次加入队列时创建的任务
+    pub last_task_id: Option<i64>,
+    pub created_at: String,
+}
+
+/// 测试队列中的一项。按 `priority` 从高到低、`position` 从小到大执行
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedJob {
+    pub id: i64,
+    pub task_id: i64,
+    pub spec: JobSpec,
+    pub priority: i64,
+    pub position: i64,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    pub created_at: String,
+    /// 首次开始执行的时间，程序重启后据此从中断处继续
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+/// 内置的默认评估提示词。
+/// 支持的占位符：{{instruction}}、{{response}}、{{dimensions}}、{{output_schema}}、{{pass_threshold}}
+pub const DEFAULT_PROMPT_TEMPLATE: &str = r#"作为车机系统测试专家，请严格评估：
+指令：{{instruction}}
+响应：{{response}}
+
+请按以下维度评估并返回严格JSON格式：
+{{dimensions}}
+overall_score: 各维度的加权平均分
+valid: 测试是否通过（加权平均分不低于{{pass_threshold}}）
+suggestions: 改进建议列表
+
+输出必须为中文
+
+输出必须严格符合以下JSON结构：
+{{output_schema}}"#;
+
+/// 评估提示词模板，同名模板的每次修改都会生成一个新版本
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PromptTemplate {
+    pub id: i64,
+    pub name: String,
+    pub version: i64,
+    pub content: String,
+    pub description: Option<String>,
+    pub created_at: String,
+}
+
+impl PromptTemplate {
+    /// 用给定的变量替换 {{name}} 形式的占位符
+    pub fn render(&self, variables: &[(&str, &str)]) -> String {
+        let mut rendered = self.content.clone();
+        for (name, value) in variables {
+            rendered = rendered
+                .replace(&format!("{{{{{}}}}}", name), value)
+                .replace(&format!("{{{{ {} }}}}", name), value);
+        }
+        rendered
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -105,12 +571,78 @@ pub struct MachineResponseData {
     pub connected: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// 测试进度（"progress_update" 事件和 `get_task_progress` 的返回值），`value` 为 0-100 的百分比
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TaskProgress {
+    #[serde(default)]
+    pub task_id: i64,
     pub value: f32,
     pub current_sample: u32,
+    /// 当前样本正在执行的环节（如 audio_task、asr_task）
     pub current_stage: Option<String>,
     pub total: u32,
+    /// 已结束运行中评估通过/未通过的次数
+    #[serde(default)]
+    pub passed: u32,
+    #[serde(default)]
+    pub failed: u32,
+    #[serde(default)]
+    pub elapsed_ms: u64,
+    /// 按最近样本的平均耗时估算的剩余时间，尚无已完成样本时为空
+    #[serde(default)]
+    pub eta_ms: Option<u64>,
+}
+
+/// 任务级别的统计汇总，时间单位均为毫秒
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+pub struct TaskStatistics {
+    pub task_id: i64,
+    pub total_samples: i64,
+    pub completed_samples: i64,
+    pub passed_samples: i64,
+    /// 大模型评估失败的样本数，不计入通过率分母
+    pub evaluation_errors: i64,
+    pub pass_rate: Option<f64>,
+    pub average_score: Option<f64>,
+    /// 识别出非空文本的样本占比
+    pub recognition_success_rate: Option<f64>,
+    pub wake_attempts: i64,
+    pub wake_successes: i64,
+    pub wake_rate: Option<f64>,
+    /// 语音识别时间（指令开始播放到首字上屏）
+    pub recognition_min_ms: Option<f64>,
+    pub recognition_max_ms: Option<f64>,
+    pub recognition_avg_ms: Option<f64>,
+    pub recognition_p50_ms: Option<f64>,
+    pub recognition_p90_ms: Option<f64>,
+    pub recognition_p99_ms: Option<f64>,
+    /// 交互响应时间（指令结束到车机执行动作）
+    pub response_avg_ms: Option<f64>,
+    pub response_p50_ms: Option<f64>,
+    pub response_p90_ms: Option<f64>,
+    pub response_p99_ms: Option<f64>,
+    pub updated_at: String,
+}
+
+/// 单个样本多次执行（重复次数、多个唤醒词）的稳定性统计，时间单位为毫秒
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SampleStability {
+    pub sample_id: i64,
+    pub sample_text: String,
+    pub runs: i64,
+    pub passed_runs: i64,
+    /// 大模型评估失败的次数，不计入通过比例分母
+    pub evaluation_errors: i64,
+    pub pass_ratio: Option<f64>,
+    pub score_avg: Option<f64>,
+    pub score_std_dev: Option<f64>,
+    pub recognition_avg_ms: Option<f64>,
+    /// 总体方差（ms²）
+    pub recognition_variance: Option<f64>,
+    pub recognition_std_dev_ms: Option<f64>,
+    pub response_avg_ms: Option<f64>,
+    pub response_variance: Option<f64>,
+    pub response_std_dev_ms: Option<f64>,
 }
 
 // 数据库行结构
@@ -176,12 +708,6 @@ pub struct AnalysisResultRow {
     pub id: i64,
     pub task_id: i64,
     pub sample_id: i64,
-    pub semantic_correctness_score: f64,
-    pub semantic_correctness_comment: Option<String>,
-    pub state_change_score: f64,
-    pub state_change_comment: Option<String>,
-    pub unambiguous_score: f64,
-    pub unambiguous_comment: Option<String>,
     pub overall_score: f64,
     pub is_valid: bool,
     pub suggestions: Option<String>, // JSON string
@@ -204,6 +730,39 @@ pub struct AnalysisResultRow {
     pub recognition_time: Option<f64>,
     pub response_time: Option<f64>,
     pub created_at: String,
+    pub prompt_template_id: Option<i64>,
+    pub prompt_template_version: Option<i64>,
+    pub rubric_id: Option<i64>,
+    pub wake_word_id: i64,
+    pub run_index: i64,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AnalysisDimensionScoreRow {
+    pub sample_id: i64,
+    pub wake_word_id: i64,
+    pub run_index: i64,
+    pub dimension_key: String,
+    pub score: f64,
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct RubricRow {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub pass_threshold: f64,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct RubricDimensionRow {
+    pub rubric_id: i64,
+    pub dimension_key: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub weight: f64,
 }
 
 #[derive(Debug, Clone, FromRow)]
@@ -211,11 +770,83 @@ pub struct MachineResponseRow {
     pub id: i64,
     pub task_id: i64,
     pub sample_id: i64,
+    pub wake_word_id: i64,
+    pub run_index: i64,
     pub text: String,
     pub connected: bool,
     pub created_at: String,
 }
 
+#[derive(Debug, Clone, FromRow)]
+pub struct SampleRunStateRow {
+    pub id: i64,
+    pub task_id: i64,
+    pub sample_id: i64,
+    pub wake_word_id: i64,
+    pub run_index: i64,
+    pub run_order: i64,
+    pub status: String,
+    pub error: Option<String>,
+    pub updated_at: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct QueuedJobRow {
+    pub id: i64,
+    pub task_id: i64,
+    pub spec: String,
+    pub priority: i64,
+    pub position: i64,
+    pub status: String,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct TaskScheduleRow {
+    pub id: i64,
+    pub task_id: i64,
+    pub rule: String,
+    pub spec: String,
+    pub priority: i64,
+    pub missed_run_policy: String,
+    pub enabled: bool,
+    pub last_run_at: Option<String>,
+    pub next_run_at: Option<String>,
+    pub last_task_id: Option<i64>,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct WorkflowRunRow {
+    pub id: i64,
+    pub task_id: i64,
+    pub sample_id: i64,
+    pub wake_word_id: i64,
+    pub run_index: i64,
+    pub pipeline: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub started_at: String,
+    pub finished_at: String,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct WorkflowTaskTraceRow {
+    pub id: i64,
+    pub run_id: i64,
+    pub node_id: String,
+    pub status: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub attempts: i64,
+    pub message: Option<String>,
+    pub outputs: String,
+}
+
 #[derive(Debug, Clone, FromRow)]
 pub struct WakeDetectionResultRow {
     pub id: i64,
@@ -1,9 +1,11 @@
 use async_trait::async_trait;
-use tauri::Emitter;
 use tokio::sync::watch;
 use std::error::Error;
 use std::time::{Duration, Instant};
-use crate::services::workflow::{ControlSignal, Task, WorkflowContext};
+use crate::services::event_sink::Events;
+use crate::services::workflow::{
+    resources, ContextKey, ControlSignal, KeyDecl, Task, TaskOutcome, TaskPolicy, WorkflowContext,
+};
 use crate::services::visual_wake_detection::get_or_create_detector;
 
 #[derive(Debug, Clone)]
@@ -18,11 +20,33 @@ pub struct VisualWakeConfig {
 pub struct ActiveTask {
     pub id: String,
     pub visual_wake_config: VisualWakeConfig,
+    timed_out: bool, // 检测窗口内未唤醒，任务以 Declined 结束
 }
 
+/// 检测窗口之外留给模板加载和状态写回的时间
+const ACTIVE_TASK_TIMEOUT_MARGIN: Duration = Duration::from_secs(30);
+
 impl ActiveTask {
     pub fn new(id: String, visual_wake_config: VisualWakeConfig) -> Self {
-        Self { id, visual_wake_config }
+        Self { id, visual_wake_config, timed_out: false }
+    }
+
+    /// 检测结果（status / confidence / duration_ms / timestamp）在上下文中的键
+    pub fn output_key(task_id: &str) -> ContextKey<serde_json::Value> {
+        ContextKey::new(task_id)
+    }
+
+    fn max_detection_time(&self) -> Duration {
+        self.visual_wake_config
+            .max_detection_time_secs
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(5)) // 默认5秒超时
+    }
+
+    /// 检测窗口内未唤醒属于正常结果（写入 "timeout" 状态），
+    /// 工作流超时只兜底检测器卡死的情况
+    pub fn policy(&self) -> TaskPolicy {
+        TaskPolicy::default().with_timeout(self.max_detection_time() + ACTIVE_TASK_TIMEOUT_MARGIN)
     }
 }
 
@@ -32,11 +56,20 @@ impl Task for ActiveTask {
         self.id.clone()
     }
 
+    fn outputs(&self) -> Vec<KeyDecl> {
+        vec![Self::output_key(&self.id).decl()]
+    }
+
+    /// 检测器是全局唯一的，同时只能为一个任务加载模板
+    fn resources(&self) -> Vec<&'static str> {
+        vec![resources::VISUAL_DETECTOR]
+    }
+
     async fn execute(
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         let config = &self.visual_wake_config;
         let detector = get_or_create_detector().await;
@@ -54,9 +87,7 @@ impl Task for ActiveTask {
         let mut wake_detected = false; // 标记是否检测到唤醒事件
         let mut detection_start_time: Option<Instant> = None; // 检测开始时间
         let mut detection_confidence: Option<f64> = None; // 检测置信度
-        let max_detection_time = config.max_detection_time_secs
-            .map(|secs| Duration::from_secs(secs))
-            .unwrap_or(Duration::from_secs(5)); // 默认30秒超时
+        let max_detection_time = self.max_detection_time();
 
         loop {
             let signal = control_rx.borrow().clone();
@@ -66,17 +97,17 @@ impl Task for ActiveTask {
                     ControlSignal::Running => {
                         detector_guard.set_enabled(true);
                         detection_start_time = Some(Instant::now()); // 记录检测开始时间
-                        app_handle.emit("active_task_info", "started").ok();
+                        events.emit("active_task_info", "started").ok();
                         println!("ActiveTask: 唤醒前端进行检测，最大检测时间: {}秒", max_detection_time.as_secs());
                     }
                     ControlSignal::Paused => {
                         detector_guard.set_enabled(false);
                         detection_start_time = None; // 暂停时重置开始时间
-                        app_handle.emit("active_task_info", "stopped").ok();
+                        events.emit("active_task_info", "stopped").ok();
                     }
                     ControlSignal::Stopped => {
                         detector_guard.set_enabled(false);
-                        app_handle.emit("active_task_info", "stopped").ok();
+                        events.emit("active_task_info", "stopped").ok();
                         return Ok(());
                     }
                 }
@@ -93,18 +124,17 @@ impl Task for ActiveTask {
                         println!("ActiveTask: 检测超时 ({}秒)，未检测到唤醒事件", max_detection_time.as_secs());
                         
                         // 写入超时状态到 context
-                        let mut context_guard = context.write().await;
-                        context_guard.insert(self.id(), Box::new(serde_json::json!({
+                        Self::output_key(&self.id).insert(&mut *context.write().await, serde_json::json!({
                             "status": "timeout",
                             "confidence": null,
                             "duration_ms": elapsed.as_millis() as u64,
                             "timestamp": chrono::Utc::now().timestamp_millis()
-                        })));
-                        drop(context_guard);
+                        }));
+                        self.timed_out = true;
 
                         drop(detector_guard);
-                        app_handle.emit("active_task_info", "timeout").ok();
-                        app_handle.emit("task_completed", "active_task_timeout").ok();
+                        events.emit("active_task_info", "timeout").ok();
+                        events.emit("task_completed", "active_task_timeout").ok();
                         return Ok(());
                     }
                 }
@@ -119,7 +149,7 @@ impl Task for ActiveTask {
                         if signal == ControlSignal::Stopped {
                             let mut detector_guard = detector.lock().await;
                             detector_guard.set_enabled(false);
-                            app_handle.emit("active_task_info", "stopped").ok();
+                            events.emit("active_task_info", "stopped").ok();
                             return Ok(());
                         }
                     }
@@ -154,24 +184,30 @@ impl Task for ActiveTask {
                     };
 
                     // 写入成功状态到 context
-                    let mut context_guard = context.write().await;
-                    context_guard.insert(self.id(), Box::new(serde_json::json!({
+                    Self::output_key(&self.id).insert(&mut *context.write().await, serde_json::json!({
                         "status": "completed",
                         "confidence": confidence,
                         "duration_ms": duration_ms,
                         "timestamp": chrono::Utc::now().timestamp_millis()
-                    })));
-                    drop(context_guard);
+                    }));
                     
-                    app_handle.emit("active_task_info", "stopped").ok();
-                    app_handle.emit("task_completed", "active_task_completed").ok();
+                    events.emit("active_task_info", "stopped").ok();
+                    events.emit("task_completed", "active_task_completed").ok();
                     return Ok(());
                 } else if last_signal == ControlSignal::Stopped {
                     // 手动停止的情况
-                    app_handle.emit("active_task_info", "stopped").ok();
+                    events.emit("active_task_info", "stopped").ok();
                     return Ok(());
                 }
             }
         }
     }
-}
\ No newline at end of file
+
+    fn outcome(&self) -> TaskOutcome {
+        if self.timed_out {
+            TaskOutcome::Declined("视觉检测超时".to_string())
+        } else {
+            TaskOutcome::Completed
+        }
+    }
+}
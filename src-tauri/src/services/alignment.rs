@@ -0,0 +1,355 @@
+//! 语音识别结果与参考文本的对齐，用于计算字错误率（CER）、词准确率和句准确率。
+//!
+//! 对齐前会对文本做归一化：全角转半角、英文转小写、去除标点和空白、阿拉伯数字转中文读法，
+//! 避免 "25度" 与 "二十五度"、"打开空调。" 与 "打开空调" 被算作识别错误。
+
+/// 对齐结果中的单个操作
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignOp {
+    Match,
+    Substitution,
+    Deletion,
+    Insertion,
+}
+
+/// 编辑距离对齐结果
+#[derive(Debug, Clone, Default)]
+pub struct Alignment {
+    pub ops: Vec<AlignOp>,
+    pub hits: usize,
+    pub substitutions: usize,
+    pub deletions: usize,
+    pub insertions: usize,
+    /// 参考序列长度（错误率的分母）
+    pub reference_len: usize,
+}
+
+impl Alignment {
+    pub fn errors(&self) -> usize {
+        self.substitutions + self.deletions + self.insertions
+    }
+
+    /// 错误率 (S+D+I)/N；参考为空时，识别结果也为空记0，否则记1
+    pub fn error_rate(&self) -> f64 {
+        if self.reference_len == 0 {
+            return if self.insertions == 0 { 0.0 } else { 1.0 };
+        }
+        self.errors() as f64 / self.reference_len as f64
+    }
+}
+
+/// 带回溯的 Levenshtein 对齐，代价相同时优先匹配/替换，其次删除、插入
+pub fn align<T: PartialEq>(reference: &[T], hypothesis: &[T]) -> Alignment {
+    let n = reference.len();
+    let m = hypothesis.len();
+
+    let mut dp = vec![vec![0usize; m + 1]; n + 1];
+    for (i, row) in dp.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in dp[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=n {
+        for j in 1..=m {
+            let cost = if reference[i - 1] == hypothesis[j - 1] { 0 } else { 1 };
+            dp[i][j] = (dp[i - 1][j - 1] + cost)
+                .min(dp[i - 1][j] + 1)
+                .min(dp[i][j - 1] + 1);
+        }
+    }
+
+    let mut alignment = Alignment {
+        reference_len: n,
+        ..Default::default()
+    };
+    let (mut i, mut j) = (n, m);
+    while i > 0 || j > 0 {
+        if i > 0 && j > 0 {
+            let same = reference[i - 1] == hypothesis[j - 1];
+            let cost = if same { 0 } else { 1 };
+            if dp[i][j] == dp[i - 1][j - 1] + cost {
+                if same {
+                    alignment.ops.push(AlignOp::Match);
+                    alignment.hits += 1;
+                } else {
+                    alignment.ops.push(AlignOp::Substitution);
+                    alignment.substitutions += 1;
+                }
+                i -= 1;
+                j -= 1;
+                continue;
+            }
+        }
+        if i > 0 && dp[i][j] == dp[i - 1][j] + 1 {
+            alignment.ops.push(AlignOp::Deletion);
+            alignment.deletions += 1;
+            i -= 1;
+        } else {
+            alignment.ops.push(AlignOp::Insertion);
+            alignment.insertions += 1;
+            j -= 1;
+        }
+    }
+    alignment.ops.reverse();
+    alignment
+}
+
+/// 单个样本的识别指标
+#[derive(Debug, Clone)]
+pub struct AsrMetrics {
+    /// 字级别对齐（中文按字，英文按字母）
+    pub chars: Alignment,
+    /// 词级别对齐（中文每个字为一个词，英文/数字按空白和标点切分）
+    pub words: Alignment,
+    /// 归一化后整句完全一致
+    pub sentence_correct: bool,
+}
+
+impl AsrMetrics {
+    pub fn compute(reference: &str, hypothesis: &str) -> Self {
+        let reference = normalize_text(reference);
+        let hypothesis = normalize_text(hypothesis);
+
+        let ref_chars: Vec<char> = reference.chars().filter(|c| *c != ' ').collect();
+        let hyp_chars: Vec<char> = hypothesis.chars().filter(|c| *c != ' ').collect();
+        let ref_words = tokenize(&reference);
+        let hyp_words = tokenize(&hypothesis);
+
+        Self {
+            chars: align(&ref_chars, &hyp_chars),
+            words: align(&ref_words, &hyp_words),
+            sentence_correct: ref_chars == hyp_chars,
+        }
+    }
+}
+
+/// 任务级别的汇总指标，错误率按总错误数/总长度计算而不是样本平均
+#[derive(Debug, Clone, Default)]
+pub struct AsrMetricsSummary {
+    pub samples: usize,
+    pub sentence_correct: usize,
+    pub total_chars: usize,
+    pub char_substitutions: usize,
+    pub char_deletions: usize,
+    pub char_insertions: usize,
+    pub total_words: usize,
+    pub word_errors: usize,
+}
+
+impl AsrMetricsSummary {
+    pub fn add(&mut self, metrics: &AsrMetrics) {
+        self.samples += 1;
+        if metrics.sentence_correct {
+            self.sentence_correct += 1;
+        }
+        self.total_chars += metrics.chars.reference_len;
+        self.char_substitutions += metrics.chars.substitutions;
+        self.char_deletions += metrics.chars.deletions;
+        self.char_insertions += metrics.chars.insertions;
+        self.total_words += metrics.words.reference_len;
+        self.word_errors += metrics.words.errors();
+    }
+
+    pub fn character_error_rate(&self) -> Option<f64> {
+        (self.total_chars > 0).then(|| {
+            (self.char_substitutions + self.char_deletions + self.char_insertions) as f64
+                / self.total_chars as f64
+        })
+    }
+
+    /// 词准确率 = 1 - WER，插入过多时下限为0
+    pub fn word_accuracy(&self) -> Option<f64> {
+        (self.total_words > 0)
+            .then(|| (1.0 - self.word_errors as f64 / self.total_words as f64).max(0.0))
+    }
+
+    pub fn sentence_accuracy(&self) -> Option<f64> {
+        (self.samples > 0).then(|| self.sentence_correct as f64 / self.samples as f64)
+    }
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF)
+}
+
+/// 归一化文本：全角转半角、小写、阿拉伯数字转中文、标点替换为空格
+pub fn normalize_text(text: &str) -> String {
+    let half_width: String = text
+        .chars()
+        .map(|c| match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            _ => c,
+        })
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    let mut normalized = String::with_capacity(half_width.len());
+    let chars: Vec<char> = half_width.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_digit() {
+                i += 1;
+            }
+            let integer: String = chars[start..i].iter().collect();
+            // 小数部分逐位读出
+            let mut decimal = String::new();
+            if i + 1 < chars.len() && chars[i] == '.' && chars[i + 1].is_ascii_digit() {
+                i += 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    decimal.push(chars[i]);
+                    i += 1;
+                }
+            }
+            let percent = i < chars.len() && chars[i] == '%';
+            if percent {
+                normalized.push_str("百分之");
+                i += 1;
+            }
+            normalized.push_str(&number_to_chinese(&integer));
+            if !decimal.is_empty() {
+                normalized.push('点');
+                normalized.push_str(&digits_to_chinese(&decimal));
+            }
+            continue;
+        }
+
+        if c.is_alphanumeric() || is_cjk(c) {
+            normalized.push(c);
+        } else if !normalized.ends_with(' ') {
+            normalized.push(' ');
+        }
+        i += 1;
+    }
+    normalized.trim().to_string()
+}
+
+/// 切分为词：中文每个字单独成词，连续的字母/数字为一个词
+fn tokenize(normalized: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    for c in normalized.chars() {
+        if is_cjk(c) || c == ' ' {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            if c != ' ' {
+                tokens.push(c.to_string());
+            }
+        } else {
+            current.push(c);
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+const CHINESE_DIGITS: [char; 10] = ['零', '一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+fn digits_to_chinese(digits: &str) -> String {
+    digits
+        .chars()
+        .filter_map(|c| c.to_digit(10))
+        .map(|d| CHINESE_DIGITS[d as usize])
+        .collect()
+}
+
+/// 整数按中文读法转换（一亿以内），更长的数字（电话号码等）或以0开头的数字逐位读出
+fn number_to_chinese(digits: &str) -> String {
+    let Ok(value) = digits.parse::<u64>() else {
+        return digits_to_chinese(digits);
+    };
+    if value >= 100_000_000 || (digits.len() > 1 && digits.starts_with('0')) {
+        return digits_to_chinese(digits);
+    }
+    if value == 0 {
+        return "零".to_string();
+    }
+
+    fn below_ten_thousand(value: u64, out: &mut String) {
+        const UNITS: [&str; 4] = ["千", "百", "十", ""];
+        let digits = [value / 1000, value / 100 % 10, value / 10 % 10, value % 10];
+        let mut pending_zero = false;
+        for (digit, unit) in digits.iter().zip(UNITS) {
+            if *digit == 0 {
+                pending_zero = !out.is_empty();
+                continue;
+            }
+            if pending_zero {
+                out.push('零');
+                pending_zero = false;
+            }
+            out.push(CHINESE_DIGITS[*digit as usize]);
+            out.push_str(unit);
+        }
+    }
+
+    let mut result = String::new();
+    let high = value / 10_000;
+    let low = value % 10_000;
+    if high > 0 {
+        below_ten_thousand(high, &mut result);
+        result.push('万');
+        if low > 0 && low < 1000 {
+            result.push('零');
+        }
+    }
+    if low > 0 {
+        let mut low_part = String::new();
+        below_ten_thousand(low, &mut low_part);
+        result.push_str(&low_part);
+    }
+
+    // 口语中 "一十二" 读作 "十二"
+    if result.starts_with("一十") {
+        result.remove(0);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_number_normalization() {
+        assert_eq!(normalize_text("空调调到25度。"), "空调调到二十五度");
+        assert_eq!(normalize_text("音量 100%"), "音量 百分之一百");
+        assert_eq!(normalize_text("导航到1005号"), "导航到一千零五号");
+        assert_eq!(normalize_text("Ｈｅｌｌｏ，World!"), "hello world");
+    }
+
+    #[test]
+    fn test_char_alignment_counts() {
+        let metrics = AsrMetrics::compute("打开主驾车窗", "打开驾驶车窗吧");
+        // 打开 | 主→驾 驾→驶 | 车窗 | +吧
+        assert_eq!(metrics.chars.reference_len, 6);
+        assert_eq!(metrics.chars.hits, 4);
+        assert_eq!(metrics.chars.substitutions, 2);
+        assert_eq!(metrics.chars.deletions, 0);
+        assert_eq!(metrics.chars.insertions, 1);
+        assert_eq!(metrics.chars.error_rate(), 0.5);
+        assert!(!metrics.sentence_correct);
+
+        let exact = AsrMetrics::compute("打开空调。", "打开空调");
+        assert!(exact.sentence_correct);
+        assert_eq!(exact.chars.error_rate(), 0.0);
+    }
+
+    #[test]
+    fn test_alignment_ops() {
+        let reference: Vec<char> = "abcd".chars().collect();
+        let hypothesis: Vec<char> = "axd".chars().collect();
+        let alignment = align(&reference, &hypothesis);
+        assert_eq!(alignment.substitutions, 1);
+        assert_eq!(alignment.deletions, 1);
+        assert_eq!(alignment.insertions, 0);
+        assert_eq!(alignment.hits, 2);
+    }
+}
@@ -1,16 +1,16 @@
+use crate::services::event_sink::Events;
 use crate::models::*; // Assuming your model definitions are here
 use crate::config::AppConfig;
-use crate::services::asr_task::AsrTaskOutput;
-use crate::services::workflow::{ControlSignal, Task, WorkflowContext};
+use crate::services::asr_task::AsrTask;
+use crate::services::llm_provider::{create_llm_provider, ChatMessage, LlmOverrides};
+use crate::services::workflow::{ContextKey, ControlSignal, KeyDecl, Task, WorkflowContext};
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use chrono::Utc;
 use reqwest::{self, Client};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
+use std::collections::BTreeMap;
 use std::error::Error;
-use std::sync::Arc;
-use tauri::Emitter;
 use tokio::sync::watch;
 
 // --- Data Structures Mirroring Python Pydantic Models ---
@@ -21,41 +21,12 @@ pub struct EvaluationResult {
     pub assessment: Assessment,
 }
 
-// This is the structure we will serialize as the request body for the LLM.
-#[derive(Serialize)]
-struct LlmRequestPayload<'a> {
-    model: &'a str,
-    messages: Vec<LlmMessage<'a>>,
-    response_format: ResponseFormat,
-}
-
-#[derive(Serialize)]
-struct LlmMessage<'a> {
-    role: &'a str,
-    content: String,
-}
-
-#[derive(Serialize)]
-struct ResponseFormat {
-    #[serde(rename = "type")]
-    format_type: String,
-}
-
-// This is the structure we expect back from the LLM.
-// Note: The actual response has a complex structure, we are interested in the message content.
-#[derive(Deserialize, Debug)]
-struct LlmResponse {
-    choices: Vec<Choice>,
-}
-
-#[derive(Deserialize, Debug)]
-struct Choice {
-    message: MessageContent,
-}
-
-#[derive(Deserialize, Debug)]
-struct MessageContent {
-    content: String,
+/// 一次评估运行使用的大模型参数、提示词模板和评分标准
+#[derive(Debug, Clone)]
+pub struct AnalysisSettings {
+    pub llm_overrides: LlmOverrides,
+    pub prompt_template: PromptTemplate,
+    pub rubric: Rubric,
 }
 
 // --- The Task Implementation ---
@@ -64,111 +35,201 @@ pub struct analysis_task {
     pub id: String,
     pub dependency_id: String,
     pub http_client: Client,
+    /// 本任务的大模型参数（模型、温度、超时等）和提示词模板
+    pub settings: AnalysisSettings,
 }
 
 impl analysis_task {
-    pub fn new(id: String, dependency_id: String, http_client: Client) -> Self {
+    pub fn new(id: String, dependency_id: String, http_client: Client, settings: AnalysisSettings) -> Self {
         Self {
             id,
             dependency_id,
             http_client,
+            settings,
         }
     }
 
-    /// Builds the prompt and calls the OpenRouter LLM API directly.
+    /// 评估结果在上下文中的键（以任务ID命名）
+    pub fn output_key(task_id: &str) -> ContextKey<AnalysisResult> {
+        ContextKey::new(task_id)
+    }
+
+    /// Builds the prompt and calls the configured LLM provider.
     async fn call_llm_analysis(
         &self,
         client: &Client,
         instruction: &str,
         response: &str,
     ) -> Result<EvaluationResult> {
-        log::info!("Calling OpenRouter API for analysis...");
-
         let config = AppConfig::load()
             .map_err(|e| anyhow!("Failed to load configuration: {}", e))?;
-        
-        let api_key = config.openrouter.api_key;
-        if api_key.is_empty() {
-            return Err(anyhow!("OpenRouter API key not configured"));
-        }
+        let provider = create_llm_provider(&config, &self.settings.llm_overrides);
+        log::info!("Calling LLM provider for analysis (model: {})...", provider.model());
 
-        // 1. Construct the detailed prompt
-        let prompt_content = format!(
-            r#"作为车机系统测试专家，请严格评估：
-指令：{instruction}
-响应：{response}
-
-请按以下维度评估并返回严格JSON格式：
-1. semantic_correctness: 评分0-1和评估意见
-2. state_change_confirmation: 评分0-1和评估意见
-3. unambiguous_expression: 评分0-1和评估意见
-4. overall_score: 三个维度的平均分
-5. valid: 测试是否通过
-6. suggestions: 改进建议列表
-
-输出必须为中文
-
-输出必须严格符合以下JSON结构：
-{{
-  "assessment": {{
-    "semantic_correctness": {{"score": 0.0, "comment": "..."}},
-    "state_change_confirmation": {{"score": 0.0, "comment": "..."}},
-    "unambiguous_expression": {{"score": 0.0, "comment": "..."}},
-    "overall_score": 0.0,
-    "valid": false,
-    "suggestions": ["...", "..."]
-  }}
-}}"#
+        // 1. Render the prompt from the task's template and rubric
+        let template = &self.settings.prompt_template;
+        let rubric = &self.settings.rubric;
+        log::info!(
+            "Using prompt template '{}' v{} with rubric '{}'",
+            template.name,
+            template.version,
+            rubric.name
         );
+        let dimensions = rubric.prompt_dimensions();
+        let output_schema = rubric.output_schema();
+        let pass_threshold = rubric.pass_threshold.to_string();
+        let prompt_content = template.render(&[
+            ("instruction", instruction),
+            ("response", response),
+            ("dimensions", &dimensions),
+            ("output_schema", &output_schema),
+            ("pass_threshold", &pass_threshold),
+        ]);
+
+        // 2. Send the request through the provider
+        let content = provider
+            .chat(client, vec![ChatMessage::user(prompt_content)])
+            .await?;
+
+        log::info!("Received successful response from LLM provider.");
+
+        // 3. The actual JSON we need is a string inside the response content, so we parse it again.
+        let mut assessment = parse_assessment(&content, rubric)?;
+
+        // 4. Overall score and pass/fail come from the rubric, not from the model
+        rubric.finalize(&mut assessment);
+        Ok(EvaluationResult { assessment })
+    }
 
-        // 2. Create the request body payload
-        let request_body = LlmRequestPayload {
-            model: "google/gemini-2.5-flash", // Or another model you prefer
-            messages: vec![LlmMessage {
-                role: "user",
-                content: prompt_content,
-            }],
-            response_format: ResponseFormat {
-                format_type: "json_object".to_string(),
-            },
-        };
+    /// 大模型多次重试后仍无法给出有效评估时的结果，记录为 evaluation_error 而不是中断整个工作流
+    fn evaluation_error_result(&self, sample: String, response: String, error: &str) -> AnalysisResult {
+        let rubric = &self.settings.rubric;
+        AnalysisResult {
+            assessment: rubric.failed_assessment(
+                "大模型评估失败",
+                vec![format!("大模型评估失败: {}", error)],
+            ),
+            llm_analysis: None,
+            test_time: Some(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+            audio_file: None,
+            recognition_file: None,
+            device: None,
+            recognition_result: None,
+            insertion_errors: None,
+            deletion_errors: None,
+            substitution_errors: None,
+            total_words: None,
+            reference_text: Some(sample),
+            recognized_text: Some(response),
+            result_status: Some("evaluation_error".to_string()),
+            recognition_time: None,
+            response_time: None,
+            prompt_template_id: Some(self.settings.prompt_template.id),
+            prompt_template_version: Some(self.settings.prompt_template.version),
+            rubric_id: Some(rubric.id),
+            wake_word_id: None,
+            run_index: 0,
+        }
+    }
+}
 
-        // 3. Send the request
-        let res = client
-            .post("https://openrouter.ai/api/v1/chat/completions")
-            .bearer_auth(api_key)
-            .header("Content-Type", "application/json")
-            .json(&request_body)
-            .send()
-            .await
-            .map_err(|e| anyhow!("Failed to send request to OpenRouter: {}", e))?;
-
-        if !res.status().is_success() {
-            let error_body = res
-                .text()
-                .await
-                .unwrap_or_else(|_| "Could not read error body".to_string());
-            log::error!("OpenRouter API returned an error status: {}", error_body);
-            return Err(anyhow!("LLM API Error: {}", error_body));
+/// 从模型输出中截取 JSON：兼容 ```json 代码块以及前后夹杂说明文字的情况
+fn extract_json(content: &str) -> Option<&str> {
+    if let Some(fence_start) = content.find("```") {
+        let after_fence = &content[fence_start + 3..];
+        // 跳过代码块的语言标记（如 ```json）
+        let body_start = after_fence.find('\n').map(|i| i + 1).unwrap_or(0);
+        let body = &after_fence[body_start..];
+        if let Some(fence_end) = body.find("```") {
+            let candidate = body[..fence_end].trim();
+            if candidate.starts_with('{') {
+                return Some(candidate);
+            }
         }
+    }
+
+    let start = content.find('{')?;
+    let end = content.rfind('}')?;
+    (end > start).then(|| &content[start..=end])
+}
+
+/// 分数可以是数值或字符串，百分数形式（"80%"）换算为 0-1
+fn parse_score(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_f64(),
+        serde_json::Value::String(s) => match s.trim().strip_suffix('%') {
+            Some(percent) => percent.trim().parse::<f64>().ok().map(|p| p / 100.0),
+            None => s.trim().parse().ok(),
+        },
+        _ => None,
+    }
+}
 
-        log::info!("Received successful response from OpenRouter.");
-
-        // 4. Parse the response
-        let llm_response = res
-            .json::<LlmResponse>()
-            .await
-            .map_err(|e| anyhow!("Failed to parse LLM response structure: {}", e))?;
-
-        if let Some(choice) = llm_response.choices.into_iter().next() {
-            // The actual JSON we need is a string inside the response content, so we parse it again.
-            let evaluation_result: EvaluationResult = serde_json::from_str(&choice.message.content)
-                .map_err(|e| anyhow!("Failed to parse the inner JSON content from LLM: {}", e))?;
-            Ok(evaluation_result)
-        } else {
-            Err(anyhow!("LLM response contained no choices."))
+/// 解析并校验模型输出的评估结果。
+/// 只接受评分标准中声明的维度，每个维度需要有数值分数；缺失的维度由 `Rubric::finalize` 补0分。
+fn parse_assessment(content: &str, rubric: &Rubric) -> Result<Assessment> {
+    let value: serde_json::Value = match serde_json::from_str(content.trim()) {
+        Ok(value) => value,
+        Err(first_error) => {
+            let candidate = extract_json(content).ok_or_else(|| {
+                anyhow!("LLM output contains no JSON object: {}", first_error)
+            })?;
+            log::warn!("LLM output is not plain JSON, extracted embedded JSON object");
+            serde_json::from_str(candidate)
+                .map_err(|e| anyhow!("Failed to parse the inner JSON content from LLM: {}", e))?
         }
+    };
+
+    // 有的模型会省略外层的 "assessment"
+    let assessment = match value.get("assessment") {
+        Some(inner) => inner,
+        None => &value,
+    };
+    let fields = assessment
+        .as_object()
+        .ok_or_else(|| anyhow!("LLM assessment is not a JSON object"))?;
+
+    let mut dimensions = BTreeMap::new();
+    for dimension in &rubric.dimensions {
+        let Some(item) = fields.get(&dimension.key) else {
+            log::warn!("LLM output is missing dimension '{}'", dimension.key);
+            continue;
+        };
+        // 既支持 {"score": 0.8, "comment": "..."}，也支持直接给出分数
+        let score = item.get("score").and_then(parse_score).or_else(|| parse_score(item));
+        let Some(score) = score else {
+            log::warn!("Dimension '{}' has no numeric score: {}", dimension.key, item);
+            continue;
+        };
+        let comment = item
+            .get("comment")
+            .and_then(|c| c.as_str())
+            .unwrap_or_default()
+            .to_string();
+        dimensions.insert(dimension.key.clone(), AssessmentItem { score, comment });
+    }
+    if dimensions.is_empty() {
+        return Err(anyhow!(
+            "LLM output does not contain any dimension of rubric '{}'",
+            rubric.name
+        ));
     }
+
+    let suggestions = match fields.get("suggestions") {
+        Some(serde_json::Value::Array(items)) => items
+            .iter()
+            .filter_map(|s| s.as_str().map(str::to_string))
+            .collect(),
+        Some(serde_json::Value::String(s)) if !s.is_empty() => vec![s.clone()],
+        _ => Vec::new(),
+    };
+
+    Ok(Assessment {
+        dimensions,
+        overall_score: 0.0,
+        valid: false,
+        suggestions,
+    })
 }
 
 #[async_trait]
@@ -177,46 +238,25 @@ impl Task for analysis_task {
         self.id.clone()
     }
 
+    fn inputs(&self) -> Vec<KeyDecl> {
+        vec![AsrTask::output_key(&self.dependency_id).decl()]
+    }
+
+    fn outputs(&self) -> Vec<KeyDecl> {
+        vec![Self::output_key(&self.id).decl()]
+    }
+
     async fn execute(
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        // 检查是否应该跳过此任务
-        {
-            let context_reader = context.read().await;
-            if let Some(should_skip) = context_reader.get("should_skip_task") {
-                if let Some(flag) = should_skip.downcast_ref::<bool>() {
-                    if *flag {
-                        println!("[AnalysisTask '{}'] Wake detection failed, skipping analysis task", self.id);
-                        return Ok(());
-                    }
-                }
-            }
-        }
-        
         log::info!(
             "[{}] Execute method started. Waiting for 'Running' signal.",
             self.id
         );
 
-        // 检查active_task的结果，如果超时则直接返回
-        let context_reader = context.read().await;
-        for (task_id, result) in context_reader.iter() {
-            if task_id.contains("active_task") {
-                if let Some(result_any) = result.downcast_ref::<serde_json::Value>() {
-                    if let Some(status) = result_any.get("status").and_then(|s| s.as_str()) {
-                        if status == "timeout" {
-                            println!("[{}] Active task timed out, skipping analysis task", self.id);
-                            return Ok(()); // 直接成功退出，让工作流继续
-                        }
-                    }
-                }
-            }
-        }
-        drop(context_reader);
-
         loop {
             let signal = control_rx.borrow().clone();
 
@@ -226,24 +266,14 @@ impl Task for analysis_task {
                         "[{}] Signal is 'Running'. Proceeding with analysis.",
                         self.id
                     );
-                    app_handle.emit("llm_analysis_event", "start")?;
-
-                    let context_reader = context.read().await;
-                    let (sample, response) =
-                        if let Some(data) = context_reader.get(&self.dependency_id) {
-                            if let Some(asr_result) = data.downcast_ref::<AsrTaskOutput>() {
-                                (asr_result.example.clone(), asr_result.response.clone())
-                            } else {
-                                return Err("Could not downcast data to AsrTaskOutput.".into());
-                            }
-                        } else {
-                            return Err(format!(
-                                "Could not get dependency task data for '{}'",
-                                self.dependency_id
-                            )
-                            .into());
-                        };
-                    drop(context_reader);
+                    events.emit("llm_analysis_event", "start")?;
+
+                    let (sample, response) = {
+                        let context_reader = context.read().await;
+                        let asr_result =
+                            AsrTask::output_key(&self.dependency_id).require(&context_reader)?;
+                        (asr_result.example.clone(), asr_result.response.clone())
+                    };
 
                     // --- MODIFIED SECTION ---
                     // Directly call the new function instead of the old one.
@@ -283,24 +313,31 @@ impl Task for analysis_task {
                                 result_status: None,
                                 recognition_time: None,
                                 response_time: None,
+                                prompt_template_id: Some(self.settings.prompt_template.id),
+                                prompt_template_version: Some(self.settings.prompt_template.version),
+                                rubric_id: Some(self.settings.rubric.id),
+                                wake_word_id: None, // 由 finish_task 按本次运行填写
+                                run_index: 0,
                             };
 
-                            app_handle.emit("llm_analysis_result", final_result.clone())?;
-                            context
-                                .write()
-                                .await
-                                .insert(self.id.clone(), Box::new(final_result.clone()));
+                            events.emit("llm_analysis_result", final_result.clone())?;
+                            Self::output_key(&self.id)
+                                .insert(&mut *context.write().await, final_result.clone());
                             return Ok(()); // Task is done, exit successfully.
                         }
                         Err(e) => {
                             log::error!(
-                                "[{}] Model processing failed: {}. Creating fallback result.",
+                                "[{}] Model processing failed: {:#}. Recording evaluation_error result.",
                                 self.id,
                                 e
                             );
-                            // Here you can decide whether to return an error or use a fallback.
-                            // Returning an error is often cleaner.
-                            return Err(e.into());
+                            // 单个样本评估失败不应中断整个批量测试，记录错误结果后继续
+                            let fallback_result =
+                                self.evaluation_error_result(sample, response, &format!("{:#}", e));
+                            events.emit("llm_analysis_result", fallback_result.clone())?;
+                            Self::output_key(&self.id)
+                                .insert(&mut *context.write().await, fallback_result);
+                            return Ok(());
                         }
                     }
                 }
@@ -331,3 +368,38 @@ impl Task for analysis_task {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rubric() -> Rubric {
+        Rubric {
+            id: 0,
+            name: "默认".to_string(),
+            description: None,
+            pass_threshold: 0.6,
+            dimensions: default_rubric_dimensions(),
+            created_at: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_assessment_scores() {
+        let content = r#"评估如下：{"assessment": {
+            "semantic_correctness": {"score": "80%", "comment": "基本正确"},
+            "state_change_confirmation": " 0.5 ",
+            "unambiguous_expression": {"score": 1}
+        }, "suggestions": "补充确认语"}"#;
+        let assessment = parse_assessment(content, &rubric()).unwrap();
+
+        let score = |key: &str| assessment.dimensions[key].score;
+        assert!((score("semantic_correctness") - 0.8).abs() < 1e-9);
+        assert!((score("state_change_confirmation") - 0.5).abs() < 1e-9);
+        assert!((score("unambiguous_expression") - 1.0).abs() < 1e-9);
+        assert_eq!(assessment.dimensions["semantic_correctness"].comment, "基本正确");
+        assert_eq!(assessment.suggestions, vec!["补充确认语".to_string()]);
+
+        assert!(parse_assessment(r#"{"other": 1}"#, &rubric()).is_err());
+    }
+}
@@ -0,0 +1,642 @@
+use async_trait::async_trait;
+use base64::Engine;
+use chrono::prelude::*;
+use futures_util::{stream::SplitSink, SinkExt, StreamExt};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Once;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::{
+    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
+};
+use url::Url;
+use urlencoding::encode;
+
+use crate::config::{AppConfig, AsrConfig, XunfeiConfig};
+
+static INIT_CRYPTO: Once = Once::new();
+
+// --- Constants ---
+const XUNFEI_HOST_URL: &str = "wss://iat-api.xfyun.cn/v2/iat";
+const DEFAULT_LOCAL_URL: &str = "ws://127.0.0.1:2700";
+const DEFAULT_MOCK_DELAY_MS: u64 = 1500;
+
+// ===================================================================
+// 1. 后端抽象 (Backend Abstraction)
+// ===================================================================
+
+/// 识别假设：中间结果会被后续结果覆盖，最终结果表示本次会话结束
+#[derive(Debug, Clone, PartialEq)]
+pub enum AsrHypothesis {
+    Partial(String),
+    Final(String),
+}
+
+/// 一次识别会话的句柄。丢弃句柄即结束会话。
+pub struct AsrSessionHandle {
+    pub hypotheses: mpsc::Receiver<Result<AsrHypothesis, String>>,
+    stop_tx: Option<oneshot::Sender<()>>,
+}
+
+impl AsrSessionHandle {
+    pub fn new(
+        hypotheses: mpsc::Receiver<Result<AsrHypothesis, String>>,
+        stop_tx: oneshot::Sender<()>,
+    ) -> Self {
+        Self {
+            hypotheses,
+            stop_tx: Some(stop_tx),
+        }
+    }
+
+    /// 通知后端音频已结束，后端收尾后给出最终结果
+    pub fn stop(&mut self) {
+        if let Some(tx) = self.stop_tx.take() {
+            tx.send(()).ok();
+        }
+    }
+}
+
+#[async_trait]
+pub trait AsrBackend: Send + Sync {
+    /// 后端名称，用于日志
+    fn name(&self) -> &'static str;
+
+    /// 开始一次识别会话。
+    /// `audio_rx` 输入 16kHz 单声道 f32 PCM 帧；`reference` 为本次会话的期望文本，仅回放后端使用。
+    async fn start_session(
+        &self,
+        reference: &str,
+        audio_rx: mpsc::Receiver<Vec<f32>>,
+    ) -> Result<AsrSessionHandle, Box<dyn Error + Send + Sync>>;
+}
+
+/// 根据配置选择识别后端，未配置时使用讯飞云端识别
+pub fn create_asr_backend(config: &AppConfig) -> Result<Box<dyn AsrBackend>, String> {
+    match config.asr.provider.as_str() {
+        "local" => Ok(Box::new(LocalAsrBackend::new(&config.asr))),
+        "mock" => Ok(Box::new(MockAsrBackend::from_config(&config.asr))),
+        "xunfei" | "" => Ok(Box::new(XunfeiAsrBackend::new(config.xunfei.clone()))),
+        other => Err(format!("未知的ASR后端 '{}'（可选 xunfei、local、mock）", other)),
+    }
+}
+
+fn ensure_crypto_provider() {
+    INIT_CRYPTO.call_once(|| {
+        if let Err(e) = rustls::crypto::ring::default_provider().install_default() {
+            eprintln!("Warning: Failed to install rustls crypto provider: {:?}", e);
+        }
+    });
+}
+
+fn f32_to_i16(samples: &[f32]) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|&s| (s.clamp(-1.0, 1.0) * 32767.0) as i16)
+        .collect()
+}
+
+fn f32_to_pcm_bytes(samples: &[f32]) -> Vec<u8> {
+    f32_to_i16(samples)
+        .iter()
+        .flat_map(|&s| s.to_le_bytes())
+        .collect()
+}
+
+// ===================================================================
+// 2. 讯飞云端识别 (iFlytek IAT)
+// ===================================================================
+
+// --- Authentication Logic ---
+fn build_auth_url(api_key: &str, api_secret: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+    let url = Url::parse(XUNFEI_HOST_URL)?;
+    let host = url.host_str().ok_or("No host in URL")?;
+    let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+    let request_line = format!("GET {} HTTP/1.1", url.path());
+    let signature_origin = format!("host: {}\ndate: {}\n{}", host, date, request_line);
+    type HmacSha256 = Hmac<Sha256>;
+    let mut mac = HmacSha256::new_from_slice(api_secret.as_bytes())?;
+    mac.update(signature_origin.as_bytes());
+    let signature = base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+    let authorization_origin = format!(
+        "api_key=\"{}\", algorithm=\"hmac-sha256\", headers=\"host date request-line\", signature=\"{}\"",
+        api_key, signature
+    );
+    let authorization =
+        base64::engine::general_purpose::STANDARD.encode(authorization_origin.as_bytes());
+    let final_url = format!(
+        "{}?authorization={}&date={}&host={}",
+        XUNFEI_HOST_URL,
+        authorization,
+        encode(&date),
+        host
+    );
+    Ok(final_url)
+}
+
+// --- JSON Data Structures ---
+#[derive(Serialize)]
+struct RequestFrame<'a> {
+    common: Common,
+    business: Business<'a>,
+    data: Data<'a>,
+}
+#[derive(Serialize)]
+struct Common {
+    app_id: String,
+}
+#[derive(Serialize)]
+struct Business<'a> {
+    language: &'a str,
+    domain: &'a str,
+    accent: &'a str,
+    dwa: &'a str,
+}
+#[derive(Serialize)]
+struct Data<'a> {
+    status: i32,
+    format: &'a str,
+    encoding: &'a str,
+    audio: String,
+}
+#[derive(Deserialize, Debug)]
+struct ResponseFrame {
+    code: i32,
+    message: String,
+    sid: String,
+    data: Option<ResponseData>,
+}
+#[derive(Deserialize, Debug)]
+struct ResponseData {
+    status: i32,
+    result: Option<ResultData>,
+}
+#[derive(Deserialize, Debug)]
+struct ResultData {
+    sn: i32,
+    ls: bool,
+    pgs: Option<String>,
+    rg: Option<Vec<i32>>,
+    ws: Vec<Ws>,
+}
+#[derive(Deserialize, Debug)]
+struct Ws {
+    cw: Vec<Cw>,
+}
+#[derive(Deserialize, Debug)]
+struct Cw {
+    w: String,
+}
+
+// --- Dynamic Result Decoder ---
+#[derive(Debug, Clone)]
+struct DecodedText {
+    text: String,
+    sn: i32,
+    deleted: bool,
+}
+struct Decoder {
+    texts: Vec<DecodedText>,
+}
+impl Decoder {
+    fn new() -> Self {
+        Decoder { texts: Vec::new() }
+    }
+    fn decode(&mut self, result: &ResultData) {
+        let mut current_text = String::new();
+        for ws in &result.ws {
+            for cw in &ws.cw {
+                current_text.push_str(&cw.w);
+            }
+        }
+        if let Some(pgs) = &result.pgs {
+            if pgs == "rpl" {
+                if let Some(rg) = &result.rg {
+                    for i in rg[0]..=rg[1] {
+                        if let Some(t) = self.texts.iter_mut().find(|t| t.sn == i) {
+                            t.deleted = true;
+                        }
+                    }
+                }
+            }
+        }
+        let decoded = DecodedText {
+            text: current_text,
+            sn: result.sn,
+            deleted: false,
+        };
+        if let Some(t) = self.texts.iter_mut().find(|t| t.sn == result.sn) {
+            *t = decoded;
+        } else {
+            self.texts.push(decoded);
+        }
+    }
+    fn get_full_text(&self) -> String {
+        let mut full_text = String::new();
+        let mut sorted_texts = self.texts.clone();
+        sorted_texts.sort_by_key(|t| t.sn);
+        for text in sorted_texts {
+            if !text.deleted {
+                full_text.push_str(&text.text);
+            }
+        }
+        full_text
+    }
+}
+
+// --- Audio Sending Task ---
+async fn send_audio(
+    mut sender: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+    mut receiver: mpsc::Receiver<Vec<f32>>,
+    appid: String,
+    mut stop_rx: oneshot::Receiver<()>,
+) {
+    let mut status = 0;
+    loop {
+        tokio::select! {
+            biased;
+            _ = &mut stop_rx => {
+                println!("\nASR send_audio task stopping, sending final frame...");
+                let last_frame = json!({ "data": { "status": 2, "audio": "" } });
+                if sender.send(Message::Text(last_frame.to_string().into())).await.is_err() { /* ... */ }
+                sender.close().await.ok();
+                println!("ASR send_audio task finished.");
+                return;
+            }
+            Some(chunk_f32) = receiver.recv() => {
+                 let chunk_bytes = f32_to_pcm_bytes(&chunk_f32);
+                 let audio_base64 = base64::engine::general_purpose::STANDARD.encode(&chunk_bytes);
+                 let msg = match status {
+                     0 => {
+                         let req = RequestFrame { common: Common { app_id: appid.clone() }, business: Business { language: "zh_cn", domain: "iat", accent: "mandarin", dwa: "wpgs" }, data: Data { status: 0, format: "audio/L16;rate=16000", encoding: "raw", audio: audio_base64 }, };
+                         status = 1;
+                         serde_json::to_string(&req).unwrap()
+                     }
+                     1 => { json!({ "data": { "status": 1, "audio": audio_base64 } }).to_string() }
+                     _ => unreachable!(),
+                 };
+                 if sender.send(Message::Text(msg.into())).await.is_err() { return; }
+            }
+        }
+    }
+}
+
+/// 讯飞语音听写（流式版）
+pub struct XunfeiAsrBackend {
+    config: XunfeiConfig,
+}
+
+impl XunfeiAsrBackend {
+    pub fn new(config: XunfeiConfig) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl AsrBackend for XunfeiAsrBackend {
+    fn name(&self) -> &'static str {
+        "xunfei"
+    }
+
+    async fn start_session(
+        &self,
+        _reference: &str,
+        audio_rx: mpsc::Receiver<Vec<f32>>,
+    ) -> Result<AsrSessionHandle, Box<dyn Error + Send + Sync>> {
+        ensure_crypto_provider();
+
+        let auth_url = build_auth_url(&self.config.api_key, &self.config.api_secret)?;
+        let (ws_stream, _) = connect_async(auth_url).await?;
+        let (ws_sender, mut ws_receiver) = ws_stream.split();
+        let (stop_tx, stop_rx) = oneshot::channel();
+        let (hypothesis_tx, hypothesis_rx) = mpsc::channel(32);
+
+        tokio::spawn(send_audio(ws_sender, audio_rx, self.config.appid.clone(), stop_rx));
+
+        tokio::spawn(async move {
+            let mut decoder = Decoder::new();
+            while let Some(msg) = ws_receiver.next().await {
+                match msg {
+                    Ok(Message::Text(text)) => {
+                        let resp: ResponseFrame = match serde_json::from_str(&text) {
+                            Ok(resp) => resp,
+                            Err(e) => {
+                                hypothesis_tx.send(Err(format!("Invalid ASR response: {}", e))).await.ok();
+                                return;
+                            }
+                        };
+                        if resp.code != 0 {
+                            println!("ASR Server error {}: {}", resp.code, resp.message);
+                            hypothesis_tx
+                                .send(Err(format!("Server error {}: {}", resp.code, resp.message)))
+                                .await
+                                .ok();
+                            return;
+                        }
+                        if let Some(data) = resp.data {
+                            if let Some(result) = data.result {
+                                decoder.decode(&result);
+                                let partial = AsrHypothesis::Partial(decoder.get_full_text());
+                                if hypothesis_tx.send(Ok(partial)).await.is_err() {
+                                    return;
+                                }
+                            }
+                            if data.status == 2 {
+                                let final_text = AsrHypothesis::Final(decoder.get_full_text());
+                                hypothesis_tx.send(Ok(final_text)).await.ok();
+                                return;
+                            }
+                        }
+                    }
+                    Ok(Message::Close(_)) => break,
+                    Err(e) => {
+                        hypothesis_tx.send(Err(e.to_string())).await.ok();
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+            hypothesis_tx.send(Err("WebSocket closed unexpectedly".to_string())).await.ok();
+        });
+
+        Ok(AsrSessionHandle::new(hypothesis_rx, stop_tx))
+    }
+}
+
+// ===================================================================
+// 3. 本地离线引擎 (Vosk 兼容的 WebSocket 协议)
+// ===================================================================
+
+/// 本地离线识别引擎，使用 Vosk/sherpa 等服务通用的 WebSocket 协议：
+/// 先发送 `{"config": {"sample_rate": 16000}}`，随后发送 16bit PCM 二进制帧，结束时发送 `{"eof": 1}`；
+/// 服务端返回 `{"partial": "..."}` 作为中间结果，`{"text": "..."}` 作为最终结果。
+pub struct LocalAsrBackend {
+    url: String,
+}
+
+impl LocalAsrBackend {
+    pub fn new(config: &AsrConfig) -> Self {
+        let url = if config.local_url.trim().is_empty() {
+            DEFAULT_LOCAL_URL.to_string()
+        } else {
+            config.local_url.trim().to_string()
+        };
+        Self { url }
+    }
+}
+
+/// 本地引擎的中文结果通常以空格分词，去掉中文字符两侧的空格
+fn join_local_tokens(text: &str) -> String {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let mut joined = String::new();
+    for (i, token) in tokens.iter().enumerate() {
+        if i > 0 {
+            let prev_ascii = tokens[i - 1].chars().last().is_some_and(|c| c.is_ascii());
+            let next_ascii = token.chars().next().is_some_and(|c| c.is_ascii());
+            if prev_ascii && next_ascii {
+                joined.push(' ');
+            }
+        }
+        joined.push_str(token);
+    }
+    joined
+}
+
+#[async_trait]
+impl AsrBackend for LocalAsrBackend {
+    fn name(&self) -> &'static str {
+        "local"
+    }
+
+    async fn start_session(
+        &self,
+        _reference: &str,
+        mut audio_rx: mpsc::Receiver<Vec<f32>>,
+    ) -> Result<AsrSessionHandle, Box<dyn Error + Send + Sync>> {
+        ensure_crypto_provider();
+
+        let (ws_stream, _) = connect_async(self.url.as_str())
+            .await
+            .map_err(|e| format!("无法连接本地ASR引擎 {}: {}", self.url, e))?;
+        let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+        let config_frame = json!({ "config": { "sample_rate": 16000 } });
+        ws_sender
+            .send(Message::Text(config_frame.to_string().into()))
+            .await?;
+
+        let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+        let (hypothesis_tx, hypothesis_rx) = mpsc::channel(32);
+
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    biased;
+                    _ = &mut stop_rx => {
+                        let eof = json!({ "eof": 1 });
+                        ws_sender.send(Message::Text(eof.to_string().into())).await.ok();
+                        return;
+                    }
+                    Some(chunk_f32) = audio_rx.recv() => {
+                        let chunk_bytes = f32_to_pcm_bytes(&chunk_f32);
+                        if ws_sender.send(Message::Binary(chunk_bytes.into())).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+        });
+
+        tokio::spawn(async move {
+            while let Some(msg) = ws_receiver.next().await {
+                match msg {
+                    Ok(Message::Text(text)) => {
+                        let value: serde_json::Value = match serde_json::from_str(&text) {
+                            Ok(value) => value,
+                            Err(e) => {
+                                hypothesis_tx.send(Err(format!("Invalid ASR response: {}", e))).await.ok();
+                                return;
+                            }
+                        };
+                        if let Some(final_text) = value.get("text").and_then(|t| t.as_str()) {
+                            // 引擎在静音段也会返回空的最终结果，忽略它们
+                            let final_text = join_local_tokens(final_text);
+                            if !final_text.is_empty() {
+                                hypothesis_tx.send(Ok(AsrHypothesis::Final(final_text))).await.ok();
+                                return;
+                            }
+                        } else if let Some(partial) = value.get("partial").and_then(|t| t.as_str()) {
+                            let partial = join_local_tokens(partial);
+                            if !partial.is_empty()
+                                && hypothesis_tx.send(Ok(AsrHypothesis::Partial(partial))).await.is_err()
+                            {
+                                return;
+                            }
+                        }
+                    }
+                    Ok(Message::Close(_)) => break,
+                    Err(e) => {
+                        hypothesis_tx.send(Err(e.to_string())).await.ok();
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+            hypothesis_tx.send(Err("Local ASR engine closed the connection".to_string())).await.ok();
+        });
+
+        Ok(AsrSessionHandle::new(hypothesis_rx, stop_tx))
+    }
+}
+
+// ===================================================================
+// 4. 回放/模拟后端 (File Replay / Mock)
+// ===================================================================
+
+/// 不依赖网络和识别引擎的模拟后端。
+/// 若配置了回放文件（`{"期望文本": "识别结果"}` 形式的 JSON），按期望文本查表返回；否则直接回显期望文本。
+pub struct MockAsrBackend {
+    replay: HashMap<String, String>,
+    delay: Duration,
+}
+
+impl MockAsrBackend {
+    pub fn from_config(config: &AsrConfig) -> Self {
+        let mut replay = HashMap::new();
+        if !config.replay_file.trim().is_empty() {
+            match std::fs::read_to_string(config.replay_file.trim()) {
+                Ok(content) => match serde_json::from_str::<HashMap<String, String>>(&content) {
+                    Ok(map) => replay = map,
+                    Err(e) => log::warn!("ASR回放文件格式错误 {}: {}", config.replay_file, e),
+                },
+                Err(e) => log::warn!("无法读取ASR回放文件 {}: {}", config.replay_file, e),
+            }
+        }
+        let delay_ms = if config.mock_delay_ms == 0 {
+            DEFAULT_MOCK_DELAY_MS
+        } else {
+            config.mock_delay_ms
+        };
+        Self {
+            replay,
+            delay: Duration::from_millis(delay_ms),
+        }
+    }
+}
+
+#[async_trait]
+impl AsrBackend for MockAsrBackend {
+    fn name(&self) -> &'static str {
+        "mock"
+    }
+
+    async fn start_session(
+        &self,
+        reference: &str,
+        mut audio_rx: mpsc::Receiver<Vec<f32>>,
+    ) -> Result<AsrSessionHandle, Box<dyn Error + Send + Sync>> {
+        let transcript = self
+            .replay
+            .get(reference.trim())
+            .cloned()
+            .unwrap_or_else(|| reference.to_string());
+        let delay = self.delay;
+        let (stop_tx, stop_rx) = oneshot::channel::<()>();
+        let (hypothesis_tx, hypothesis_rx) = mpsc::channel(4);
+
+        // 持续消费麦克风帧，避免采集线程阻塞
+        tokio::spawn(async move { while audio_rx.recv().await.is_some() {} });
+
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = stop_rx => {}
+            }
+            if hypothesis_tx
+                .send(Ok(AsrHypothesis::Partial(transcript.clone())))
+                .await
+                .is_ok()
+            {
+                hypothesis_tx.send(Ok(AsrHypothesis::Final(transcript))).await.ok();
+            }
+        });
+
+        Ok(AsrSessionHandle::new(hypothesis_rx, stop_tx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mock_config(replay_file: &str, mock_delay_ms: u64) -> AsrConfig {
+        AsrConfig {
+            provider: "mock".to_string(),
+            replay_file: replay_file.to_string(),
+            mock_delay_ms,
+            ..AsrConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_join_local_tokens() {
+        assert_eq!(join_local_tokens("打开 主驾 车窗"), "打开主驾车窗");
+        assert_eq!(join_local_tokens("turn on the AC"), "turn on the AC");
+        assert_eq!(join_local_tokens("打开 wifi 设置"), "打开wifi设置");
+        assert_eq!(join_local_tokens("  播放  音乐 "), "播放音乐");
+        assert_eq!(join_local_tokens(""), "");
+        assert_eq!(join_local_tokens("   "), "");
+    }
+
+    #[tokio::test]
+    async fn test_mock_backend_replay() {
+        let replay_file = std::env::temp_dir().join("asr_backend_test_replay.json");
+        std::fs::write(&replay_file, r#"{"打开车窗": "打开窗户"}"#).unwrap();
+        let backend = MockAsrBackend::from_config(&mock_config(&replay_file.to_string_lossy(), 10));
+        std::fs::remove_file(&replay_file).ok();
+        assert_eq!(backend.delay, Duration::from_millis(10));
+
+        // 回放文件中有的期望文本返回对应结果，没有的回显期望文本
+        for (reference, expected) in [("打开车窗", "打开窗户"), ("关闭空调", "关闭空调")] {
+            let (_audio_tx, audio_rx) = mpsc::channel(1);
+            let mut session = backend.start_session(reference, audio_rx).await.unwrap();
+            assert_eq!(
+                session.hypotheses.recv().await,
+                Some(Ok(AsrHypothesis::Partial(expected.to_string())))
+            );
+            assert_eq!(
+                session.hypotheses.recv().await,
+                Some(Ok(AsrHypothesis::Final(expected.to_string())))
+            );
+        }
+    }
+
+    #[test]
+    fn test_mock_backend_defaults() {
+        let backend = MockAsrBackend::from_config(&mock_config("", 0));
+        assert_eq!(backend.delay, Duration::from_millis(DEFAULT_MOCK_DELAY_MS));
+        assert!(backend.replay.is_empty());
+
+        // 回放文件无法读取时回显期望文本
+        let backend = MockAsrBackend::from_config(&mock_config("/nonexistent/replay.json", 0));
+        assert!(backend.replay.is_empty());
+    }
+
+    #[test]
+    fn test_create_asr_backend() {
+        let mut config = AppConfig::default();
+        for (provider, name) in [("local", "local"), ("mock", "mock"), ("xunfei", "xunfei"), ("", "xunfei")] {
+            config.asr.provider = provider.to_string();
+            assert_eq!(create_asr_backend(&config).unwrap().name(), name);
+        }
+
+        config.asr.provider = "whisper".to_string();
+        let error = create_asr_backend(&config).err().unwrap();
+        assert!(error.contains("未知的ASR后端 'whisper'"), "{}", error);
+    }
+}
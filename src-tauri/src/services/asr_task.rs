@@ -1,17 +1,10 @@
 // [!BEGIN!]
 use async_trait::async_trait;
-use base64::Engine;
-use chrono::prelude::*;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::SampleFormat;
-use futures_util::{stream::SplitSink, SinkExt, StreamExt};
-use hmac::{Hmac, Mac};
 use rubato::{
     Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction,
 };
-use serde::{Deserialize, Serialize};
-use serde_json::json;
-use sha2::Sha256;
 use std::cell::RefCell;
 use std::error::Error;
 // Add necessary imports for threading
@@ -19,161 +12,17 @@ use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread::{self, JoinHandle};
 use std::time::Duration;
-use tauri::Emitter;
-use tokio::net::TcpStream;
-use tokio::sync::{mpsc, oneshot, watch};
-use tokio_tungstenite::{
-    connect_async, tungstenite::protocol::Message, MaybeTlsStream, WebSocketStream,
-};
-use url::Url;
-use urlencoding::encode;
+use tokio::sync::{mpsc, watch};
 
 // Import your project's workflow definitions
-use crate::services::workflow::{ControlSignal, Task, WorkflowContext};
-use std::sync::Once;
+use crate::services::event_sink::Events;
+use crate::services::asr_backend::{create_asr_backend, AsrHypothesis, AsrSessionHandle};
+use crate::services::workflow::{
+    resources, ContextKey, ControlSignal, KeyDecl, Task, TaskPolicy, WorkflowContext,
+};
 
-static INIT_CRYPTO: Once = Once::new();
 // --- Constants ---
-const HOST_URL: &str = "wss://iat-api.xfyun.cn/v2/iat";
 const SAMPLES_PER_FRAME: usize = 640;
-const INTERVAL_MS: u64 = 40;
-
-// --- Authentication Logic [No changes] ---
-fn build_auth_url(api_key: &str, api_secret: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
-    let url = Url::parse(HOST_URL)?;
-    let host = url.host_str().ok_or("No host in URL")?;
-    let date = Utc::now().format("%a, %d %b %Y %H:%M:%S GMT").to_string();
-    let request_line = format!("GET {} HTTP/1.1", url.path());
-    let signature_origin = format!("host: {}\ndate: {}\n{}", host, date, request_line);
-    type HmacSha256 = Hmac<Sha256>;
-    let mut mac = HmacSha256::new_from_slice(api_secret.as_bytes())?;
-    mac.update(signature_origin.as_bytes());
-    let signature = base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
-    let authorization_origin = format!(
-        "api_key=\"{}\", algorithm=\"hmac-sha256\", headers=\"host date request-line\", signature=\"{}\"",
-        api_key, signature
-    );
-    let authorization =
-        base64::engine::general_purpose::STANDARD.encode(authorization_origin.as_bytes());
-    let final_url = format!(
-        "{}?authorization={}&date={}&host={}",
-        HOST_URL,
-        authorization,
-        encode(&date),
-        host
-    );
-    Ok(final_url)
-}
-
-// --- JSON Data Structures [No changes] ---
-#[derive(Serialize)]
-struct RequestFrame<'a> {
-    common: Common,
-    business: Business<'a>,
-    data: Data<'a>,
-}
-#[derive(Serialize)]
-struct Common {
-    app_id: String,
-}
-#[derive(Serialize)]
-struct Business<'a> {
-    language: &'a str,
-    domain: &'a str,
-    accent: &'a str,
-    dwa: &'a str,
-}
-#[derive(Serialize)]
-struct Data<'a> {
-    status: i32,
-    format: &'a str,
-    encoding: &'a str,
-    audio: String,
-}
-#[derive(Deserialize, Debug)]
-struct ResponseFrame {
-    code: i32,
-    message: String,
-    sid: String,
-    data: Option<ResponseData>,
-}
-#[derive(Deserialize, Debug)]
-struct ResponseData {
-    status: i32,
-    result: Option<ResultData>,
-}
-#[derive(Deserialize, Debug)]
-struct ResultData {
-    sn: i32,
-    ls: bool,
-    pgs: Option<String>,
-    rg: Option<Vec<i32>>,
-    ws: Vec<Ws>,
-}
-#[derive(Deserialize, Debug)]
-struct Ws {
-    cw: Vec<Cw>,
-}
-#[derive(Deserialize, Debug)]
-struct Cw {
-    w: String,
-}
-
-// --- Dynamic Result Decoder [No changes] ---
-#[derive(Debug, Clone)]
-struct DecodedText {
-    text: String,
-    sn: i32,
-    deleted: bool,
-}
-struct Decoder {
-    texts: Vec<DecodedText>,
-}
-impl Decoder {
-    fn new() -> Self {
-        Decoder { texts: Vec::new() }
-    }
-    fn decode(&mut self, result: &ResultData) {
-        let mut current_text = String::new();
-        for ws in &result.ws {
-            for cw in &ws.cw {
-                current_text.push_str(&cw.w);
-            }
-        }
-        if let Some(pgs) = &result.pgs {
-            if pgs == "rpl" {
-                if let Some(rg) = &result.rg {
-                    for i in rg[0]..=rg[1] {
-                        if let Some(t) = self.texts.iter_mut().find(|t| t.sn == i) {
-                            t.deleted = true;
-                        }
-                    }
-                }
-            }
-        }
-        let decoded = DecodedText {
-            text: current_text,
-            sn: result.sn,
-            deleted: false,
-        };
-        if let Some(t) = self.texts.iter_mut().find(|t| t.sn == result.sn) {
-            *t = decoded;
-        } else {
-            self.texts.push(decoded);
-        }
-    }
-    fn get_full_text(&self) -> String {
-        let mut full_text = String::new();
-        let mut sorted_texts = self.texts.clone();
-        sorted_texts.sort_by_key(|t| t.sn);
-        for text in sorted_texts {
-            if !text.deleted {
-                full_text.push_str(&text.text);
-            }
-        }
-        full_text
-    }
-}
 
 // --- Audio Processing Logic [No changes] ---
 #[derive(Debug)]
@@ -194,12 +43,6 @@ impl std::fmt::Display for AudioError {
     }
 }
 impl std::error::Error for AudioError {}
-fn f32_to_i16(samples: &[f32]) -> Vec<i16> {
-    samples
-        .iter()
-        .map(|&s| (s.clamp(-1.0, 1.0) * 32767.0) as i16)
-        .collect()
-}
 fn find_best_f32_input_config(
     device: &cpal::Device,
 ) -> Result<(cpal::StreamConfig, cpal::SampleFormat), Box<dyn Error + Send + Sync>> {
@@ -356,44 +199,6 @@ fn audio_capture_thread(
     Ok(())
 }
 
-// --- Audio Sending Task [No changes] ---
-async fn send_audio(
-    mut sender: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
-    mut receiver: mpsc::Receiver<Vec<f32>>,
-    appid: String,
-    mut stop_rx: oneshot::Receiver<()>,
-) {
-    let mut status = 0;
-    loop {
-        tokio::select! {
-            biased;
-            _ = &mut stop_rx => {
-                println!("\nASR send_audio task stopping, sending final frame...");
-                let last_frame = json!({ "data": { "status": 2, "audio": "" } });
-                if sender.send(Message::Text(last_frame.to_string().into())).await.is_err() { /* ... */ }
-                sender.close().await.ok();
-                println!("ASR send_audio task finished.");
-                return;
-            }
-            Some(chunk_f32) = receiver.recv() => {
-                 let chunk_i16 = f32_to_i16(&chunk_f32);
-                 let chunk_bytes: Vec<u8> = chunk_i16.iter().flat_map(|&s| s.to_le_bytes()).collect();
-                 let audio_base64 = base64::engine::general_purpose::STANDARD.encode(&chunk_bytes);
-                 let msg = match status {
-                     0 => {
-                         let req = RequestFrame { common: Common { app_id: appid.clone() }, business: Business { language: "zh_cn", domain: "iat", accent: "mandarin", dwa: "wpgs" }, data: Data { status: 0, format: "audio/L16;rate=16000", encoding: "raw", audio: audio_base64 }, };
-                         status = 1;
-                         serde_json::to_string(&req).unwrap()
-                     }
-                     1 => { json!({ "data": { "status": 1, "audio": audio_base64 } }).to_string() }
-                     _ => unreachable!(),
-                 };
-                 if sender.send(Message::Text(msg.into())).await.is_err() { return; }
-            }
-        }
-    }
-}
-
 // --- Task Definition ---
 #[derive(Debug, Clone)]
 pub struct AsrTaskOutput {
@@ -407,9 +212,8 @@ pub struct AsrTaskOutput {
 // AsrSession no longer holds the cpal::Stream.
 // It holds the tools to manage the capture thread.
 struct AsrSession {
-    ws_receiver: futures_util::stream::SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
-    stop_audio_tx: oneshot::Sender<()>,
-    decoder: Decoder,
+    // 识别后端会话，丢弃即断开连接
+    backend_session: AsrSessionHandle,
     // NEW: Thread management
     stop_capture_signal: Arc<AtomicBool>,
     capture_thread_handle: Option<JoinHandle<()>>,
@@ -419,6 +223,7 @@ struct AsrSession {
 impl Drop for AsrSession {
     fn drop(&mut self) {
         println!("Dropping AsrSession, stopping audio capture thread...");
+        self.backend_session.stop();
         self.stop_capture_signal.store(true, Ordering::SeqCst);
         if let Some(handle) = self.capture_thread_handle.take() {
             let _ = handle
@@ -434,6 +239,9 @@ pub struct AsrTask {
     session: Option<AsrSession>,
 }
 
+/// 单次识别的最长时间，WebSocket 无响应时由工作流取消任务（会话在 Drop 时关闭）
+const ASR_TASK_TIMEOUT: Duration = Duration::from_secs(60);
+
 impl AsrTask {
     // 创建一个公有的 `new` 函数
     pub fn new(id: String, example: String) -> Self {
@@ -444,6 +252,16 @@ impl AsrTask {
             session: None,
         }
     }
+
+    /// 识别结果在上下文中的键（以任务ID命名）
+    pub fn output_key(task_id: &str) -> ContextKey<AsrTaskOutput> {
+        ContextKey::new(task_id)
+    }
+
+    /// 识别依赖刚播放过的音频，重试无法重新采集，因此只限制时间不重试
+    pub fn policy() -> TaskPolicy {
+        TaskPolicy::default().with_timeout(ASR_TASK_TIMEOUT)
+    }
 }
 
 #[async_trait]
@@ -452,50 +270,29 @@ impl Task for AsrTask {
         self.id.clone()
     }
 
+    fn outputs(&self) -> Vec<KeyDecl> {
+        vec![Self::output_key(&self.id).decl()]
+    }
+
+    /// 识别期间播放的其他音频会被一起录入，同时占用扬声器
+    fn resources(&self) -> Vec<&'static str> {
+        vec![resources::MICROPHONE, resources::SPEAKER]
+    }
+
+    /// 超时取消的执行留下的会话可能已经挂起，重试时关闭并重新建立
+    fn reset(&mut self) {
+        self.session = None;
+    }
+
     async fn execute(
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        // 检查是否应该跳过此任务
-        {
-            let context_reader = context.read().await;
-            if let Some(should_skip) = context_reader.get("should_skip_task") {
-                if let Some(flag) = should_skip.downcast_ref::<bool>() {
-                    if *flag {
-                        println!("[ASRTask '{}'] Wake detection failed, skipping ASR task", self.id);
-                        return Ok(());
-                    }
-                }
-            }
-        }
-        
         println!("开始ASR任务: [{}].", self.id);
         let start_timestamp = chrono::Utc::now().timestamp_millis();
 
-        // 检查active_task的结果，如果超时则直接返回
-        let context_reader = context.read().await;
-        for (task_id, result) in context_reader.iter() {
-            if task_id.contains("active_task") {
-                if let Some(result_any) = result.downcast_ref::<serde_json::Value>() {
-                    if let Some(status) = result_any.get("status").and_then(|s| s.as_str()) {
-                        if status == "timeout" {
-                            println!("[{}] Active task timed out, skipping ASR task", self.id);
-                            return Ok(()); // 直接成功退出，让工作流继续
-                        }
-                    }
-                }
-            }
-        }
-        drop(context_reader);
-
-        INIT_CRYPTO.call_once(|| {
-            if let Err(e) = rustls::crypto::ring::default_provider().install_default() {
-                eprintln!("Warning: Failed to install rustls crypto provider: {:?}", e);
-            }
-        });
-
         loop {
             let signal = *control_rx.borrow();
             match signal {
@@ -504,16 +301,21 @@ impl Task for AsrTask {
                         println!("[{}] Initializing ASR session...", self.id);
                     
                         //控制ocr任务同步开始
-                        // app_handle.emit("ocr_event", "start".to_string()).ok();
+                        // events.emit("ocr_event", "start".to_string()).ok();
 
                         let config = crate::config::AppConfig::load()
                             .map_err(|e| anyhow::anyhow!("Failed to load configuration: {}", e))?;  
-                        let appid = config.xunfei.appid;
-                        let api_key = config.xunfei.api_key;
-                        let api_secret = config.xunfei.api_secret;
+                        let backend = create_asr_backend(&config)?;
+                        println!("[{}] Using ASR backend: {}", self.id, backend.name());
 
                         let (audio_sender, audio_receiver) = mpsc::channel::<Vec<f32>>(100);
 
+                        // 先建立识别会话，连接失败时不启动采集线程
+                        let backend_session = backend
+                            .start_session(&self.example, audio_receiver)
+                            .await
+                            .map_err(|e| format!("ASR后端 '{}' 启动失败: {}", backend.name(), e))?;
+
                         // --- Spawn the dedicated audio capture thread ---
                         let stop_capture_signal = Arc::new(AtomicBool::new(false));
                         let signal_clone = stop_capture_signal.clone();
@@ -523,17 +325,9 @@ impl Task for AsrTask {
                             }
                         });
                         // ---
-                        let auth_url = build_auth_url(&api_key, &api_secret)?;
-                        let (ws_stream, _) = connect_async(auth_url).await?;
-                        let (ws_sender, ws_receiver) = ws_stream.split();
-                        let (stop_audio_tx, stop_audio_rx) = oneshot::channel();
-
-                        tokio::spawn(send_audio(ws_sender, audio_receiver, appid, stop_audio_rx));
 
                         self.session = Some(AsrSession {
-                            ws_receiver,
-                            stop_audio_tx,
-                            decoder: Decoder::new(),
+                            backend_session,
                             stop_capture_signal,
                             capture_thread_handle: Some(capture_thread_handle),
                         });
@@ -542,47 +336,34 @@ impl Task for AsrTask {
                     if let Some(session) = self.session.as_mut() {
                         tokio::select! {
                             Ok(_) = control_rx.changed() => { continue; }
-                            Some(msg) = session.ws_receiver.next() => {
-                                match msg {
-                                    Ok(Message::Text(text)) => {
-                                        let resp: ResponseFrame = serde_json::from_str(&text)?;
-                                        if resp.code != 0 {
-                                            println!("ASR Server error {}: {}", resp.code, resp.message);
-                                            return Err(format!("Server error {}: {}", resp.code, resp.message).into());
-                                        }
-                                        if let Some(data) = resp.data {
-                                            if let Some(result) = data.result {
-                                                session.decoder.decode(&result);
-                                                let intermediate_text = session.decoder.get_full_text();
-                                                app_handle.emit("asr_intermediate_result", &intermediate_text).ok();
-                                                print!("\rASR intermediate: {}", intermediate_text);
-                                            }
-                                            if data.status == 2 {
-                                                println!("\n[{}] ASR session completed by server.", self.id);
-                                                let final_text = session.decoder.get_full_text();
-                                                let end_timestamp = chrono::Utc::now().timestamp_millis();
-                                                let output = AsrTaskOutput {
-                                                    example: self.example.clone(),
-                                                    response: final_text,
-                                                    duration_ms: (end_timestamp - start_timestamp) as u64,
-                                                    start_time: start_timestamp,
-                                                    end_time: end_timestamp
-                                                };
-                                                context.write().await.insert(self.id(), Box::new(output));
-                                                self.session = None;
-                                                app_handle.emit("asr_event", "complete".to_string()).ok();
-                                                //同时结束osr任务
-                                                // app_handle.emit("ocr_event", "stop".to_string()).ok();
-                                                return Ok(());
-                                            }
-                                        }
+                            hypothesis = session.backend_session.hypotheses.recv() => {
+                                match hypothesis {
+                                    Some(Ok(AsrHypothesis::Partial(intermediate_text))) => {
+                                        events.emit("asr_intermediate_result", &intermediate_text).ok();
+                                        print!("\rASR intermediate: {}", intermediate_text);
+                                    }
+                                    Some(Ok(AsrHypothesis::Final(final_text))) => {
+                                        println!("\n[{}] ASR session completed by server.", self.id);
+                                        let end_timestamp = chrono::Utc::now().timestamp_millis();
+                                        let output = AsrTaskOutput {
+                                            example: self.example.clone(),
+                                            response: final_text,
+                                            duration_ms: (end_timestamp - start_timestamp) as u64,
+                                            start_time: start_timestamp,
+                                            end_time: end_timestamp
+                                        };
+                                        Self::output_key(&self.id).insert(&mut *context.write().await, output);
+                                        self.session = None;
+                                        events.emit("asr_event", "complete".to_string()).ok();
+                                        //同时结束osr任务
+                                        // events.emit("ocr_event", "stop".to_string()).ok();
+                                        return Ok(());
                                     }
-                                    Ok(Message::Close(_)) => { self.session = None;
-                                        // app_handle.emit("ocr_event", "stop".to_string()).ok();
-                                        return Err("WebSocket closed unexpectedly".into()); }
-                                    Err(e) => { self.session = None; //app_handle.emit("ocr_event", "stop".to_string()).ok();
-                                    return Err(Box::new(e)); }
-                                    _ => {}
+                                    Some(Err(e)) => { self.session = None; //events.emit("ocr_event", "stop".to_string()).ok();
+                                        return Err(e.into()); }
+                                    None => { self.session = None;
+                                        // events.emit("ocr_event", "stop".to_string()).ok();
+                                        return Err("ASR backend closed unexpectedly".into()); }
                                 }
                             }
                         }
@@ -592,8 +373,8 @@ impl Task for AsrTask {
                     println!("[{}] Paused.", self.id);
                     if self.session.is_some() {
                         println!("[{}] Tearing down session for pause.", self.id);
-                        app_handle.emit("asr_event", "pause".to_string()).ok();
-                        //app_handle.emit("ocr_event", "stop".to_string()).ok();
+                        events.emit("asr_event", "pause".to_string()).ok();
+                        //events.emit("ocr_event", "stop".to_string()).ok();
                         self.session = None;
                     }
                     if control_rx.changed().await.is_err() {
@@ -1,10 +1,12 @@
 use async_trait::async_trait;
-use tauri::Emitter;
 use tokio::sync::watch;
 use tokio::time;
 use std::error::Error;
 use chrono::Utc;
-use crate::services::workflow::{ControlSignal, Task, WorkflowContext};
+use crate::services::event_sink::Events;
+use crate::services::workflow::{
+    resources, ContextKey, ControlSignal, KeyDecl, Task, TaskPolicy, WorkflowContext,
+};
 use crate::models::TimingData;
 
 pub struct audio_task {
@@ -13,32 +15,39 @@ pub struct audio_task {
     pub url : Option<String>,
 }
 
+impl audio_task {
+    /// 语音指令播放起止时间在上下文中的键
+    pub fn timing_key(task_id: &str) -> ContextKey<TimingData> {
+        ContextKey::new(format!("{}_timing", task_id))
+    }
+
+    /// 播放失败（设备被占用等）重试一次。
+    /// 播放在 block_in_place 中同步执行，工作流无法中途取消，因此不设超时。
+    pub fn policy() -> TaskPolicy {
+        TaskPolicy::default().with_retries(1, time::Duration::from_secs(1))
+    }
+}
+
 #[async_trait]
 impl Task for audio_task {
     fn id(&self) -> String {
         self.id.clone()
     }
 
+    fn outputs(&self) -> Vec<KeyDecl> {
+        vec![Self::timing_key(&self.id).decl()]
+    }
+
+    fn resources(&self) -> Vec<&'static str> {
+        vec![resources::SPEAKER]
+    }
+
     async fn execute(
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        // 检查是否应该跳过此任务（仅对语音指令播放任务，不是唤醒词播放任务）
-        // 通过任务ID判断：wakeword_task_开头的任务是唤醒词播放任务，其他的是语音指令播放任务
-        if !self.id.starts_with("wakeword_task_") { // 语音指令播放任务
-            let context_reader = context.read().await;
-            if let Some(should_skip) = context_reader.get("should_skip_task") {
-                if let Some(flag) = should_skip.downcast_ref::<bool>() {
-                    if *flag {
-                        println!("[AudioTask '{}'] Wake detection failed, skipping audio task", self.id);
-                        return Ok(());
-                    }
-                }
-            }
-        }
-        
         println!("开始播放音频文件 {} .", self.keyword);
         
         // 主控制循环 - 持续检查控制信号
@@ -79,12 +88,9 @@ impl Task for audio_task {
                             timing.voice_command_start_time = Some(voice_start_time);
                             timing.voice_command_end_time = Some(voice_end_time);
                             
-                            context.write().await.insert(
-                                format!("{}_timing", self.id),
-                                Box::new(timing)
-                            );
+                            Self::timing_key(&self.id).insert(&mut *context.write().await, timing);
 
-                            app_handle.emit("task_completed", "wake_task_completed").unwrap();
+                            events.emit("task_completed", "wake_task_completed").unwrap();
                             
                             return Ok(());
                         }
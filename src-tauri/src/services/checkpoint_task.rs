@@ -1,23 +1,25 @@
 use async_trait::async_trait;
 use tokio::sync::watch;
 use std::error::Error;
-use tauri::Emitter;
-use crate::services::workflow::{ControlSignal, Task, WorkflowContext};
-use crate::services::asr_task::AsrTaskOutput;
+use crate::services::event_sink::Events;
+use crate::services::workflow::{ContextKey, ControlSignal, KeyDecl, Task, TaskOutcome, WorkflowContext};
+use crate::services::active_task::ActiveTask;
+use crate::services::asr_task::AsrTask;
 
 pub struct checkpoint_task {
     pub id: String,
-    pub active_task_id: String,
+    pub active_task_id: Option<String>, // 未配置视觉检测时仅凭唤醒回复判断
     pub asr_task_id: String,
     pub expected_responses: Vec<String>,
     pub sample_index: Option<u32>, // 添加样本索引
     pub wake_word_text: Option<String>, // 添加唤醒词文本
+    wake_success: Option<bool>, // 本次判断结果，作为任务结论供条件依赖使用
 }
 
 impl checkpoint_task {
     pub fn new(
         id: String,
-        active_task_id: String,
+        active_task_id: Option<String>,
         asr_task_id: String,
         expected_responses: Vec<String>,
     ) -> Self {
@@ -28,12 +30,13 @@ impl checkpoint_task {
             expected_responses,
             sample_index: None,
             wake_word_text: None,
+            wake_success: None,
         }
     }
 
     pub fn new_with_sample_info(
         id: String,
-        active_task_id: String,
+        active_task_id: Option<String>,
         asr_task_id: String,
         expected_responses: Vec<String>,
         sample_index: u32,
@@ -46,9 +49,15 @@ impl checkpoint_task {
             expected_responses,
             sample_index: Some(sample_index),
             wake_word_text: Some(wake_word_text),
+            wake_success: None,
         }
     }
 
+    /// 唤醒检测结论在上下文中的键，finish_task 据此保存唤醒失败结果
+    pub fn wake_success_key() -> ContextKey<bool> {
+        ContextKey::new("wake_detection_success")
+    }
+
     /// 检查ASR结果是否匹配预期回复
     fn check_asr_response(&self, asr_result: &str) -> bool {
         let response = asr_result.trim().to_lowercase();
@@ -92,32 +101,32 @@ impl checkpoint_task {
         let mut asr_duration: Option<u64> = None;
         
         // 检查 Active 任务结果
-        if let Some(active_task_result_any) = context_guard.get(&self.active_task_id) {
-            if let Some(active_task_result) = active_task_result_any.downcast_ref::<serde_json::Value>() {
-                if let Some(status) = active_task_result.get("status").and_then(|s| s.as_str()) {
-                    if status == "completed" {
-                        active_task_completed = true;
-                    }
-                }
-                
-                // 获取active_task的duration
-                if let Some(duration) = active_task_result.get("duration_ms").and_then(|d| d.as_u64()) {
-                    wake_duration = Some(duration);
+        let active_task_result = self
+            .active_task_id
+            .as_ref()
+            .and_then(|active_task_id| ActiveTask::output_key(active_task_id).get(&context_guard));
+        if let Some(active_task_result) = active_task_result {
+            if let Some(status) = active_task_result.get("status").and_then(|s| s.as_str()) {
+                if status == "completed" {
+                    active_task_completed = true;
                 }
             }
+            
+            // 获取active_task的duration
+            if let Some(duration) = active_task_result.get("duration_ms").and_then(|d| d.as_u64()) {
+                wake_duration = Some(duration);
+            }
         }
         
         // 检查 ASR 任务结果
-        if let Some(asr_task_result_any) = context_guard.get(&self.asr_task_id) {
-            if let Some(asr_task_output) = asr_task_result_any.downcast_ref::<AsrTaskOutput>() {
-                let response = asr_task_output.response.trim();
-                if !response.is_empty() {
-                    asr_result = Some(asr_task_output.response.clone());
-                }
-                
-                // 获取asr_task的duration
-                asr_duration = Some(asr_task_output.duration_ms);
+        if let Some(asr_task_output) = AsrTask::output_key(&self.asr_task_id).get(&context_guard) {
+            let response = asr_task_output.response.trim();
+            if !response.is_empty() {
+                asr_result = Some(asr_task_output.response.clone());
             }
+            
+            // 获取asr_task的duration
+            asr_duration = Some(asr_task_output.duration_ms);
         }
         
         // 判断成功条件：
@@ -145,7 +154,7 @@ impl checkpoint_task {
     }
 }
 
-// 该任务的作用是判断唤醒检测是否成功，并设置相应的标志
+// 该任务的作用是判断唤醒检测是否成功，唤醒失败时以 Declined 结束，下游的条件依赖据此跳过语音指令
 
 #[async_trait]
 impl Task for checkpoint_task {
@@ -153,39 +162,40 @@ impl Task for checkpoint_task {
         self.id.clone()
     }
 
+    fn inputs(&self) -> Vec<KeyDecl> {
+        let mut inputs = vec![AsrTask::output_key(&self.asr_task_id).decl()];
+        if let Some(active_task_id) = &self.active_task_id {
+            inputs.push(ActiveTask::output_key(active_task_id).decl());
+        }
+        inputs
+    }
+
+    fn outputs(&self) -> Vec<KeyDecl> {
+        vec![Self::wake_success_key().decl()]
+    }
+
     async fn execute(
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         println!("[CheckpointTask '{}'] Starting wake detection success check", self.id);
 
         // 判断唤醒检测是否成功并获取duration
         let (wake_success, final_duration_ms) = self.check_wake_detection_success(&context).await;
+        self.wake_success = Some(wake_success);
         
-        // 在 context 中设置唤醒检测结果
-        {
-            let mut context_guard = context.write().await;
-            context_guard.insert(
-                "wake_detection_success".to_string(),
-                Box::new(wake_success),
-            );
-            
-            // 如果唤醒失败，设置跳过标志
-            if !wake_success {
-                context_guard.insert(
-                    "should_skip_task".to_string(),
-                    Box::new(true),
-                );
-                println!("[CheckpointTask '{}'] Wake detection failed, setting skip flag", self.id);
-            } else {
-                println!("[CheckpointTask '{}'] Wake detection succeeded", self.id);
-            }
+        // 在 context 中设置唤醒检测结果，供 finish_task 保存
+        Self::wake_success_key().insert(&mut *context.write().await, wake_success);
+        if wake_success {
+            println!("[CheckpointTask '{}'] Wake detection succeeded", self.id);
+        } else {
+            println!("[CheckpointTask '{}'] Wake detection failed", self.id);
         }
 
         // 发送唤醒检测结果到前端（包含duration）
-        app_handle
+        events
             .emit(
                 "wake_detection_result",
                 serde_json::json!({
@@ -221,4 +231,11 @@ impl Task for checkpoint_task {
             }
         }
     }
-}
\ No newline at end of file
+
+    fn outcome(&self) -> TaskOutcome {
+        match self.wake_success {
+            Some(false) => TaskOutcome::Declined("唤醒检测失败".to_string()),
+            _ => TaskOutcome::Completed,
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! 定时执行使用的 cron 表达式解析。
+//!
+//! 支持标准 5 段格式 `分 时 日 月 周`，每段可以是 `*`、数字、范围 `a-b`、步长 `*/n` 或 `a-b/n`，
+//! 以及用逗号分隔的组合；周的取值为 0-7，0 和 7 都表示周日。
+//! 与常见的 cron 实现一致，日和周都不是 `*` 时满足其一即可。
+
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+
+/// 查找下一次执行时间时最多向后搜索的天数，超出仍找不到时视为永不执行（例如 2 月 30 日）
+const MAX_SEARCH_DAYS: i64 = 366 * 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days_of_month: u64,
+    months: u64,
+    days_of_week: u64,
+    /// 日一段为 `*`
+    any_day_of_month: bool,
+    /// 周一段为 `*`
+    any_day_of_week: bool,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(format!(
+                "cron 表达式应包含 5 段（分 时 日 月 周），实际为 {} 段: '{}'",
+                fields.len(),
+                expression
+            ));
+        }
+        let mut days_of_week = parse_field(fields[4], 0, 7, "周")?;
+        // 7 与 0 都表示周日
+        if days_of_week & (1 << 7) != 0 {
+            days_of_week |= 1;
+        }
+        Ok(Self {
+            minutes: parse_field(fields[0], 0, 59, "分")?,
+            hours: parse_field(fields[1], 0, 23, "时")?,
+            days_of_month: parse_field(fields[2], 1, 31, "日")?,
+            months: parse_field(fields[3], 1, 12, "月")?,
+            days_of_week,
+            any_day_of_month: fields[2] == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+
+    /// 严格晚于 `after` 的下一次执行时间（精确到分钟），找不到时返回 None
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let mut date = start.date();
+        let last_date = date + Duration::days(MAX_SEARCH_DAYS);
+        while date <= last_date {
+            if self.matches_date(date) {
+                let from = if date == start.date() { start.time() } else { NaiveTime::MIN };
+                if let Some(time) = self.first_time_from(from) {
+                    return Some(date.and_time(time));
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if !contains(self.months, date.month()) {
+            return false;
+        }
+        let day_of_month = contains(self.days_of_month, date.day());
+        let day_of_week = contains(self.days_of_week, date.weekday().num_days_from_sunday());
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (true, false) => day_of_week,
+            (false, true) => day_of_month,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+
+    /// 当天不早于 `from` 的第一个执行时刻
+    fn first_time_from(&self, from: NaiveTime) -> Option<NaiveTime> {
+        (from.hour()..24)
+            .filter(|hour| contains(self.hours, *hour))
+            .find_map(|hour| {
+                let first_minute = if hour == from.hour() { from.minute() } else { 0 };
+                (first_minute..60)
+                    .find(|minute| contains(self.minutes, *minute))
+                    .and_then(|minute| NaiveTime::from_hms_opt(hour, minute, 0))
+            })
+    }
+}
+
+fn contains(set: u64, value: u32) -> bool {
+    set & (1 << value) != 0
+}
+
+/// 解析一段，返回取值的位集合
+fn parse_field(field: &str, min: u32, max: u32, name: &str) -> Result<u64, String> {
+    let invalid = || format!("cron 表达式的{}段无效: '{}'（取值范围 {}-{}）", name, field, min, max);
+    let mut set = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step.parse().map_err(|_| invalid())?;
+                if step == 0 {
+                    return Err(invalid());
+                }
+                (range, step)
+            }
+            None => (part, 1),
+        };
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (
+                    start.parse().map_err(|_| invalid())?,
+                    end.parse().map_err(|_| invalid())?,
+                ),
+                // `a/n` 表示从 a 开始到最大值
+                None => {
+                    let start: u32 = range.parse().map_err(|_| invalid())?;
+                    (start, if part.contains('/') { max } else { start })
+                }
+            },
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for value in (start..=end).step_by(step as usize) {
+            set |= 1 << value;
+        }
+    }
+    Ok(set)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> Option<NaiveDateTime> {
+        CronSchedule::parse(expression).unwrap().next_after(at(after))
+    }
+
+    #[test]
+    fn test_next_after() {
+        assert_eq!(next("*/15 * * * *", "2026-10-17 10:07"), Some(at("2026-10-17 10:15")));
+        // 恰好在执行时刻时取下一次
+        assert_eq!(next("0 2 * * *", "2026-10-17 02:00"), Some(at("2026-10-18 02:00")));
+        assert_eq!(next("30 23 * * *", "2026-12-31 23:45"), Some(at("2027-01-01 23:30")));
+        // 2026-10-17 是周六，工作日下一次为周一
+        assert_eq!(next("30 2 * * 1-5", "2026-10-17 03:00"), Some(at("2026-10-19 02:30")));
+        assert_eq!(next("0 9 * * 7", "2026-10-17 10:00"), Some(at("2026-10-18 09:00")));
+        assert_eq!(next("0 0 29 2 *", "2026-03-01 00:00"), Some(at("2028-02-29 00:00")));
+        assert_eq!(next("0 8,20 * 1,7 *", "2026-10-17 10:00"), Some(at("2027-01-01 08:00")));
+    }
+
+    #[test]
+    fn test_day_of_month_or_day_of_week() {
+        // 日和周都指定时满足其一即可：1 号或周一
+        assert_eq!(next("0 0 1 * 1", "2026-10-17 00:00"), Some(at("2026-10-19 00:00")));
+        assert_eq!(next("0 0 1 * 1", "2026-10-27 00:00"), Some(at("2026-11-01 00:00")));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        for expression in ["", "* * * *", "60 * * * *", "* 24 * * *", "* * 0 * *", "*/0 * * * *", "5-1 * * * *", "a * * * *"] {
+            assert!(CronSchedule::parse(expression).is_err(), "{}", expression);
+        }
+        assert_eq!(next("0 0 30 2 *", "2026-10-17 00:00"), None);
+    }
+}
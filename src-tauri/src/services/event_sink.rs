@@ -0,0 +1,70 @@
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::Emitter;
+use tokio::sync::mpsc;
+
+/// 任务和工作流发送事件的出口。
+/// GUI 中由 `tauri::AppHandle` 转发给前端；无界面运行时写入日志；测试中收集到通道里检查。
+pub trait EventSink: Send + Sync {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) -> Result<(), String>;
+}
+
+/// 任务持有的事件出口，克隆开销很小
+pub type Events = Arc<dyn EventSink>;
+
+impl dyn EventSink {
+    /// 序列化后发送，用法与 `tauri::Emitter::emit` 相同
+    pub fn emit<S: Serialize>(&self, event: &str, payload: S) -> Result<(), String> {
+        let payload = serde_json::to_value(payload).map_err(|e| format!("事件序列化失败: {}", e))?;
+        self.emit_value(event, payload)
+    }
+}
+
+impl EventSink for tauri::AppHandle {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) -> Result<(), String> {
+        Emitter::emit(self, event, payload).map_err(|e| e.to_string())
+    }
+}
+
+/// 只把事件写入日志，用于无界面运行
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogEventSink;
+
+impl EventSink for LogEventSink {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) -> Result<(), String> {
+        log::info!("[Event] {}: {}", event, payload);
+        Ok(())
+    }
+}
+
+/// 发送到通道中的一个事件
+#[derive(Debug, Clone)]
+pub struct EmittedEvent {
+    pub event: String,
+    pub payload: serde_json::Value,
+}
+
+/// 把事件转发到通道，测试中用来检查任务发出的事件。接收端关闭后事件被丢弃
+#[derive(Debug, Clone)]
+pub struct ChannelEventSink {
+    tx: mpsc::UnboundedSender<EmittedEvent>,
+}
+
+impl ChannelEventSink {
+    pub fn channel() -> (Events, mpsc::UnboundedReceiver<EmittedEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        (Arc::new(ChannelEventSink { tx }), rx)
+    }
+}
+
+impl EventSink for ChannelEventSink {
+    fn emit_value(&self, event: &str, payload: serde_json::Value) -> Result<(), String> {
+        self.tx
+            .send(EmittedEvent {
+                event: event.to_string(),
+                payload,
+            })
+            .ok();
+        Ok(())
+    }
+}
@@ -1,14 +1,19 @@
 use async_trait::async_trait;
 use std::error::Error;
 use std::sync::Arc;
-use tauri::Emitter;
 use tokio::sync::watch;
 
+use crate::services::event_sink::Events;
 use crate::db::database::DatabaseService; // 假设您的数据库服务类型路径是这个
-use crate::models::{AnalysisResult, MachineResponseData, TimingData};
-use crate::services::asr_task::AsrTaskOutput;
+use crate::models::{AnalysisResult, MachineResponseData, SampleRunKey, TimingData};
+use crate::services::alignment::{AsrMetrics, AsrMetricsSummary};
+use crate::services::active_task::ActiveTask;
+use crate::services::analysis_task::analysis_task;
+use crate::services::asr_task::AsrTask;
+use crate::services::audio_task::audio_task;
+use crate::services::checkpoint_task::checkpoint_task;
 use crate::services::ocr_session::OcrSessionResult;
-use crate::services::workflow::{ControlSignal, Task, WorkflowContext};
+use crate::services::workflow::{ControlSignal, KeyDecl, Task, WorkflowContext};
 
 pub struct finish_task {
     pub id: String,
@@ -22,6 +27,9 @@ pub struct finish_task {
     // 唤醒检测相关字段
     pub active_task_id: Option<String>, // 用于唤醒检测的active_task_id
     pub wake_word_id: Option<u32>,      // 唤醒词ID
+    // 样本测试中本次运行使用的唤醒词（0 表示未记录）和重复序号
+    pub run_wake_word_id: u32,
+    pub run_index: u32,
     // 任务持有所需的数据库服务
     pub db: Arc<DatabaseService>,
 }
@@ -49,6 +57,8 @@ impl finish_task {
             audio_task_id,
             active_task_id: None, // 初始化为None
             wake_word_id: None,   // 初始化为None
+            run_wake_word_id: 0,
+            run_index: 0,
             db,                   // 存储传入的数据库服务
         }
     }
@@ -76,6 +86,8 @@ impl finish_task {
             audio_task_id,
             active_task_id: None, // 初始化为None
             wake_word_id: None,   // 初始化为None
+            run_wake_word_id: 0,
+            run_index: 0,
             db,
         }
     }
@@ -85,6 +97,7 @@ impl finish_task {
         id: String,
         task_id: i64,
         active_task_id: String,
+        asr_dependency_id: String,
         wake_word_id: u32,
         db: Arc<DatabaseService>,
     ) -> Self {
@@ -92,13 +105,15 @@ impl finish_task {
             id,
             task_id,
             sample_id: 0, // 唤醒检测不使用sample_id
-            asr_dependency_id: String::new(),
+            asr_dependency_id,
             analysis_dependency_id: String::new(),
             audio_ocr_dependency_id: String::new(),
             ocr_dependency_id: Some(String::new()),
             audio_task_id: String::new(),
             active_task_id: Some(active_task_id),
             wake_word_id: Some(wake_word_id),
+            run_wake_word_id: 0,
+            run_index: 0,
             db,
         }
     }
@@ -126,16 +141,38 @@ impl finish_task {
             audio_task_id,
             active_task_id: Some(active_task_id), // 同时设置这个字段以便后续检查
             wake_word_id: None,                   // 混合工作流不使用wake_word_id
+            run_wake_word_id: 0,
+            run_index: 0,
             db,
         }
     }
 
+    /// 记录本次样本运行使用的唤醒词和重复序号，结果按 (样本, 唤醒词, 重复序号) 分别保存
+    pub fn with_run(mut self, wake_word_id: u32, run_index: u32) -> Self {
+        self.run_wake_word_id = wake_word_id;
+        self.run_index = run_index;
+        self
+    }
+
+    fn run_key(&self) -> SampleRunKey {
+        SampleRunKey::new(
+            self.task_id,
+            self.sample_id as i64,
+            self.run_wake_word_id,
+            self.run_index,
+        )
+    }
+
+    fn run_wake_word(&self) -> Option<u32> {
+        (self.run_wake_word_id > 0).then_some(self.run_wake_word_id)
+    }
+
     /// 核心数据处理逻辑
-    /// 此函数现在使用 self.db，不再需要从 app_handle 获取 state
+    /// 此函数使用 self.db 访问数据库，不依赖全局 AppState
     async fn process_and_save_data(
         &self,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         log::info!(
             "[{}] 开始处理和保存数据 - 样本ID: {}",
@@ -144,45 +181,29 @@ impl finish_task {
         );
 
         // 检查是否为唤醒失败的情况
-        let context_reader = context.read().await;
-        let wake_detection_failed = if let Some(success_flag) = context_reader.get("wake_detection_success") {
-            if let Some(success) = success_flag.downcast_ref::<bool>() {
-                !success
-            } else {
-                false
-            }
-        } else {
-            false
-        };
-        drop(context_reader);
+        let wake_detection_failed = checkpoint_task::wake_success_key()
+            .get(&*context.read().await)
+            .is_some_and(|success| !success);
 
         // 如果唤醒失败且不是唤醒检测专用任务，则保存唤醒失败的结果
         if wake_detection_failed && self.wake_word_id.is_none() && self.sample_id > 0 {
             log::info!("[{}] 唤醒检测失败，保存唤醒失败结果", self.id);
             
-            // 创建唤醒失败的分析结果
+            // 创建唤醒失败的分析结果，维度取自任务的评分标准
+            let rubric = self
+                .db
+                .get_task_rubric(self.task_id)
+                .await
+                .map_err(|e| format!("[{}] 获取评分标准失败: {}", self.id, e))?;
             let failed_analysis_result = AnalysisResult {
-                assessment: crate::models::Assessment {
-                    semantic_correctness: crate::models::AssessmentItem {
-                        score: 0.0,
-                        comment: "唤醒检测失败，无法进行语义正确性评估".to_string(),
-                    },
-                    state_change_confirmation: crate::models::AssessmentItem {
-                        score: 0.0,
-                        comment: "唤醒检测失败，无法进行状态变化确认评估".to_string(),
-                    },
-                    unambiguous_expression: crate::models::AssessmentItem {
-                        score: 0.0,
-                        comment: "唤醒检测失败，无法进行表达明确性评估".to_string(),
-                    },
-                    overall_score: 0.0,
-                    valid: false,
-                    suggestions: vec![
+                assessment: rubric.failed_assessment(
+                    "唤醒检测失败",
+                    vec![
                         "检查唤醒词配置".to_string(),
                         "检查视觉检测配置".to_string(),
                         "确保车机系统处于可唤醒状态".to_string(),
                     ],
-                },
+                ),
                 llm_analysis: Some(crate::models::LlmAnalysis {
                     title: "唤醒失败".to_string(),
                     content: "语音唤醒检测失败，车机系统未能成功响应唤醒词".to_string(),
@@ -203,6 +224,11 @@ impl finish_task {
                 result_status: Some("wake_failed".to_string()),
                 recognition_time: None,
                 response_time: None,
+                prompt_template_id: None,
+                prompt_template_version: None,
+                rubric_id: Some(rubric.id),
+                wake_word_id: self.run_wake_word(),
+                run_index: self.run_index,
             };
 
             // 创建唤醒失败的机器响应数据
@@ -216,17 +242,17 @@ impl finish_task {
 
             // 保存到数据库
             self.db
-                .save_machine_response(self.task_id, self.sample_id as i64, &failed_response_data)
+                .save_machine_response(&self.run_key(), &failed_response_data)
                 .await
                 .map_err(|e| format!("[{}] 保存唤醒失败机器响应失败: {}", self.id, e))?;
 
             self.db
-                .save_analysis_result(self.task_id, self.sample_id as i64, &failed_analysis_result)
+                .save_analysis_result(&self.run_key(), &failed_analysis_result)
                 .await
                 .map_err(|e| format!("[{}] 保存唤醒失败分析结果失败: {}", self.id, e))?;
 
             self.db
-                .save_timing_data(self.task_id, self.sample_id as i64, &empty_timing_data)
+                .save_timing_data(&self.run_key(), &empty_timing_data)
                 .await
                 .map_err(|e| format!("[{}] 保存唤醒失败时间数据失败: {}", self.id, e))?;
 
@@ -234,12 +260,14 @@ impl finish_task {
             let event_data = serde_json::json!({
                 "task_id": self.task_id,
                 "sample_id": self.sample_id,
+                "wake_word_id": self.run_wake_word(),
+                "run_index": self.run_index,
                 "response": "唤醒检测失败",
                 "analysis_score": 0.0,
                 "status": "wake_failed"
             });
 
-            app_handle.emit("finish_task_complete", event_data)?;
+            events.emit("finish_task_complete", event_data)?;
 
             log::info!("[{}] 唤醒失败结果保存完成", self.id);
             return Ok(());
@@ -253,49 +281,22 @@ impl finish_task {
                 let context_reader = context.read().await;
 
                 // 从active_task结果中获取数据
-                let active_task_result = if let Some(data) = context_reader.get(active_task_id) {
-                    if let Some(result) = data.downcast_ref::<serde_json::Value>() {
-                        log::info!("[{}] 获取到active_task结果: {:?}", self.id, result);
-                        result.clone()
-                    } else {
-                        return Err(format!("[{}] 无法将active_task数据转换为JSON", self.id).into());
-                    }
-                } else {
-                    return Err(format!(
-                        "[{}] 在context中找不到active_task '{}'",
-                        self.id, active_task_id
-                    )
-                    .into());
-                };
+                let active_task_result = ActiveTask::output_key(active_task_id)
+                    .require(&context_reader)
+                    .map_err(|e| format!("[{}] {}", self.id, e))?
+                    .clone();
+                log::info!("[{}] 获取到active_task结果: {:?}", self.id, active_task_result);
 
                 // 从asr_task结果中获取数据
                 let mut asr_result: Option<String> = None;
-                if let Some(asr_task_id) = context_reader.keys().find(|k| k.contains("asr_task")) {
-                    if let Some(asr_data) = context_reader.get(asr_task_id) {
-                        // 首先尝试获取 AsrTaskOutput 类型的结果
-                        if let Some(asr_task_output) = asr_data.downcast_ref::<AsrTaskOutput>() {
-                            // 检查ASR结果是否为空字符串或只包含空白字符
-                            let response = asr_task_output.response.trim();
-                            if !response.is_empty() {
-                                asr_result = Some(asr_task_output.response.clone());
-                                log::info!("[{}] 获取到ASR结果: {}", self.id, asr_task_output.response);
-                            } else {
-                                log::info!("[{}] ASR任务完成但结果为空", self.id);
-                            }
-                        } else {
-                            // 如果不是 AsrTaskOutput，尝试获取 JSON 格式的结果
-                            if let Some(asr_json) = asr_data.downcast_ref::<serde_json::Value>() {
-                                if let Some(response) = asr_json.get("response").and_then(|r| r.as_str()) {
-                                    let trimmed_response = response.trim();
-                                    if !trimmed_response.is_empty() {
-                                        asr_result = Some(response.to_string());
-                                        log::info!("[{}] 获取到ASR JSON结果: {}", self.id, response);
-                                    } else {
-                                        log::info!("[{}] ASR任务完成但结果为空", self.id);
-                                    }
-                                }
-                            }
-                        }
+                if let Some(asr_task_output) = AsrTask::output_key(&self.asr_dependency_id).get(&context_reader) {
+                    // 检查ASR结果是否为空字符串或只包含空白字符
+                    let response = asr_task_output.response.trim();
+                    if !response.is_empty() {
+                        asr_result = Some(asr_task_output.response.clone());
+                        log::info!("[{}] 获取到ASR结果: {}", self.id, asr_task_output.response);
+                    } else {
+                        log::info!("[{}] ASR任务完成但结果为空", self.id);
                     }
                 }
 
@@ -361,7 +362,7 @@ impl finish_task {
                     "duration_ms": duration_ms
                 });
 
-                app_handle.emit("wake_detection_result_saved", event_data)?;
+                events.emit("wake_detection_result_saved", event_data)?;
                 return Ok(());
             }
         }
@@ -369,17 +370,13 @@ impl finish_task {
         // 检查混合工作流中的active_task状态
         let context_reader = context.read().await;
         let active_task_failed = if let Some(active_task_id) = &self.active_task_id {
-            if let Some(data) = context_reader.get(active_task_id) {
-                if let Some(result) = data.downcast_ref::<serde_json::Value>() {
-                    let status = result
-                        .get("status")
-                        .and_then(|s| s.as_str())
-                        .unwrap_or("unknown");
-                    log::info!("[{}] 检查active_task状态: {}", self.id, status);
-                    status == "timeout" || status == "failed"
-                } else {
-                    false
-                }
+            if let Some(result) = ActiveTask::output_key(active_task_id).get(&context_reader) {
+                let status = result
+                    .get("status")
+                    .and_then(|s| s.as_str())
+                    .unwrap_or("unknown");
+                log::info!("[{}] 检查active_task状态: {}", self.id, status);
+                status == "timeout" || status == "failed"
             } else {
                 log::warn!("[{}] 未找到active_task结果: {}", self.id, active_task_id);
                 false
@@ -389,47 +386,24 @@ impl finish_task {
         };
 
         // 1. 从 asr_task 结果中提取数据
-        let response_data = if let Some(data) = context_reader.get(&self.asr_dependency_id) {
-            if let Some(asr_result) = data.downcast_ref::<AsrTaskOutput>() {
-                MachineResponseData {
-                    text: asr_result.response.clone(),
-                    connected: true, // 假设连接正常
-                }
-            } else {
-                return Err(format!("[{}] 无法将context数据转换为 AsrTaskOutput", self.id).into());
-            }
-        } else {
-            return Err(format!(
-                "[{}] 在context中找不到依赖项 '{}'",
-                self.id, self.asr_dependency_id
-            )
-            .into());
+        let asr_result = AsrTask::output_key(&self.asr_dependency_id)
+            .require(&context_reader)
+            .map_err(|e| format!("[{}] {}", self.id, e))?;
+        let response_data = MachineResponseData {
+            text: asr_result.response.clone(),
+            connected: true, // 假设连接正常
         };
 
         // 2. 从 analysis_task 结果中提取数据
-        let mut analysis_result = if let Some(data) =
-            context_reader.get(&self.analysis_dependency_id)
-        {
-            if let Some(result) = data.downcast_ref::<AnalysisResult>() {
-                result.clone()
-            } else {
-                return Err(format!("[{}] 无法将context数据转换为 AnalysisResult", self.id).into());
-            }
-        } else {
-            return Err(format!(
-                "[{}] 在context中找不到依赖项 '{}'",
-                self.id, self.analysis_dependency_id
-            )
-            .into());
-        };
+        let mut analysis_result = analysis_task::output_key(&self.analysis_dependency_id)
+            .require(&context_reader)
+            .map_err(|e| format!("[{}] {}", self.id, e))?
+            .clone();
 
         // 3. 从audio_task获取语音指令时间
-        let audio_timing =
-            if let Some(data) = context_reader.get(&format!("{}_timing", self.audio_task_id)) {
-                data.downcast_ref::<TimingData>().cloned()
-            } else {
-                None
-            };
+        let audio_timing = audio_task::timing_key(&self.audio_task_id)
+            .get(&context_reader)
+            .cloned();
 
         // // 4. 从audio_ocr_task获取首字上屏时间和文本稳定时间
         // let audio_ocr_result = if let Some(data) = context_reader.get(&self.audio_ocr_dependency_id)
@@ -499,7 +473,7 @@ impl finish_task {
         // 3. 直接使用 self.db 进行数据库操作
         log::info!("[{}] 保存车机响应到数据库...", self.id);
         self.db
-            .save_machine_response(self.task_id, self.sample_id as i64, &response_data)
+            .save_machine_response(&self.run_key(), &response_data)
             .await
             .map_err(|e| format!("[{}] 保存车机响应失败: {}", self.id, e))?;
 
@@ -508,9 +482,9 @@ impl finish_task {
             log::info!("[{}] active_task失败，将分析结果标记为唤醒失败", self.id);
             analysis_result.assessment.overall_score = 0.0;
             analysis_result.assessment.valid = false; // 使用valid字段标记失败
-            analysis_result.assessment.semantic_correctness.score = 0.0;
-            analysis_result.assessment.state_change_confirmation.score = 0.0;
-            analysis_result.assessment.unambiguous_expression.score = 0.0;
+            for item in analysis_result.assessment.dimensions.values_mut() {
+                item.score = 0.0;
+            }
 
             // 添加失败原因说明
             if analysis_result.assessment.suggestions.is_empty() {
@@ -526,17 +500,44 @@ impl finish_task {
             }
         }
 
+        // 计算参考文本与识别文本的字级别对齐结果
+        if let (Some(reference), Some(recognized)) =
+            (&analysis_result.reference_text, &analysis_result.recognized_text)
+        {
+            let metrics = AsrMetrics::compute(reference, recognized);
+            log::info!(
+                "[{}] 识别指标: CER={:.3}, S={}, D={}, I={}, N={}",
+                self.id,
+                metrics.chars.error_rate(),
+                metrics.chars.substitutions,
+                metrics.chars.deletions,
+                metrics.chars.insertions,
+                metrics.chars.reference_len
+            );
+            analysis_result.substitution_errors = Some(metrics.chars.substitutions as u32);
+            analysis_result.deletion_errors = Some(metrics.chars.deletions as u32);
+            analysis_result.insertion_errors = Some(metrics.chars.insertions as u32);
+            analysis_result.total_words = Some(metrics.chars.reference_len as u32);
+        }
+
+        analysis_result.wake_word_id = self.run_wake_word();
+        analysis_result.run_index = self.run_index;
+
         log::info!("[{}] 保存分析结果到数据库...", self.id);
         self.db
-            .save_analysis_result(self.task_id, self.sample_id as i64, &analysis_result)
+            .save_analysis_result(&self.run_key(), &analysis_result)
             .await
             .map_err(|e| format!("[{}] 保存分析结果失败: {}", self.id, e))?;
 
+        self.update_task_asr_metrics()
+            .await
+            .map_err(|e| format!("[{}] 更新任务识别指标失败: {}", self.id, e))?;
+
         // 保存时间数据
         log::info!("[{}] 保存时间数据到数据库...", self.id);
         println!("时间数据：{:?}", timing_data.clone());
         self.db
-            .save_timing_data(self.task_id, self.sample_id as i64, &timing_data)
+            .save_timing_data(&self.run_key(), &timing_data)
             .await
             .map_err(|e| format!("[{}] 保存时间数据失败: {}", self.id, e))?;
 
@@ -551,24 +552,15 @@ impl finish_task {
             log::info!("[{}]   TTS响应时间: {}ms", self.id, tts_time);
         }
 
-        log::info!("[{}] 更新任务状态为完成...", self.id);
-        self.db
-            .update_task_status(self.task_id, "completed")
-            .await
-            .map_err(|e| format!("[{}] 更新任务状态失败: {}", self.id, e))?;
-
-        // 可选：更新任务进度为100%
-        self.db
-            .update_task_progress(self.task_id, 1.0)
-            .await
-            .map_err(|e| format!("[{}] 更新任务进度失败: {}", self.id, e))?;
-
+        // 任务状态和进度由 MetaTaskExecutor 在所有样本结束后统一更新
         log::info!("[{}] 数据保存完成 - 样本ID: {}", self.id, self.sample_id);
 
         // 4. 发送完成事件到前端
         let mut event_data = serde_json::json!({
             "task_id": self.task_id,
             "sample_id": self.sample_id,
+            "wake_word_id": self.run_wake_word(),
+            "run_index": self.run_index,
             "response": response_data.text,
             "analysis_score": analysis_result.assessment.overall_score
         });
@@ -584,16 +576,43 @@ impl finish_task {
             event_data["tts_response_time_ms"] = serde_json::Value::from(tts_time);
         }
 
-        app_handle.emit("finish_task_complete", event_data)?;
+        events.emit("finish_task_complete", event_data)?;
 
         Ok(())
     }
 
+    /// 根据任务下所有已完成样本重新汇总识别指标，写入任务记录。
+    /// 唤醒失败和超时的样本没有识别文本，不计入统计。
+    async fn update_task_asr_metrics(&self) -> anyhow::Result<()> {
+        let results = self.db.list_analysis_results_by_task(self.task_id).await?;
+        let mut summary = AsrMetricsSummary::default();
+        for (_, result) in &results {
+            if matches!(result.result_status.as_deref(), Some("wake_failed") | Some("timeout")) {
+                continue;
+            }
+            if let (Some(reference), Some(recognized)) =
+                (&result.reference_text, &result.recognized_text)
+            {
+                summary.add(&AsrMetrics::compute(reference, recognized));
+            }
+        }
+
+        log::info!(
+            "[{}] 任务识别指标: 样本数={}, CER={:?}, 词准确率={:?}, 句准确率={:?}",
+            self.id,
+            summary.samples,
+            summary.character_error_rate(),
+            summary.word_accuracy(),
+            summary.sentence_accuracy()
+        );
+        self.db.update_task_asr_metrics(self.task_id, &summary).await
+    }
+
     /// 保存超时错误数据
     async fn save_timeout_error_data(
         &self,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         log::info!(
             "[{}] 保存超时错误数据 - 样本ID: {}",
@@ -631,7 +650,7 @@ impl finish_task {
                 "status": "timeout"
             });
 
-            app_handle.emit("finish_task_complete", event_data)?;
+            events.emit("finish_task_complete", event_data)?;
 
             log::info!(
                 "[{}] 唤醒检测任务超时处理完成 - 样本ID: {}",
@@ -643,27 +662,19 @@ impl finish_task {
 
         // 对于非唤醒检测任务，保存完整的超时错误数据
         // 创建超时错误的分析结果
+        let rubric = self
+            .db
+            .get_task_rubric(self.task_id)
+            .await
+            .map_err(|e| format!("[{}] 获取评分标准失败: {}", self.id, e))?;
         let timeout_analysis_result = AnalysisResult {
-            assessment: crate::models::Assessment {
-                semantic_correctness: crate::models::AssessmentItem {
-                    score: 0.0,
-                    comment: "视觉检测超时，无法进行语义正确性评估".to_string(),
-                },
-                state_change_confirmation: crate::models::AssessmentItem {
-                    score: 0.0,
-                    comment: "视觉检测超时，无法进行状态变化确认评估".to_string(),
-                },
-                unambiguous_expression: crate::models::AssessmentItem {
-                    score: 0.0,
-                    comment: "视觉检测超时，无法进行表达明确性评估".to_string(),
-                },
-                overall_score: 0.0,
-                valid: false,
-                suggestions: vec![
+            assessment: rubric.failed_assessment(
+                "视觉检测超时",
+                vec![
                     "检查视觉检测配置".to_string(),
                     "增加检测超时时间".to_string(),
                 ],
-            },
+            ),
             llm_analysis: Some(crate::models::LlmAnalysis {
                 title: "超时错误".to_string(),
                 content: "视觉检测任务超时，系统未能及时响应".to_string(),
@@ -684,6 +695,11 @@ impl finish_task {
             result_status: Some("timeout".to_string()),
             recognition_time: None,
             response_time: None,
+            prompt_template_id: None,
+            prompt_template_version: None,
+            rubric_id: Some(rubric.id),
+            wake_word_id: self.run_wake_word(),
+            run_index: self.run_index,
         };
 
         // 创建超时错误的机器响应数据
@@ -707,45 +723,32 @@ impl finish_task {
 
         // 保存到数据库
         self.db
-            .save_analysis_result(
-                self.task_id,
-                self.sample_id as i64,
-                &timeout_analysis_result,
-            )
+            .save_analysis_result(&self.run_key(), &timeout_analysis_result)
             .await
             .map_err(|e| format!("[{}] 保存超时错误分析结果失败: {}", self.id, e))?;
 
         self.db
-            .save_machine_response(self.task_id, self.sample_id as i64, &timeout_response_data)
+            .save_machine_response(&self.run_key(), &timeout_response_data)
             .await
             .map_err(|e| format!("[{}] 保存超时错误机器响应失败: {}", self.id, e))?;
 
         self.db
-            .save_timing_data(self.task_id, self.sample_id as i64, &timeout_timing_data)
+            .save_timing_data(&self.run_key(), &timeout_timing_data)
             .await
             .map_err(|e| format!("[{}] 保存超时错误时间数据失败: {}", self.id, e))?;
 
-        // 更新任务状态
-        self.db
-            .update_task_status(self.task_id, "completed")
-            .await
-            .map_err(|e| format!("[{}] 更新任务状态失败: {}", self.id, e))?;
-
-        self.db
-            .update_task_progress(self.task_id, 1.0)
-            .await
-            .map_err(|e| format!("[{}] 更新任务进度失败: {}", self.id, e))?;
-
         // 发送超时完成事件到前端
         let event_data = serde_json::json!({
             "task_id": self.task_id,
             "sample_id": self.sample_id,
+            "wake_word_id": self.run_wake_word(),
+            "run_index": self.run_index,
             "response": "视觉检测超时",
             "analysis_score": 0.0,
             "status": "timeout"
         });
 
-        app_handle.emit("finish_task_complete", event_data)?;
+        events.emit("finish_task_complete", event_data)?;
 
         log::info!(
             "[{}] 超时错误数据保存完成 - 样本ID: {}",
@@ -762,11 +765,27 @@ impl Task for finish_task {
         self.id.clone()
     }
 
+    fn inputs(&self) -> Vec<KeyDecl> {
+        let mut inputs = vec![AsrTask::output_key(&self.asr_dependency_id).decl()];
+        if let Some(active_task_id) = &self.active_task_id {
+            inputs.push(ActiveTask::output_key(active_task_id).decl());
+        }
+        // 唤醒检测任务只保存唤醒结果，样本测试还需要分析结果和语音指令时间
+        if self.wake_word_id.is_none() {
+            inputs.push(analysis_task::output_key(&self.analysis_dependency_id).decl());
+            inputs.push(audio_task::timing_key(&self.audio_task_id).decl());
+            if self.active_task_id.is_some() {
+                inputs.push(checkpoint_task::wake_success_key().decl());
+            }
+        }
+        inputs
+    }
+
     async fn execute(
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         log::info!(
             "[{}] 开始执行 - 保存样本 {} 的结果",
@@ -777,26 +796,15 @@ impl Task for finish_task {
         // 检查active_task的结果，如果超时则保存超时错误数据
         // 注意：对于唤醒检测任务，超时是正常情况，不需要保存错误数据
         let context_reader = context.read().await;
-        let mut is_timeout = false;
         let is_wake_detection_task = self.wake_word_id.is_some(); // 判断是否为唤醒检测任务
-        
-        for (task_id, result) in context_reader.iter() {
-            if let Some(active_task_id) = &self.active_task_id {
-                if task_id == active_task_id {
-                    if let Some(result_any) = result.downcast_ref::<serde_json::Value>() {
-                        if let Some(status) = result_any.get("status").and_then(|s| s.as_str()) {
-                            if status == "timeout" {
-                                println!(
-                                    "[{}] Active task timed out",
-                                    self.id
-                                );
-                                is_timeout = true;
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
+        let is_timeout = self
+            .active_task_id
+            .as_ref()
+            .and_then(|active_task_id| ActiveTask::output_key(active_task_id).get(&context_reader))
+            .and_then(|result| result.get("status").and_then(|s| s.as_str()))
+            == Some("timeout");
+        if is_timeout {
+            println!("[{}] Active task timed out", self.id);
         }
         drop(context_reader);
 
@@ -806,7 +814,7 @@ impl Task for finish_task {
                 "[{}] Active task timed out in non-wake-detection task, saving timeout error data",
                 self.id
             );
-            return self.save_timeout_error_data(context, app_handle).await;
+            return self.save_timeout_error_data(context, events).await;
         }
 
         loop {
@@ -816,7 +824,7 @@ impl Task for finish_task {
                     log::info!("[{}] 收到 'Running' 信号, 开始数据保存操作", self.id);
 
                     match self
-                        .process_and_save_data(context.clone(), app_handle.clone())
+                        .process_and_save_data(context.clone(), events.clone())
                         .await
                     {
                         Ok(_) => {
@@ -0,0 +1,177 @@
+//! 测试队列：按优先级和排列顺序依次执行排队的样本测试和唤醒测试。
+//! 队列保存在数据库中，程序重启后继续执行；退出时正在执行的任务重新排队，样本测试从第一个未完成的运行继续。
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+use crate::commands::{build_meta_executor, prepare_task_runs, remaining_runs};
+use crate::models::{JobSpec, JobStatus, JobVisualSettings, QueuedJob};
+use crate::services::active_task::VisualWakeConfig;
+use crate::services::event_sink::Events;
+use crate::services::wake_detection_meta_executor::wake_detection_meta_executor;
+use crate::services::workflow::{ControlHandle, ControlSignal, Workflow};
+use crate::state::AppState;
+
+/// 没有新任务入队的通知时，检查队列的间隔（手动启动的测试结束后据此继续执行队列）
+const IDLE_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 队列执行循环，随程序启动，在后台一直运行
+pub async fn run_job_queue(state: Arc<AppState>, events: Events) {
+    match state.db.requeue_running_jobs().await {
+        Ok(0) => {}
+        Ok(count) => log::info!("[JOB_QUEUE] Requeued {} job(s) interrupted by the last shutdown", count),
+        Err(e) => log::error!("[JOB_QUEUE] Failed to requeue interrupted jobs: {}", e),
+    }
+
+    loop {
+        match claim_next_job(&state).await {
+            Ok(Some((job, control_rx))) => run_job(&state, &events, job, control_rx).await,
+            Ok(None) => {
+                tokio::select! {
+                    _ = state.job_queue_notify.notified() => {}
+                    _ = tokio::time::sleep(IDLE_POLL_INTERVAL) => {}
+                }
+            }
+            Err(e) => {
+                log::error!("[JOB_QUEUE] Failed to fetch next job: {}", e);
+                tokio::time::sleep(IDLE_POLL_INTERVAL).await;
+            }
+        }
+    }
+}
+
+/// 没有工作流在运行时取出下一个排队的任务并标记为执行中，控制句柄存入全局状态，
+/// `pause_workflow` / `stop_workflow` 等命令对队列中的任务同样有效
+async fn claim_next_job(
+    state: &AppState,
+) -> anyhow::Result<Option<(QueuedJob, watch::Receiver<ControlSignal>)>> {
+    let mut workflow_handle_guard = state.workflow_handle.lock().await;
+    if workflow_handle_guard.as_ref().is_some_and(|handle| handle.is_running()) {
+        return Ok(None);
+    }
+    let Some(job) = state.db.next_queued_job().await? else {
+        return Ok(None);
+    };
+    // 取出后被暂停或取消的任务留到下次再取
+    if !state
+        .db
+        .transition_job(job.id, &[JobStatus::Queued], JobStatus::Running, None)
+        .await?
+    {
+        return Ok(None);
+    }
+    let (handle, control_rx) = ControlHandle::channel();
+    *workflow_handle_guard = Some(handle);
+    Ok(Some((job, control_rx)))
+}
+
+async fn run_job(
+    state: &Arc<AppState>,
+    events: &Events,
+    job: QueuedJob,
+    control_rx: watch::Receiver<ControlSignal>,
+) {
+    log::info!("[JOB_QUEUE] Starting job {} for task {}", job.id, job.task_id);
+    emit_job_update(state, events, job.id).await;
+
+    let signal_rx = control_rx.clone();
+    let result = match build_job_workflow(state, &job).await {
+        Ok(Some(workflow)) => workflow.run_and_wait(events.clone(), control_rx).await.map(|_| ()),
+        Ok(None) => {
+            log::info!("[JOB_QUEUE] Job {} has no remaining runs", job.id);
+            Ok(())
+        }
+        Err(e) => Err(e),
+    };
+    let stopped = *signal_rx.borrow() == ControlSignal::Stopped;
+    drop(signal_rx);
+
+    let (status, error) = match result {
+        _ if stopped => (JobStatus::Cancelled, None),
+        Ok(()) => (JobStatus::Done, None),
+        Err(e) => (JobStatus::Failed, Some(e)),
+    };
+    match &error {
+        Some(e) => log::error!("[JOB_QUEUE] Job {} failed: {}", job.id, e),
+        None => log::info!("[JOB_QUEUE] Job {} finished: {}", job.id, status.as_str()),
+    }
+    if let Err(e) = state
+        .db
+        .transition_job(job.id, &[JobStatus::Running], status, error.as_deref())
+        .await
+    {
+        log::error!("[JOB_QUEUE] Failed to update job {}: {}", job.id, e);
+    }
+    *state.workflow_handle.lock().await = None;
+    emit_job_update(state, events, job.id).await;
+}
+
+/// 按队列项的参数创建主工作流。之前开始过的样本测试从第一个未完成的运行继续，
+/// 所有运行均已完成时返回 None
+async fn build_job_workflow(state: &Arc<AppState>, job: &QueuedJob) -> Result<Option<Workflow>, String> {
+    let task_id = job.task_id;
+    let (mut workflow, _) = Workflow::new();
+    match &job.spec {
+        JobSpec::SampleTest { wake_word_strategy, visual } => {
+            let run_states = state
+                .db
+                .list_sample_run_states(task_id)
+                .await
+                .map_err(|e| format!("获取运行状态失败: {}", e))?;
+            // 之前开始过且已保存运行计划时继续执行；开始前就中断（尚未保存运行计划）或首次执行时重新生成计划
+            let (runs, completed_runs) = if job.started_at.is_some() && !run_states.is_empty() {
+                match remaining_runs(&state.db, task_id, &run_states).await? {
+                    Some(remaining) => remaining,
+                    None => return Ok(None),
+                }
+            } else {
+                let strategy = match wake_word_strategy {
+                    Some(strategy) => strategy.clone(),
+                    None => state
+                        .db
+                        .get_task_wake_word_strategy(task_id)
+                        .await
+                        .map_err(|e| format!("获取唤醒词策略失败: {}", e))?,
+                };
+                (prepare_task_runs(&state.db, task_id, &strategy).await?, HashSet::new())
+            };
+            workflow.add_task(
+                build_meta_executor(state, task_id, runs, visual_config(visual), completed_runs)
+                    .await?,
+            );
+        }
+        JobSpec::WakeTest { visual, expected_responses } => {
+            workflow.add_task(wake_detection_meta_executor::new(
+                &format!("wake_detection_task_{}", task_id),
+                task_id,
+                visual_config(visual),
+                state.clone(),
+                expected_responses.clone(),
+            ));
+        }
+    }
+    Ok(Some(workflow))
+}
+
+/// 未提供的参数使用与手动启动相同的默认值
+fn visual_config(settings: &JobVisualSettings) -> VisualWakeConfig {
+    VisualWakeConfig {
+        template_data: settings.template_data.clone(),
+        frame_rate: settings.frame_rate.unwrap_or(10),
+        threshold: settings.threshold.unwrap_or(0.5),
+        max_detection_time_secs: Some(settings.max_detection_time_secs.unwrap_or(5)),
+    }
+}
+
+/// 通知前端队列项状态变化
+async fn emit_job_update(state: &AppState, events: &Events, job_id: i64) {
+    match state.db.get_job(job_id).await {
+        Ok(Some(job)) => {
+            events.emit("job_queue_update", &job).ok();
+        }
+        Ok(None) => {}
+        Err(e) => log::error!("[JOB_QUEUE] Failed to load job {}: {}", job_id, e),
+    }
+}
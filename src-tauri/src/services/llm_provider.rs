@@ -0,0 +1,287 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::AppConfig;
+
+const DEFAULT_BASE_URL: &str = "https://openrouter.ai/api/v1";
+const DEFAULT_MODEL: &str = "google/gemini-2.5-flash";
+const DEFAULT_TIMEOUT_SECONDS: u64 = 60;
+const RETRY_BASE_DELAY: Duration = Duration::from_secs(1);
+const RETRY_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// 单个任务可覆盖的大模型参数，未设置的项使用全局配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LlmOverrides {
+    #[serde(default)]
+    pub base_url: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+/// 合并全局配置与任务覆盖项后的最终参数
+#[derive(Debug, Clone)]
+pub struct LlmSettings {
+    pub base_url: String,
+    pub api_key: String,
+    pub model: String,
+    pub temperature: Option<f32>,
+    pub timeout: Duration,
+    pub json_mode: bool,
+    pub max_retries: u32,
+}
+
+impl LlmSettings {
+    pub fn resolve(config: &AppConfig, overrides: &LlmOverrides) -> Self {
+        let llm = &config.openrouter;
+        let pick = |override_value: &Option<String>, configured: &str, default: &str| {
+            override_value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .or_else(|| Some(configured.trim()).filter(|v| !v.is_empty()))
+                .unwrap_or(default)
+                .to_string()
+        };
+
+        let timeout_seconds = overrides
+            .timeout_seconds
+            .filter(|v| *v > 0)
+            .or(Some(llm.timeout_seconds).filter(|v| *v > 0))
+            .or(Some(config.app.timeout_seconds).filter(|v| *v > 0))
+            .unwrap_or(DEFAULT_TIMEOUT_SECONDS);
+
+        Self {
+            base_url: pick(&overrides.base_url, &llm.base_url, DEFAULT_BASE_URL),
+            api_key: llm.api_key.clone(),
+            model: pick(&overrides.model, &llm.model, DEFAULT_MODEL),
+            temperature: overrides.temperature.or(llm.temperature),
+            timeout: Duration::from_secs(timeout_seconds),
+            json_mode: llm.json_mode,
+            max_retries: llm.max_retries,
+        }
+    }
+
+    /// 兼容直接填写完整 `/chat/completions` 地址的情况
+    pub fn chat_completions_url(&self) -> String {
+        let base = self.base_url.trim_end_matches('/');
+        if base.ends_with("/chat/completions") {
+            base.to_string()
+        } else {
+            format!("{}/chat/completions", base)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: "user".to_string(),
+            content: content.into(),
+        }
+    }
+}
+
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// 实际使用的模型名称，用于日志和结果记录
+    fn model(&self) -> &str;
+
+    /// 发送对话并返回模型输出的文本内容
+    async fn chat(&self, client: &Client, messages: Vec<ChatMessage>) -> Result<String>;
+}
+
+pub fn create_llm_provider(config: &AppConfig, overrides: &LlmOverrides) -> Box<dyn LlmProvider> {
+    Box::new(OpenAiCompatibleProvider::new(LlmSettings::resolve(
+        config, overrides,
+    )))
+}
+
+// --- OpenAI-compatible Chat Completions (OpenRouter / vLLM / llama.cpp ...) ---
+
+#[derive(Serialize)]
+struct ChatCompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_format: Option<ResponseFormat>,
+}
+
+#[derive(Serialize)]
+struct ResponseFormat {
+    #[serde(rename = "type")]
+    format_type: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct ChatCompletionResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Choice {
+    message: MessageContent,
+}
+
+#[derive(Deserialize, Debug)]
+struct MessageContent {
+    content: Option<String>,
+}
+
+/// 单次请求的结果：成功、可重试（限流/服务端错误/网络错误）或不可重试
+enum Attempt {
+    Done(String),
+    Retry {
+        error: anyhow::Error,
+        retry_after: Option<Duration>,
+    },
+    Fatal(anyhow::Error),
+}
+
+pub struct OpenAiCompatibleProvider {
+    settings: LlmSettings,
+}
+
+impl OpenAiCompatibleProvider {
+    pub fn new(settings: LlmSettings) -> Self {
+        Self { settings }
+    }
+
+    async fn send_once(
+        &self,
+        client: &Client,
+        url: &str,
+        request_body: &ChatCompletionRequest<'_>,
+    ) -> Attempt {
+        let mut request = client
+            .post(url)
+            .timeout(self.settings.timeout)
+            .header("Content-Type", "application/json")
+            .json(request_body);
+        // 本地部署的服务通常不需要密钥
+        if !self.settings.api_key.is_empty() {
+            request = request.bearer_auth(&self.settings.api_key);
+        }
+
+        let res = match request.send().await {
+            Ok(res) => res,
+            Err(e) => {
+                let error = anyhow!("Failed to send request to {}: {}", url, e);
+                return if e.is_timeout() || e.is_connect() {
+                    Attempt::Retry {
+                        error,
+                        retry_after: None,
+                    }
+                } else {
+                    Attempt::Fatal(error)
+                };
+            }
+        };
+
+        let status = res.status();
+        if !status.is_success() {
+            let retry_after = res
+                .headers()
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.trim().parse::<u64>().ok())
+                .map(Duration::from_secs);
+            let error_body = res
+                .text()
+                .await
+                .unwrap_or_else(|_| "Could not read error body".to_string());
+            log::error!("LLM API returned an error status {}: {}", status, error_body);
+            let error = anyhow!("LLM API Error ({}): {}", status, error_body);
+            return if status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                Attempt::Retry { error, retry_after }
+            } else {
+                Attempt::Fatal(error)
+            };
+        }
+
+        let llm_response = match res.json::<ChatCompletionResponse>().await {
+            Ok(body) => body,
+            Err(e) => {
+                // 网关偶尔返回截断的响应体，按可重试处理
+                return Attempt::Retry {
+                    error: anyhow!("Failed to parse LLM response structure: {}", e),
+                    retry_after: None,
+                };
+            }
+        };
+
+        match llm_response
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+        {
+            Some(content) => Attempt::Done(content),
+            None => Attempt::Fatal(anyhow!("LLM response contained no choices.")),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiCompatibleProvider {
+    fn model(&self) -> &str {
+        &self.settings.model
+    }
+
+    async fn chat(&self, client: &Client, messages: Vec<ChatMessage>) -> Result<String> {
+        let url = self.settings.chat_completions_url();
+        log::info!("Calling LLM endpoint {} with model {}", url, self.settings.model);
+
+        let request_body = ChatCompletionRequest {
+            model: &self.settings.model,
+            messages,
+            temperature: self.settings.temperature,
+            response_format: self.settings.json_mode.then(|| ResponseFormat {
+                format_type: "json_object".to_string(),
+            }),
+        };
+
+        let mut attempt = 0;
+        loop {
+            match self.send_once(client, &url, &request_body).await {
+                Attempt::Done(content) => return Ok(content),
+                Attempt::Fatal(e) => return Err(e),
+                Attempt::Retry { error, retry_after } => {
+                    if attempt >= self.settings.max_retries {
+                        return Err(error.context(format!(
+                            "LLM request failed after {} attempts",
+                            attempt + 1
+                        )));
+                    }
+                    // 指数退避，服务端给出 Retry-After 时以其为准
+                    let delay = retry_after
+                        .unwrap_or_else(|| RETRY_BASE_DELAY * 2u32.saturating_pow(attempt))
+                        .min(RETRY_MAX_DELAY);
+                    attempt += 1;
+                    log::warn!(
+                        "LLM request failed ({}), retrying in {:?} ({}/{})",
+                        error,
+                        delay,
+                        attempt,
+                        self.settings.max_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
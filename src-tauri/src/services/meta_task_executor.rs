@@ -1,31 +1,150 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::collections::{HashSet, VecDeque};
 use std::error::Error;
+use std::future::Future;
 use std::sync::Arc;
-use tauri::Emitter;
+use std::time::Duration;
 use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
 
-use crate::models::TaskProgress;
-use crate::models::TestSample;
-use crate::models::WakeWord;
-use crate::services::active_task::{ActiveTask, VisualWakeConfig};
-use crate::services::analysis_task::analysis_task;
-use crate::services::asr_task::AsrTask;
-use crate::services::audio_task::audio_task;
-use crate::services::finish_task::finish_task;
-use crate::services::checkpoint_task::checkpoint_task;
+use crate::services::event_sink::Events;
+use crate::config::AppConfig;
+use crate::db::database::DatabaseService;
+use crate::models::{SampleRunKey, SampleRunStatus, TaskProgress, WorkflowRunRecord, WorkflowTaskRecord};
+use crate::services::active_task::VisualWakeConfig;
+use crate::services::analysis_task::AnalysisSettings;
+use crate::services::pipeline::{PipelineDefinition, SampleEnv};
+use crate::services::run_plan::SampleRun;
+use crate::services::task_statistics::refresh_task_statistics;
 use crate::services::workflow::ControlSignal;
+use crate::services::workflow::ExecutionLimits;
 use crate::services::workflow::Task;
+use crate::services::workflow::TaskTrace;
+use crate::services::workflow::TraceStatus;
 use crate::services::workflow::Workflow;
 use crate::services::workflow::WorkflowContext;
+use crate::services::workflow::WorkflowTrace;
 use crate::state::AppState;
 
+/// 样本执行期间上报进度的间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+/// 估算剩余时间时参考的最近运行数
+const ETA_WINDOW: usize = 10;
+
+/// 样本子工作流的执行限制，同时执行的任务数取自配置 `app.max_concurrent_tasks`
+pub(crate) fn configured_limits() -> ExecutionLimits {
+    let max_concurrent_tasks = AppConfig::load()
+        .map(|config| config.app.max_concurrent_tasks)
+        .unwrap_or(0);
+    ExecutionLimits::new(max_concurrent_tasks)
+}
+
+/// 统计已结束运行的评估结论，并按最近若干次运行的平均间隔估算剩余时间。
+/// 使用相邻两次运行结束的间隔而不是单次运行的耗时，流水线执行时多个样本重叠也能正确估算
+struct ProgressTracker {
+    task_id: i64,
+    total: usize,
+    started: Instant,
+    /// 上一次运行结束的时间
+    last_finished: Instant,
+    recent: VecDeque<Duration>,
+    passed: u32,
+    failed: u32,
+}
+
+impl ProgressTracker {
+    fn new(task_id: i64, total: usize) -> Self {
+        Self {
+            task_id,
+            total,
+            started: Instant::now(),
+            last_finished: Instant::now(),
+            recent: VecDeque::with_capacity(ETA_WINDOW),
+            passed: 0,
+            failed: 0,
+        }
+    }
+
+    /// 记录一次结束的运行，没有评估结果（如唤醒失败）按未通过计
+    fn count(&mut self, verdict: Option<bool>) {
+        if verdict == Some(true) {
+            self.passed += 1;
+        } else {
+            self.failed += 1;
+        }
+    }
+
+    fn finish_run(&mut self, verdict: Option<bool>) {
+        if self.recent.len() == ETA_WINDOW {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(self.last_finished.elapsed());
+        self.last_finished = Instant::now();
+        self.count(verdict);
+    }
+
+    fn finished(&self) -> usize {
+        (self.passed + self.failed) as usize
+    }
+
+    /// 剩余运行数 × 最近运行的平均间隔，减去上一次运行结束后已过的时间
+    fn eta(&self) -> Option<Duration> {
+        if self.recent.is_empty() {
+            return None;
+        }
+        let average = self.recent.iter().sum::<Duration>() / self.recent.len() as u32;
+        let remaining = self.total.saturating_sub(self.finished()) as u32;
+        Some((average * remaining).saturating_sub(self.last_finished.elapsed()))
+    }
+
+    fn progress(&self, current_sample: usize, stage: Option<String>) -> TaskProgress {
+        TaskProgress {
+            task_id: self.task_id,
+            value: self.finished() as f32 / self.total.max(1) as f32 * 100.0,
+            current_sample: current_sample as u32,
+            current_stage: stage,
+            total: self.total as u32,
+            passed: self.passed,
+            failed: self.failed,
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            eta_ms: self.eta().map(|eta| eta.as_millis() as u64),
+        }
+    }
+}
+
+/// 已开始执行的一次样本运行
+struct StartedRun {
+    index: usize,
+    key: SampleRunKey,
+    sample_id: u32,
+    sample_text: String,
+    trace: WorkflowTrace,
+    started_at: DateTime<Utc>,
+}
+
+/// 在后台执行的样本子工作流
+type RunExecution = JoinHandle<Result<WorkflowContext, String>>;
+
+/// 样本运行没有正常完成的原因
+enum RunAbort {
+    /// 收到外部停止信号
+    Stopped,
+    /// 子流程失败，附带发给前端的错误信息
+    Failed(String),
+}
+
 /// 这个任务是所有样本测试的"总指挥"，现在支持视觉唤醒检测
 pub struct MetaTaskExecutor {
     id: String,
     task_id: i64,
-    samples: Vec<TestSample>,
-    wakeword: WakeWord,
+    runs: Vec<SampleRun>, // 按唤醒词策略展开后的样本运行
     visual_config: VisualWakeConfig, // 添加视觉配置
+    analysis_settings: AnalysisSettings, // 任务级别的大模型参数和提示词模板
+    pipeline: PipelineDefinition, // 任务选择的样本流水线
+    completed_runs: HashSet<SampleRunKey>, // 恢复执行时跳过的已完成运行
+    limits: ExecutionLimits, // 各样本子工作流共享的并发和资源限制
     state_snapshot: Arc<AppState>,
 }
 
@@ -33,20 +152,248 @@ impl MetaTaskExecutor {
     pub fn new(
         id: &str,
         task_id: i64,
-        samples: Vec<TestSample>,
-        wakeword: WakeWord,
+        runs: Vec<SampleRun>,
         visual_config: VisualWakeConfig, // 添加视觉配置参数
+        analysis_settings: AnalysisSettings,
+        pipeline: PipelineDefinition,
         state: Arc<AppState>,
     ) -> Self {
         Self {
             id: id.to_string(),
             task_id,
-            samples,
-            wakeword,
+            runs,
             visual_config,
+            analysis_settings,
+            pipeline,
+            completed_runs: HashSet::new(),
+            limits: configured_limits(),
             state_snapshot: state,
         }
     }
+
+    /// 从中断处恢复：跳过上次已完成的运行
+    pub fn with_completed_runs(mut self, completed_runs: HashSet<SampleRunKey>) -> Self {
+        self.completed_runs = completed_runs;
+        self
+    }
+
+    /// 按任务选择的流水线构建单次样本运行的子工作流。`index` 为本次运行在计划中的序号。
+    pub fn build_sample_workflow(&self, index: usize, run: &SampleRun) -> Workflow {
+        let env = SampleEnv {
+            task_id: self.task_id,
+            visual_config: &self.visual_config,
+            analysis_settings: &self.analysis_settings,
+            state: &self.state_snapshot,
+        };
+        let mut workflow = self.pipeline.build_sample_workflow(&env, index, run);
+        workflow.set_execution_limits(self.limits.clone());
+        workflow
+    }
+
+    /// 更新任务状态，失败只记录日志，不影响测试继续
+    async fn set_task_status(&self, status: &str) {
+        if let Err(e) = self.state_snapshot.db.update_task_status(self.task_id, status).await {
+            log::error!("[MetaTask '{}'] Failed to set task status '{}': {}", self.id, status, e);
+        }
+    }
+
+    /// 持久化单次运行的状态，程序中断后 `resume_task` 据此继续
+    async fn set_run_status(&self, key: &SampleRunKey, status: SampleRunStatus, error: Option<&str>) {
+        if let Err(e) = self.state_snapshot.db.set_sample_run_status(key, status, error).await {
+            log::error!(
+                "[MetaTask '{}'] Failed to save run state of sample {}: {}",
+                self.id,
+                key.sample_id,
+                e
+            );
+        }
+    }
+
+    async fn save_trace(
+        &self,
+        key: SampleRunKey,
+        started_at: DateTime<Utc>,
+        status: &str,
+        error: Option<&str>,
+        trace: &WorkflowTrace,
+    ) {
+        save_workflow_trace(&self.state_snapshot.db, key, &self.pipeline.name, started_at, status, error, trace).await;
+    }
+
+    /// 一次运行的评估结论，查询失败按没有结果处理
+    async fn run_verdict(&self, key: &SampleRunKey) -> Option<bool> {
+        self.state_snapshot
+            .db
+            .get_analysis_verdict(key)
+            .await
+            .unwrap_or_else(|e| {
+                log::error!("[MetaTask '{}'] Failed to load verdict of sample {}: {}", self.id, key.sample_id, e);
+                None
+            })
+    }
+
+    /// 发送 "progress_update" 事件，并保存供 `get_task_progress` 查询
+    async fn publish_progress(&self, events: &Events, progress: TaskProgress) {
+        events.emit("progress_update", &progress).ok();
+        *self.state_snapshot.task_progress.write().await = Some(progress);
+    }
+
+    /// 创建样本的子工作流并在后台开始执行
+    async fn start_run(
+        &self,
+        index: usize,
+        run: &SampleRun,
+        events: &Events,
+        control_rx: &watch::Receiver<ControlSignal>,
+    ) -> (StartedRun, RunExecution) {
+        let sub_workflow = self.build_sample_workflow(index, run);
+        let started = StartedRun {
+            index,
+            key: run.key(self.task_id),
+            sample_id: run.sample.id,
+            sample_text: run.sample.text.clone(),
+            trace: sub_workflow.trace(),
+            started_at: Utc::now(),
+        };
+        self.set_run_status(&started.key, SampleRunStatus::Running, None).await;
+        let execution = tokio::spawn(sub_workflow.run_and_wait(events.clone(), control_rx.clone()));
+        (started, execution)
+    }
+
+    /// 等待 `future` 完成，期间定时上报进度，当前环节取自 `current` 样本
+    async fn with_progress<T>(
+        &self,
+        events: &Events,
+        tracker: &ProgressTracker,
+        current: &StartedRun,
+        future: impl Future<Output = T>,
+    ) -> T {
+        tokio::pin!(future);
+        let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+        loop {
+            tokio::select! {
+                output = &mut future => return output,
+                _ = ticker.tick() => {
+                    let stage = current_stage(&current.trace, current.sample_id);
+                    let progress = tracker.progress(current.index + 1, stage);
+                    self.publish_progress(events, progress).await;
+                }
+            }
+        }
+    }
+
+    /// 等待一次样本运行结束，保存执行记录和运行状态，正常完成时计入进度
+    async fn finish_run(
+        &self,
+        started: &StartedRun,
+        execution: RunExecution,
+        current: &StartedRun,
+        events: &Events,
+        control_rx: &watch::Receiver<ControlSignal>,
+        tracker: &mut ProgressTracker,
+    ) -> Result<(), RunAbort> {
+        let result = self
+            .with_progress(events, tracker, current, execution)
+            .await
+            .unwrap_or_else(|e| Err(e.to_string()));
+
+        // 子任务收到停止信号后也会正常退出（执行记录中为取消），此时样本并未完成，恢复时需要重新执行；
+        // 停止前已经执行完的样本照常保存
+        let stopped = *control_rx.borrow() == ControlSignal::Stopped;
+        let unfinished = match &result {
+            Ok(_) => {
+                stopped
+                    && started.trace.tasks().iter().any(|task| task.status == TraceStatus::Cancelled)
+            }
+            Err(_) => stopped,
+        };
+        if unfinished {
+            println!("[MetaTask] Stopped by control signal during sample {}.", started.sample_id);
+            self.save_trace(started.key, started.started_at, "cancelled", None, &started.trace).await;
+            self.set_run_status(&started.key, SampleRunStatus::Pending, None).await;
+            return Err(RunAbort::Stopped);
+        }
+        if let Err(e) = result {
+            self.save_trace(started.key, started.started_at, "failed", Some(&e), &started.trace).await;
+            self.set_run_status(&started.key, SampleRunStatus::Failed, Some(&e)).await;
+            return Err(RunAbort::Failed(format!(
+                "样本 '{}' 的子流程失败: {}. 终止所有任务。",
+                started.sample_text, e
+            )));
+        }
+        self.save_trace(started.key, started.started_at, "completed", None, &started.trace).await;
+        self.set_run_status(&started.key, SampleRunStatus::Done, None).await;
+
+        tracker.finish_run(self.run_verdict(&started.key).await);
+        let fraction = tracker.finished() as f64 / tracker.total as f64;
+        if let Err(e) = self.state_snapshot.db.update_task_progress(self.task_id, fraction).await {
+            log::error!("[MetaTask '{}'] Failed to update task progress: {}", self.id, e);
+        }
+        self.publish_progress(events, tracker.progress(current.index + 1, None)).await;
+        events
+            .emit(
+                "meta_task_update",
+                format!("样本 {} 处理成功。", started.sample_text),
+            )
+            .ok();
+        if stopped {
+            return Err(RunAbort::Stopped);
+        }
+        Ok(())
+    }
+
+    /// 按开始顺序结束后台的样本运行，直到后台运行数不超过 `keep`，已经结束的运行总是立即处理。
+    /// 某次运行失败或被停止后不再保留后台运行：已开始的样本都执行完并保存后，返回第一个异常
+    async fn settle_runs(
+        &self,
+        pending: &mut VecDeque<(StartedRun, RunExecution)>,
+        mut keep: usize,
+        events: &Events,
+        control_rx: &watch::Receiver<ControlSignal>,
+        tracker: &mut ProgressTracker,
+    ) -> Result<(), RunAbort> {
+        let mut abort = None;
+        while let Some((_, execution)) = pending.front() {
+            if pending.len() <= keep && !execution.is_finished() {
+                break;
+            }
+            let (started, execution) = pending.pop_front().unwrap();
+            let current = pending.back().map(|(run, _)| run).unwrap_or(&started);
+            if let Err(e) = self
+                .finish_run(&started, execution, current, events, control_rx, tracker)
+                .await
+            {
+                keep = 0;
+                abort.get_or_insert(e);
+            }
+        }
+        abort.map_or(Ok(()), Err)
+    }
+
+    /// 样本运行未正常完成时更新任务状态，返回元任务的错误
+    async fn abort_with(&self, abort: RunAbort, events: &Events) -> Box<dyn Error + Send + Sync> {
+        match abort {
+            RunAbort::Stopped => {
+                self.set_task_status("interrupted").await;
+                self.refresh_statistics().await;
+                "MetaTask was stopped externally.".into()
+            }
+            RunAbort::Failed(error_message) => {
+                self.set_task_status("failed").await;
+                eprintln!("[MetaTask] {}", error_message);
+                events.emit("meta_task_error", &error_message).ok();
+                self.refresh_statistics().await;
+                error_message.into()
+            }
+        }
+    }
+
+    /// 工作流结束（无论成功与否）时汇总任务统计，失败只记录日志
+    async fn refresh_statistics(&self) {
+        if let Err(e) = refresh_task_statistics(&self.state_snapshot.db, self.task_id).await {
+            log::error!("[MetaTask '{}'] Failed to refresh task statistics: {}", self.id, e);
+        }
+    }
 }
 
 #[async_trait]
@@ -59,32 +406,60 @@ impl Task for MetaTaskExecutor {
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         _context: WorkflowContext, // 此元任务不使用共享上下文
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let total = self.samples.len();
+        let total = self.runs.len();
         println!(
-            "[MetaTask '{}'] Starting execution of {} samples.",
-            self.id, total
+            "[MetaTask '{}'] Starting execution of {} samples with pipeline '{}' ({} already completed, up to {} in background).",
+            self.id, total, self.pipeline.name, self.completed_runs.len(), self.pipeline.max_background_samples
         );
-        app_handle
+        self.set_task_status("in_progress").await;
+        events
             .emit(
                 "meta_task_update",
                 format!("总任务开始，共 {} 个样本。", total),
             )
             .ok();
 
-        for (index, sample) in self.samples.iter().enumerate() {
+        // 恢复执行时，之前完成的运行计入进度和通过/未通过数，但不参与耗时估算
+        let mut tracker = ProgressTracker::new(self.task_id, total);
+        for run in &self.runs {
+            let run_key = run.key(self.task_id);
+            if self.completed_runs.contains(&run_key) {
+                tracker.count(self.run_verdict(&run_key).await);
+            }
+        }
+
+        // 采集步骤已结束、分析和保存仍在后台执行的运行，按开始顺序排列
+        let mut pending: VecDeque<(StartedRun, RunExecution)> = VecDeque::new();
+
+        for (index, run) in self.runs.iter().enumerate() {
+            let sample = &run.sample;
+            let wakeword = &run.wakeword;
+            let run_key = run.key(self.task_id);
+            if self.completed_runs.contains(&run_key) {
+                continue;
+            }
             println!(
-                "[MetaTask '{}'] Preparing sample {}/{}: '{}'",
+                "[MetaTask '{}'] Preparing sample {}/{}: '{}' (wake word '{}', run #{})",
                 self.id,
                 index + 1,
                 total,
-                sample.text
+                sample.text,
+                wakeword.text,
+                run.run_index + 1
             );
-            app_handle
+            events
                 .emit(
                     "meta_task_update",
-                    format!("开始处理样本 {}/{}: {}", index + 1, total, sample.text),
+                    format!(
+                        "开始处理样本 {}/{}: {}（唤醒词: {}，第 {} 次）",
+                        index + 1,
+                        total,
+                        sample.text,
+                        wakeword.text,
+                        run.run_index + 1
+                    ),
                 )
                 .ok();
 
@@ -96,11 +471,12 @@ impl Task for MetaTaskExecutor {
                     let signal = *control_rx.borrow();
                      if signal == ControlSignal::Stopped {
                         println!("[MetaTask] Stopped by control signal before starting sample {}.", sample.id);
-                        return Err("MetaTask was stopped externally.".into());
+                        self.settle_runs(&mut pending, 0, &events, control_rx, &mut tracker).await.ok();
+                        return Err(self.abort_with(RunAbort::Stopped, &events).await);
                     }
                     if signal == ControlSignal::Paused {
                          println!("[MetaTask] Paused. Waiting to resume...");
-                         app_handle.emit("meta_task_update", "任务已暂停...").ok();
+                         events.emit("meta_task_update", "任务已暂停...").ok();
                          // 等待信号不再是 Paused
                          while *control_rx.borrow() == ControlSignal::Paused {
                             if control_rx.changed().await.is_err() {
@@ -108,149 +484,125 @@ impl Task for MetaTaskExecutor {
                             }
                          }
                          println!("[MetaTask] Resumed.");
-                         app_handle.emit("meta_task_update", "任务已恢复。").ok();
+                         events.emit("meta_task_update", "任务已恢复。").ok();
                     }
                 }
                 // 如果没有控制信号变化，则正常执行
                 _ = tokio::time::sleep(std::time::Duration::from_millis(1)) => {}
             }
 
-            // 1. 为当前样本创建子工作流
-            let (mut sub_workflow, _) = Workflow::new();
-            let sample_id = sample.id;
-            let keyword = sample.text.clone();
-
-            // 2. 添加所有子任务，确保ID唯一
-            let wake_word_index = 0; // 因为只有一个唤醒词，所以索引为0
-            let wakeword_task_id = format!("wakeword_task_{}", sample_id);
-            let audio_task_id = format!("audio_task_{}", sample_id);
-            let active_task_id = format!("active_task_{}", sample_id); //视觉检测任务，替换audio_ocr_task
-            let wake_asr_task_id = format!("wake_asr_task_{}_{}", self.wakeword.id, wake_word_index);
-            let checkpoint_task_id = format!("checkpoint_task_{}", sample_id);
-            // let ocr_task_id = format!("ocr_task_{}", sample_id);
-            let asr_task_id = format!("asr_task_{}", sample_id);
-            let analysis_task_id = format!("analysis_task_{}", sample_id);
-            let finish_task_id = format!("finish_task_{}", sample_id);
-
-            // 添加唤醒词播放任务
-            sub_workflow.add_task(audio_task {
-                id: wakeword_task_id.clone(),
-                keyword: self.wakeword.text.clone(),
-                url: self.wakeword.audio_file.clone(), // 使用选定唤醒词的音频文件
-            });
-            
-            // 添加视觉检测任务（替换audio_ocr_task）
-            sub_workflow.add_task(ActiveTask::new(
-                active_task_id.clone(),
-                self.visual_config.clone(),
-            ));
-
-            sub_workflow.add_task(AsrTask::new(wake_asr_task_id.clone(), self.wakeword.text.clone()));
-
-            // 添加检查点任务（判断唤醒检测是否成功）
-            sub_workflow.add_task(checkpoint_task::new_with_sample_info(
-                checkpoint_task_id.clone(),
-                active_task_id.clone(),
-                wake_asr_task_id.clone(),
-                Vec::new(), // 预期回复为空，使用默认逻辑
-                (index + 1) as u32, // 样本索引
-                self.wakeword.text.clone(), // 唤醒词文本
-            ));
-
-            // 添加语音指令播放任务（仅在唤醒成功后执行）
-            sub_workflow.add_task(audio_task {
-                id: audio_task_id.clone(),
-                keyword: keyword.clone(),
-                url: sample.audio_file.clone(), // 使用样本的音频文件
-            });
-
-            // // 添加OCR任务
-            // sub_workflow.add_task(ocr_task {
-            //     id: ocr_task_id.clone(),
-            // });
-
-            // 添加ASR任务
-            sub_workflow.add_task(AsrTask::new(
-                asr_task_id.clone(),
-                keyword,
-            ));
-            
-            // 添加分析任务
-            sub_workflow.add_task(analysis_task {
-                id: analysis_task_id.clone(),
-                dependency_id: asr_task_id.clone(),
-                http_client: self.state_snapshot.http_client.clone(),
-            });
-            
-            // 添加完成任务
-            sub_workflow.add_task(finish_task::new_with_active_task(
-                finish_task_id.clone(),
-                self.task_id,
-                sample_id,
-                asr_task_id.clone(),
-                analysis_task_id.clone(),
-                active_task_id.clone(), // 传递active_task_id用于超时检查
-                None,
-                // ocr_task_id.clone(),
-                audio_task_id.clone(),
-                self.state_snapshot.db.clone(),
-            ));
-
-            // 3. 设置依赖关系
-            sub_workflow.add_dependency(&active_task_id, &wakeword_task_id); // active_task在唤醒词播放后开始
-            sub_workflow.add_dependency(&wake_asr_task_id, &wakeword_task_id); // wake_asr_task在唤醒词播放后开始
-            sub_workflow.add_dependency(&checkpoint_task_id, &active_task_id); // checkpoint_task等待active_task完成
-            sub_workflow.add_dependency(&checkpoint_task_id, &wake_asr_task_id); // checkpoint_task等待wake_asr_task完成
-            sub_workflow.add_dependency(&audio_task_id, &checkpoint_task_id); // 语音指令播放仅在唤醒成功后执行
-            sub_workflow.add_dependency(&asr_task_id, &audio_task_id); // ASR任务在语音指令播放后执行
-            // sub_workflow.add_dependency(&ocr_task_id, &middle_task_id);
-            // sub_workflow.add_dependency(&analysis_task_id, &ocr_task_id);
-            sub_workflow.add_dependency(&analysis_task_id, &asr_task_id);
-            sub_workflow.add_dependency(&finish_task_id, &analysis_task_id);
-            // 确保 finish_task 总是执行（即使唤醒失败也要执行）
-            sub_workflow.add_dependency(&finish_task_id, &checkpoint_task_id);
-
-            // 4. 执行并等待子工作流完成
-            let result = sub_workflow
-                .run_and_wait(app_handle.clone(), control_rx.clone())
+            // 1. 为当前样本创建子工作流并开始执行
+            let (started, execution) = self.start_run(index, run, &events, control_rx).await;
+
+            // 2. 等待采集步骤结束，期间定时上报当前环节和剩余时间
+            let capture = self.pipeline.capture_task_ids(sample.id);
+            self.with_progress(&events, &tracker, &started, started.trace.wait_for_tasks(&capture))
                 .await;
-            let value = (index + 1) as f32 / total as f32 * 100 as f32;
-            app_handle
-                .emit(
-                    "progress_update",
-                    TaskProgress {
-                        value,
-                        current_sample: (index + 1) as u32,
-                        current_stage: None,
-                        total: total as u32,
-                    },
-                )
-                .ok();
 
-            if let Err(e) = result {
-                let error_message =
-                    format!("样本 '{}' 的子流程失败: {}. 终止所有任务。", sample.text, e);
-                eprintln!("[MetaTask] {}", error_message);
-                app_handle.emit("meta_task_error", &error_message).ok();
-                return Err(error_message.into());
+            // 3. 后台运行超过上限时等待最早的运行结束（逐个执行时上限为 0，即等待本次运行完成）。
+            //    采集失败或收到停止信号时不再开始新的样本
+            let failed = started
+                .trace
+                .tasks()
+                .iter()
+                .any(|task| task.status == TraceStatus::Failed);
+            let keep = if failed || *control_rx.borrow() == ControlSignal::Stopped {
+                0
+            } else {
+                self.pipeline.max_background_samples
+            };
+            pending.push_back((started, execution));
+            if let Err(abort) = self
+                .settle_runs(&mut pending, keep, &events, control_rx, &mut tracker)
+                .await
+            {
+                return Err(self.abort_with(abort, &events).await);
             }
-            app_handle
-                .emit(
-                    "meta_task_update",
-                    format!("样本 {} 处理成功。", sample.text),
-                )
-                .ok();
+        }
+
+        if let Err(abort) = self
+            .settle_runs(&mut pending, 0, &events, control_rx, &mut tracker)
+            .await
+        {
+            return Err(self.abort_with(abort, &events).await);
         }
 
         println!(
             "[MetaTask '{}'] All samples processed successfully.",
             self.id
         );
-        app_handle
+        events
             .emit("meta_task_update", "所有样本处理完成！")
             .ok();
+        self.set_task_status("completed").await;
+        if let Err(e) = self.state_snapshot.db.update_task_progress(self.task_id, 1.0).await {
+            log::error!("[MetaTask '{}'] Failed to update task progress: {}", self.id, e);
+        }
+        self.refresh_statistics().await;
         Ok(())
     }
 }
 
+/// 子工作流中正在执行的环节，去掉任务ID中的样本后缀（"asr_task_12" -> "asr_task"）
+fn current_stage(trace: &WorkflowTrace, sample_id: u32) -> Option<String> {
+    let suffix = format!("_{}", sample_id);
+    let running: Vec<String> = trace
+        .running()
+        .iter()
+        .map(|id| id.strip_suffix(suffix.as_str()).unwrap_or(id).to_string())
+        .collect();
+    (!running.is_empty()).then(|| running.join(", "))
+}
+
+/// 执行记录中的时间格式，精确到毫秒
+fn format_trace_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S%.3f").to_string()
+}
 
+fn task_record(trace: &TaskTrace) -> WorkflowTaskRecord {
+    WorkflowTaskRecord {
+        node_id: trace.task_id.clone(),
+        status: trace.status.as_str().to_string(),
+        started_at: trace.started_at.map(format_trace_time),
+        finished_at: trace.finished_at.map(format_trace_time),
+        duration_ms: trace
+            .started_at
+            .zip(trace.finished_at)
+            .map(|(start, end)| (end - start).num_milliseconds()),
+        attempts: trace.attempts as i64,
+        message: trace.message.clone(),
+        outputs: serde_json::to_value(&trace.outputs).unwrap_or_default(),
+    }
+}
+
+/// 保存一次子工作流运行的执行记录，失败只记录日志，不影响测试继续
+pub(crate) async fn save_workflow_trace(
+    db: &DatabaseService,
+    key: SampleRunKey,
+    pipeline: &str,
+    started_at: DateTime<Utc>,
+    status: &str,
+    error: Option<&str>,
+    trace: &WorkflowTrace,
+) {
+    let record = WorkflowRunRecord {
+        id: 0,
+        task_id: key.task_id,
+        sample_id: key.sample_id,
+        wake_word_id: key.wake_word_id,
+        run_index: key.run_index,
+        pipeline: pipeline.to_string(),
+        status: status.to_string(),
+        error: error.map(str::to_string),
+        started_at: format_trace_time(started_at),
+        finished_at: format_trace_time(Utc::now()),
+        tasks: trace.tasks().iter().map(task_record).collect(),
+    };
+    if let Err(e) = db.save_workflow_run(&record).await {
+        log::error!(
+            "[Workflow] Failed to save execution trace of sample {}: {}",
+            key.sample_id,
+            e
+        );
+    }
+}
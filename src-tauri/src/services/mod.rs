@@ -1,14 +1,25 @@
 pub mod audio_controller;
 pub mod workflow;
+pub mod event_sink;
 pub mod audio_task;
 pub mod asr_task;
+pub mod asr_backend;
+pub mod alignment;
+pub mod task_statistics;
+pub mod report_export;
 pub mod analysis_task;
+pub mod llm_provider;
 pub mod finish_task;
 pub mod ocr_engine;
 pub mod ocr_task;
 pub mod meta_task_executor;
+pub mod run_plan;
+pub mod pipeline;
 pub mod ocr_session;
 pub mod checkpoint_task;
 pub mod visual_wake_detection;
 pub mod active_task;
-pub mod wake_detection_meta_executor;
\ No newline at end of file
+pub mod wake_detection_meta_executor;
+pub mod job_queue;
+pub mod cron;
+pub mod scheduler;
\ No newline at end of file
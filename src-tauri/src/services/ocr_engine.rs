@@ -7,7 +7,7 @@ use std::collections::VecDeque;
 use std::sync::Arc;
 use tauri::{
     ipc::{Channel, InvokeResponseBody},
-    Manager, State,
+    State,
 };
 use tesseract::{OcrEngineMode, PageSegMode, Tesseract};
 
@@ -82,14 +82,14 @@ fn parse_tsv_data(tsv: &str) -> anyhow::Result<Vec<OcrResultItem>> {
 /// 初始化OCR引擎池
 pub async fn initialize_ocr_pool(
     state: Arc<AppState>,
-    app_handle: &tauri::AppHandle,
     pool_size: usize,
 ) -> anyhow::Result<()> {
     println!("Initializing OCR engine pool with size: {}", pool_size);
     
-    let tessdata_path = app_handle
-        .path()
-        .resolve("tessdata", tauri::path::BaseDirectory::Resource)?;
+    let tessdata_path = state
+        .tessdata_dir
+        .clone()
+        .ok_or_else(|| anyhow!("未找到 tessdata 目录"))?;
 
     std::env::set_var("TESSDATA_PREFIX", &tessdata_path);
 
@@ -1,18 +1,20 @@
 use crate::commands::stop_ocr_session;
 use crate::models::VideoFrame;
+use crate::services::event_sink::Events;
 use crate::services::ocr_engine::{initialize_ocr_pool, shutdown_ocr_pool};
-use crate::services::workflow::{ControlSignal, Task, WorkflowContext};
+use crate::services::workflow::{resources, ControlSignal, Task, WorkflowContext};
 use async_trait::async_trait;
 use std::error::Error;
 use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, Manager};
+use crate::state::AppState;
 use tokio::sync::{mpsc, Mutex, Semaphore};
 use tokio::sync::watch;
 use tokio::time::{timeout, Duration};
 
 pub struct ocr_task {
     pub id: String,
+    pub state: Arc<AppState>,
 }
 
 #[async_trait]
@@ -21,11 +23,15 @@ impl Task for ocr_task {
         self.id.clone()
     }
 
+    fn resources(&self) -> Vec<&'static str> {
+        vec![resources::OCR]
+    }
+
     async fn execute(
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         println!("开始 {} 任务.", self.id);
 
@@ -45,15 +51,11 @@ impl Task for ocr_task {
         }
         drop(context_reader);
 
-        // 修复：从app_handle获取AppState
-        let state = app_handle.state::<Arc<crate::state::AppState>>();
-
-        // 2. 从 State 守卫中拿到内部的 Arc<AppState>
-        let state_arc = state.inner().clone();
+        let state_arc = self.state.clone();
 
         // 初始化OCR引擎
         //如果不clone，父函数就会在调用子函数后“失去”它的 state_arc。
-        initialize_ocr_pool(state_arc.clone(), &app_handle, 6)
+        initialize_ocr_pool(state_arc.clone(), 6)
             .await
             .ok();
 
@@ -89,7 +91,7 @@ impl Task for ocr_task {
             "message": "OCR任务开始，准备初始化"
         });
 
-        if let Err(e) = app_handle.emit("ocr_task_event", start_event) {
+        if let Err(e) = events.emit("ocr_task_event", start_event) {
             eprintln!("启动任务失败：无法发送初始事件。错误：{}", e);
             return Err(e.into());
         }
@@ -102,7 +104,7 @@ impl Task for ocr_task {
             "message": "OCR任务已准备就绪，开始处理视频帧"
         });
 
-        if let Err(e) = app_handle.emit("ocr_task_event", ready_event) {
+        if let Err(e) = events.emit("ocr_task_event", ready_event) {
             eprintln!("发送OCR就绪信号失败：{}", e);
             return Err(e.into());
         }
@@ -181,7 +183,7 @@ impl Task for ocr_task {
                             "reason": "控制通道已关闭，任务将停止。",
                             "message": "控制通道已关闭，任务将停止。",
                         });
-                        let _ = app_handle.emit("ocr_task_event", stop_event);
+                        let _ = events.emit("ocr_task_event", stop_event);
                         stop_signal_received.store(true, Ordering::SeqCst);
                         break;
                     }
@@ -196,18 +198,18 @@ impl Task for ocr_task {
                             "reason": "信号变更为 Paused。",
                             "message": "信号变更为 Paused。",
                         });
-                        let _ = app_handle.emit("ocr_task_event", stop_event);
+                        let _ = events.emit("ocr_task_event", stop_event);
                             // 等待恢复信号
                             while let Ok(()) = control_rx.changed().await {
                                 if *control_rx.borrow() == ControlSignal::Running {
                                     break;
                                 }
                             }
-                            let _ = app_handle.emit("ocr_event", "resume".to_string());
+                            let _ = events.emit("ocr_event", "resume".to_string());
                         }
                         ControlSignal::Stopped => {
                             println!("信号变更为 Stopped，通知前端并退出任务。");
-                            let _ = app_handle.emit("ocr_event", "stop".to_string());
+                            let _ = events.emit("ocr_event", "stop".to_string());
                             stop_signal_received.store(true, Ordering::SeqCst);
                             break;
                         }
@@ -229,7 +231,7 @@ impl Task for ocr_task {
                         // 克隆所有需要在新任务中使用的共享资源
                         let state_clone = state_arc.clone();
                         let context_clone = context.clone();
-                        let app_handle_clone = app_handle.clone();
+                        let events_clone = events.clone();
                         let task_id_clone = self.id.clone();
                         let stop_signal_clone = stop_signal_received.clone();
                         let processed_frames_clone = processed_frames.clone();
@@ -272,7 +274,7 @@ impl Task for ocr_task {
                                     let error_event = serde_json::json!({
                                         "type": "error", "task_id": task_id_clone, "error": e.to_string()
                                     });
-                                    let _ = app_handle_clone.emit("ocr_task_event", error_event);
+                                    let _ = events_clone.emit("ocr_task_event", error_event);
 
                                     if error_count >= max_consecutive_errors {
                                         eprintln!("连续 {} 次处理帧失败，任务将终止", max_consecutive_errors);
@@ -306,7 +308,7 @@ impl Task for ocr_task {
             "processed_frames": final_processed_count,
             "timestamp": chrono::Utc::now().timestamp_millis()
         });
-        let _ = app_handle.emit("ocr_task_event", stop_event);
+        let _ = events.emit("ocr_task_event", stop_event);
 
         // -----------------------------------------------------
         // 第一个逻辑块：清理 ocr_channel
@@ -0,0 +1,492 @@
+//! 声明式测试流水线：用 JSON 描述单次样本运行的子工作流，运行时加载，每个任务可选择不同的流水线。
+//!
+//! 每个步骤通过 `type` 对应一个已有的 `Task` 实现，步骤参数直接写在步骤对象中，
+//! `depends_on` 声明依赖（可带条件）。内置流水线随程序发布，
+//! 数据目录下 `pipelines/*.json` 中的同名流水线会覆盖内置版本，修改后无需重新编译。
+
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::config::AppConfig;
+use crate::services::active_task::{ActiveTask, VisualWakeConfig};
+use crate::services::analysis_task::{analysis_task, AnalysisSettings};
+use crate::services::asr_task::AsrTask;
+use crate::services::audio_task::audio_task;
+use crate::services::checkpoint_task::checkpoint_task;
+use crate::services::finish_task::finish_task;
+use crate::services::ocr_task::ocr_task;
+use crate::services::run_plan::SampleRun;
+use crate::services::workflow::{Condition, TaskPolicy, Workflow};
+use crate::state::AppState;
+
+/// 未选择流水线的任务使用的内置流水线
+pub const DEFAULT_PIPELINE: &str = "default";
+
+const BUILTIN_PIPELINES: &[&str] = &[
+    include_str!("../../pipelines/default.json"),
+    include_str!("../../pipelines/asr_only.json"),
+    include_str!("../../pipelines/default_pipelined.json"),
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub steps: Vec<PipelineStep>,
+    /// 为 0 时逐个样本执行。大于 0 时，样本的采集步骤（播放、检测、识别等）结束后即开始下一个样本，
+    /// 分析和保存步骤在后台完成，最多允许这么多个样本在后台尚未完成
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub max_background_samples: usize,
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineStep {
+    /// 步骤ID，实际任务ID为 "{id}_{样本ID}"
+    pub id: String,
+    #[serde(flatten)]
+    pub kind: StepKind,
+    #[serde(default)]
+    pub depends_on: Vec<StepDependency>,
+    /// 覆盖步骤类型默认的超时时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// 覆盖步骤类型默认的重试次数
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retries: Option<u32>,
+}
+
+/// 步骤类型及其参数，引用其他步骤时使用步骤ID
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StepKind {
+    /// 播放本次运行的唤醒词音频
+    WakeWordAudio,
+    /// 播放样本的语音指令音频
+    CommandAudio,
+    /// 视觉唤醒检测
+    VisualDetection,
+    /// 语音识别，`reference` 决定以唤醒词还是样本文本作为参考
+    Asr { reference: AsrReference },
+    /// 判断唤醒是否成功，失败时下游的 completed 依赖被跳过
+    Checkpoint {
+        asr: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        visual: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        expected_responses: Vec<String>,
+    },
+    /// 屏幕文字识别
+    Ocr,
+    /// 大模型评估
+    Analysis { asr: String },
+    /// 汇总并保存样本结果
+    Finish {
+        asr: String,
+        analysis: String,
+        command_audio: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        visual: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        ocr: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AsrReference {
+    WakeWord,
+    Sample,
+}
+
+/// 依赖可以只写步骤ID（默认条件），也可以写成 {"step": ..., "condition": ...}
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum StepDependency {
+    Step(String),
+    Conditional {
+        step: String,
+        condition: DependencyCondition,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCondition {
+    Executed,
+    Always,
+    Completed,
+}
+
+impl StepDependency {
+    pub fn step(&self) -> &str {
+        match self {
+            StepDependency::Step(step) => step,
+            StepDependency::Conditional { step, .. } => step,
+        }
+    }
+
+    fn condition(&self) -> Condition {
+        match self {
+            StepDependency::Step(_) => Condition::Executed,
+            StepDependency::Conditional { condition, .. } => match condition {
+                DependencyCondition::Executed => Condition::Executed,
+                DependencyCondition::Always => Condition::Always,
+                DependencyCondition::Completed => Condition::Completed,
+            },
+        }
+    }
+}
+
+impl StepKind {
+    fn type_name(&self) -> &'static str {
+        match self {
+            StepKind::WakeWordAudio => "wake_word_audio",
+            StepKind::CommandAudio => "command_audio",
+            StepKind::VisualDetection => "visual_detection",
+            StepKind::Asr { .. } => "asr",
+            StepKind::Checkpoint { .. } => "checkpoint",
+            StepKind::Ocr => "ocr",
+            StepKind::Analysis { .. } => "analysis",
+            StepKind::Finish { .. } => "finish",
+        }
+    }
+
+    /// 不占用测试设备、可以与下一个样本的采集重叠执行的步骤
+    fn runs_in_background(&self) -> bool {
+        matches!(self, StepKind::Analysis { .. } | StepKind::Finish { .. })
+    }
+
+    /// 参数中引用的步骤及其应有的类型
+    fn references(&self) -> Vec<(&str, &'static str)> {
+        match self {
+            StepKind::Checkpoint { asr, visual, .. } => {
+                let mut refs = vec![(asr.as_str(), "asr")];
+                refs.extend(visual.as_deref().map(|v| (v, "visual_detection")));
+                refs
+            }
+            StepKind::Analysis { asr } => vec![(asr.as_str(), "asr")],
+            StepKind::Finish { asr, analysis, command_audio, visual, ocr } => {
+                let mut refs = vec![
+                    (asr.as_str(), "asr"),
+                    (analysis.as_str(), "analysis"),
+                    (command_audio.as_str(), "command_audio"),
+                ];
+                refs.extend(visual.as_deref().map(|v| (v, "visual_detection")));
+                refs.extend(ocr.as_deref().map(|o| (o, "ocr")));
+                refs
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// 构建样本子工作流所需的任务级参数
+pub struct SampleEnv<'a> {
+    pub task_id: i64,
+    pub visual_config: &'a VisualWakeConfig,
+    pub analysis_settings: &'a AnalysisSettings,
+    /// 任务通过它访问数据库、HTTP 客户端和 OCR 引擎池
+    pub state: &'a Arc<AppState>,
+}
+
+impl PipelineDefinition {
+    pub fn from_json(content: &str) -> Result<Self> {
+        let pipeline: PipelineDefinition = serde_json::from_str(content)?;
+        pipeline.check()?;
+        Ok(pipeline)
+    }
+
+    /// 检查步骤ID唯一、依赖和参数引用的步骤存在且类型正确。
+    /// 环路、上下文数据等图级别的问题由 `Workflow::validate` 在构建后检查
+    pub fn check(&self) -> Result<()> {
+        if self.name.trim().is_empty() {
+            return Err(anyhow!("流水线名称不能为空"));
+        }
+        let mut types: HashMap<&str, &'static str> = HashMap::new();
+        for step in &self.steps {
+            if types.insert(step.id.as_str(), step.kind.type_name()).is_some() {
+                return Err(anyhow!("流水线 '{}' 中步骤ID '{}' 重复", self.name, step.id));
+            }
+        }
+
+        let mut errors = Vec::new();
+        for step in &self.steps {
+            for dependency in &step.depends_on {
+                if !types.contains_key(dependency.step()) {
+                    errors.push(format!("步骤 '{}' 依赖的步骤 '{}' 不存在", step.id, dependency.step()));
+                }
+            }
+            for (reference, expected) in step.kind.references() {
+                match types.get(reference) {
+                    None => errors.push(format!("步骤 '{}' 引用的步骤 '{}' 不存在", step.id, reference)),
+                    Some(actual) if *actual != expected => errors.push(format!(
+                        "步骤 '{}' 引用的步骤 '{}' 类型为 {}，应为 {}",
+                        step.id, reference, actual, expected
+                    )),
+                    Some(_) => {}
+                }
+            }
+        }
+        if self.steps.iter().filter(|s| matches!(s.kind, StepKind::Finish { .. })).count() != 1 {
+            errors.push("流水线必须有且只有一个 finish 步骤".to_string());
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("流水线 '{}' 无效: {}", self.name, errors.join("; ")))
+        }
+    }
+
+    /// 样本采集步骤（分析和保存之外的步骤）对应的任务ID，流水线执行时这些任务都结束后开始下一个样本
+    pub fn capture_task_ids(&self, sample_id: u32) -> Vec<String> {
+        self.steps
+            .iter()
+            .filter(|step| !step.kind.runs_in_background())
+            .map(|step| format!("{}_{}", step.id, sample_id))
+            .collect()
+    }
+
+    /// 按流水线定义构建一次样本运行的子工作流。`index` 为本次运行在计划中的序号
+    pub fn build_sample_workflow(&self, env: &SampleEnv<'_>, index: usize, run: &SampleRun) -> Workflow {
+        let (mut workflow, _) = Workflow::new();
+        let sample = &run.sample;
+        let wakeword = &run.wakeword;
+        let task_id_of = |step: &str| format!("{}_{}", step, sample.id);
+
+        for step in &self.steps {
+            let id = task_id_of(&step.id);
+            match &step.kind {
+                StepKind::WakeWordAudio => workflow.add_task_with_policy(
+                    audio_task {
+                        id,
+                        keyword: wakeword.text.clone(),
+                        url: wakeword.audio_file.clone(),
+                    },
+                    step.policy(audio_task::policy()),
+                ),
+                StepKind::CommandAudio => workflow.add_task_with_policy(
+                    audio_task {
+                        id,
+                        keyword: sample.text.clone(),
+                        url: sample.audio_file.clone(),
+                    },
+                    step.policy(audio_task::policy()),
+                ),
+                StepKind::VisualDetection => {
+                    let active_task = ActiveTask::new(id, env.visual_config.clone());
+                    let policy = step.policy(active_task.policy());
+                    workflow.add_task_with_policy(active_task, policy);
+                }
+                StepKind::Asr { reference } => {
+                    let example = match reference {
+                        AsrReference::WakeWord => wakeword.text.clone(),
+                        AsrReference::Sample => sample.text.clone(),
+                    };
+                    workflow.add_task_with_policy(AsrTask::new(id, example), step.policy(AsrTask::policy()));
+                }
+                StepKind::Checkpoint { asr, visual, expected_responses } => workflow.add_task_with_policy(
+                    checkpoint_task::new_with_sample_info(
+                        id,
+                        visual.as_deref().map(task_id_of),
+                        task_id_of(asr),
+                        expected_responses.clone(),
+                        (index + 1) as u32,
+                        wakeword.text.clone(),
+                    ),
+                    step.policy(TaskPolicy::default()),
+                ),
+                StepKind::Ocr => workflow.add_task_with_policy(
+                    ocr_task {
+                        id,
+                        state: env.state.clone(),
+                    },
+                    step.policy(TaskPolicy::default()),
+                ),
+                StepKind::Analysis { asr } => workflow.add_task_with_policy(
+                    analysis_task::new(
+                        id,
+                        task_id_of(asr),
+                        env.state.http_client.clone(),
+                        env.analysis_settings.clone(),
+                    ),
+                    step.policy(TaskPolicy::default()),
+                ),
+                StepKind::Finish { asr, analysis, command_audio, visual, ocr } => {
+                    let finish = match visual {
+                        Some(visual) => finish_task::new_with_active_task(
+                            id,
+                            env.task_id,
+                            sample.id,
+                            task_id_of(asr),
+                            task_id_of(analysis),
+                            task_id_of(visual),
+                            ocr.as_deref().map(task_id_of),
+                            task_id_of(command_audio),
+                            env.state.db.clone(),
+                        ),
+                        None => finish_task::new_with_dependencies(
+                            id,
+                            env.task_id,
+                            sample.id,
+                            task_id_of(asr),
+                            task_id_of(analysis),
+                            String::new(),
+                            ocr.as_deref().map(task_id_of),
+                            task_id_of(command_audio),
+                            env.state.db.clone(),
+                        ),
+                    };
+                    workflow.add_task_with_policy(
+                        finish.with_run(wakeword.id, run.run_index),
+                        step.policy(TaskPolicy::default()),
+                    );
+                }
+            }
+        }
+
+        for step in &self.steps {
+            let id = task_id_of(&step.id);
+            for dependency in &step.depends_on {
+                workflow.add_conditional_dependency(&id, &task_id_of(dependency.step()), dependency.condition());
+            }
+        }
+
+        workflow
+    }
+}
+
+impl PipelineStep {
+    /// 在步骤类型默认策略的基础上应用定义中的覆盖项
+    fn policy(&self, mut policy: TaskPolicy) -> TaskPolicy {
+        if let Some(secs) = self.timeout_secs {
+            policy.timeout = Some(Duration::from_secs(secs));
+        }
+        if let Some(retries) = self.retries {
+            policy.max_retries = retries;
+        }
+        policy
+    }
+}
+
+/// 用户自定义流水线所在目录
+pub fn pipelines_dir() -> Result<PathBuf> {
+    Ok(AppConfig::get_data_dir()?.join("pipelines"))
+}
+
+/// 列出所有可用流水线：内置流水线在前，数据目录中的同名文件覆盖内置版本。
+/// 无法解析的文件只记录日志，不影响其它流水线
+pub fn list_pipelines() -> Result<Vec<PipelineDefinition>> {
+    let mut pipelines = Vec::new();
+    for content in BUILTIN_PIPELINES {
+        pipelines.push(PipelineDefinition::from_json(content)?);
+    }
+
+    let dir = pipelines_dir()?;
+    if !dir.exists() {
+        return Ok(pipelines);
+    }
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(&dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let builtin_names: HashSet<String> = pipelines.iter().map(|p| p.name.clone()).collect();
+    for path in paths {
+        let pipeline = match std::fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|content| PipelineDefinition::from_json(&content))
+        {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                log::warn!("[PIPELINE] Skipping invalid pipeline file {:?}: {}", path, e);
+                continue;
+            }
+        };
+        if builtin_names.contains(&pipeline.name) {
+            log::info!("[PIPELINE] {:?} overrides built-in pipeline '{}'", path, pipeline.name);
+        }
+        pipelines.retain(|p| p.name != pipeline.name);
+        pipelines.push(pipeline);
+    }
+    Ok(pipelines)
+}
+
+/// 按名称加载流水线
+pub fn load_pipeline(name: &str) -> Result<PipelineDefinition> {
+    list_pipelines()?
+        .into_iter()
+        .find(|p| p.name == name)
+        .ok_or_else(|| anyhow!("流水线 '{}' 不存在", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_pipelines_are_valid() {
+        for content in BUILTIN_PIPELINES {
+            PipelineDefinition::from_json(content).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_dependency_forms() {
+        let pipeline = PipelineDefinition::from_json(
+            r#"{
+                "name": "ocr",
+                "steps": [
+                    { "id": "wakeword_task", "type": "wake_word_audio" },
+                    { "id": "ocr_task", "type": "ocr", "depends_on": ["wakeword_task"], "timeout_secs": 20 },
+                    { "id": "audio_task", "type": "command_audio",
+                      "depends_on": [{ "step": "ocr_task", "condition": "completed" }] },
+                    { "id": "asr_task", "type": "asr", "reference": "sample", "depends_on": ["audio_task"], "retries": 2 },
+                    { "id": "analysis_task", "type": "analysis", "asr": "asr_task", "depends_on": ["asr_task"] },
+                    { "id": "finish_task", "type": "finish", "asr": "asr_task", "analysis": "analysis_task",
+                      "command_audio": "audio_task", "ocr": "ocr_task",
+                      "depends_on": [{ "step": "analysis_task", "condition": "always" }] }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let audio = &pipeline.steps[2];
+        assert!(matches!(audio.depends_on[0].condition(), Condition::Completed));
+        assert_eq!(pipeline.steps[1].policy(TaskPolicy::default()).timeout, Some(Duration::from_secs(20)));
+        assert_eq!(pipeline.steps[3].policy(AsrTask::policy()).max_retries, 2);
+        assert_eq!(pipeline.max_background_samples, 0);
+        assert_eq!(
+            pipeline.capture_task_ids(7),
+            vec!["wakeword_task_7", "ocr_task_7", "audio_task_7", "asr_task_7"]
+        );
+    }
+
+    #[test]
+    fn test_invalid_references() {
+        let error = PipelineDefinition::from_json(
+            r#"{
+                "name": "broken",
+                "steps": [
+                    { "id": "asr_task", "type": "asr", "reference": "sample", "depends_on": ["audio_task"] },
+                    { "id": "finish_task", "type": "finish", "asr": "asr_task", "analysis": "asr_task",
+                      "command_audio": "audio_task" }
+                ]
+            }"#,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("依赖的步骤 'audio_task' 不存在"), "{}", error);
+        assert!(error.contains("类型为 asr，应为 analysis"), "{}", error);
+    }
+}
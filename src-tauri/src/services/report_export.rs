@@ -0,0 +1,621 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, Utc};
+use rust_xlsxwriter::{Color, Format, FormatBorder, RowNum, Workbook, Worksheet};
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+use crate::db::database::DatabaseService;
+use crate::models::{
+    AnalysisResult, MachineResponseData, SampleRunKey, SampleStability, Task, TaskStatistics,
+    TestSample, TimingData,
+};
+use crate::services::task_statistics::{compute_sample_stability, refresh_task_statistics};
+use crate::services::wake_detection_meta_executor::WakeDetectionResult;
+
+/// 导出一个任务所需的全部数据
+pub struct TaskReportData {
+    pub task: Task,
+    pub statistics: TaskStatistics,
+    pub stability: Vec<SampleStability>,
+    pub samples: Vec<TestSample>,
+    /// 每次样本运行一条，按保存顺序排列
+    pub results: Vec<(SampleRunKey, AnalysisResult)>,
+    pub timings: HashMap<SampleRunKey, TimingData>,
+    pub responses: HashMap<SampleRunKey, MachineResponseData>,
+    pub wake_results: Vec<WakeDetectionResult>,
+    pub wake_words: HashMap<u32, String>,
+}
+
+impl TaskReportData {
+    pub async fn load(db: &DatabaseService, task_id: i64) -> Result<Self> {
+        let task = db
+            .get_task_by_id(task_id)
+            .await?
+            .ok_or_else(|| anyhow!("任务 {} 不存在", task_id))?;
+        // 导出前重新汇总，避免手动修改结果后统计过期
+        let statistics = refresh_task_statistics(db, task_id).await?;
+        let wake_words = db
+            .get_all_wake_words()
+            .await?
+            .into_iter()
+            .map(|w| (w.id, w.text))
+            .collect();
+
+        Ok(Self {
+            task,
+            statistics,
+            stability: compute_sample_stability(db, task_id).await?,
+            samples: db.get_samples_by_task_id(task_id).await?,
+            results: db.list_analysis_results_by_task(task_id).await?,
+            timings: db.list_timing_data_by_task(task_id).await?.into_iter().collect(),
+            responses: db.list_machine_responses_by_task(task_id).await?.into_iter().collect(),
+            wake_results: db.get_wake_detection_results_by_task(task_id).await?,
+            wake_words,
+        })
+    }
+
+    /// 本次运行使用的唤醒词（旧数据未记录时取任务的第一个唤醒词）
+    fn run_wake_word(&self, key: Option<&SampleRunKey>) -> String {
+        key.map(|k| k.wake_word_id as u32)
+            .filter(|id| *id > 0)
+            .or_else(|| self.task.wake_word_ids.first().copied())
+            .and_then(|id| self.wake_words.get(&id))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// 按样本顺序展开的运行列表，同一样本的多次运行相邻排列；
+    /// 尚未执行的样本保留一行，结果为 None
+    fn sample_runs(&self) -> Vec<(&TestSample, Option<&(SampleRunKey, AnalysisResult)>)> {
+        let mut runs_by_sample: HashMap<i64, Vec<&(SampleRunKey, AnalysisResult)>> = HashMap::new();
+        for run in &self.results {
+            runs_by_sample.entry(run.0.sample_id).or_default().push(run);
+        }
+        let mut rows = Vec::new();
+        for sample in &self.samples {
+            match runs_by_sample.remove(&(sample.id as i64)) {
+                Some(runs) => rows.extend(runs.into_iter().map(|run| (sample, Some(run)))),
+                None => rows.push((sample, None)),
+            }
+        }
+        rows
+    }
+}
+
+/// 导出一个或多个任务的测试报告
+pub async fn export_task_report(db: &DatabaseService, task_ids: &[i64], path: &Path) -> Result<()> {
+    if task_ids.is_empty() {
+        return Err(anyhow!("没有选择要导出的任务"));
+    }
+    let mut reports = Vec::with_capacity(task_ids.len());
+    for task_id in task_ids {
+        reports.push(TaskReportData::load(db, *task_id).await?);
+    }
+    write_task_report(&reports, path)?;
+    log::info!(
+        "[REPORT_EXPORT] Exported {} task(s) to {}",
+        reports.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// 单元格内容
+enum Cell {
+    Text(String),
+    Number(f64),
+    Percent(f64),
+    Empty,
+}
+
+impl Cell {
+    fn text(value: impl Into<String>) -> Self {
+        Cell::Text(value.into())
+    }
+
+    fn opt_number<T: Into<f64>>(value: Option<T>) -> Self {
+        value.map(|v| Cell::Number(v.into())).unwrap_or(Cell::Empty)
+    }
+
+    fn opt_percent(value: Option<f64>) -> Self {
+        value.map(Cell::Percent).unwrap_or(Cell::Empty)
+    }
+
+    fn opt_time(value: Option<DateTime<Utc>>) -> Self {
+        value
+            .map(|t| Cell::Text(t.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S%.3f").to_string()))
+            .unwrap_or(Cell::Empty)
+    }
+}
+
+struct Formats {
+    header: Format,
+    label: Format,
+    percent: Format,
+}
+
+impl Formats {
+    fn new() -> Self {
+        Self {
+            header: Format::new()
+                .set_bold()
+                .set_background_color(Color::RGB(0xD9E1F2))
+                .set_border(FormatBorder::Thin),
+            label: Format::new().set_bold(),
+            percent: Format::new().set_num_format("0.00%"),
+        }
+    }
+}
+
+/// 按行顺序写入的工作表
+struct Sheet<'a> {
+    worksheet: Worksheet,
+    row: RowNum,
+    formats: &'a Formats,
+}
+
+impl<'a> Sheet<'a> {
+    fn new(name: &str, formats: &'a Formats) -> Result<Self> {
+        let mut worksheet = Worksheet::new();
+        worksheet.set_name(name)?;
+        Ok(Self {
+            worksheet,
+            row: 0,
+            formats,
+        })
+    }
+
+    fn header(&mut self, titles: &[&str]) -> Result<()> {
+        for (col, title) in titles.iter().enumerate() {
+            self.worksheet
+                .write_string_with_format(self.row, col as u16, *title, &self.formats.header)?;
+            // 中文标题按字符数估算列宽
+            let width = (title.chars().count() * 2 + 2).max(10) as f64;
+            self.worksheet.set_column_width(col as u16, width)?;
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    /// 模板中每块数据上方的 "名称 值 名称 值 ..." 概要行
+    fn summary(&mut self, pairs: Vec<(&str, Cell)>) -> Result<()> {
+        for (index, (label, value)) in pairs.into_iter().enumerate() {
+            let col = (index * 2) as u16;
+            self.worksheet
+                .write_string_with_format(self.row, col, label, &self.formats.label)?;
+            self.write_cell(col + 1, value)?;
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    fn row(&mut self, cells: Vec<Cell>) -> Result<()> {
+        for (col, cell) in cells.into_iter().enumerate() {
+            self.write_cell(col as u16, cell)?;
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    fn write_cell(&mut self, col: u16, cell: Cell) -> Result<()> {
+        match cell {
+            Cell::Text(text) => {
+                self.worksheet.write_string(self.row, col, text)?;
+            }
+            Cell::Number(number) => {
+                self.worksheet.write_number(self.row, col, number)?;
+            }
+            Cell::Percent(number) => {
+                self.worksheet
+                    .write_number_with_format(self.row, col, number, &self.formats.percent)?;
+            }
+            Cell::Empty => {}
+        }
+        Ok(())
+    }
+
+    fn skip(&mut self, rows: RowNum) {
+        self.row += rows;
+    }
+}
+
+fn interaction_status(result: &AnalysisResult) -> &'static str {
+    match result.result_status.as_deref() {
+        Some("wake_failed") => "Fail",
+        Some("timeout") => "Timeout",
+        Some("evaluation_error") => "Error",
+        _ if result.assessment.valid => "Success",
+        _ => "Fail",
+    }
+}
+
+fn success_text(success: bool) -> &'static str {
+    if success {
+        "Success"
+    } else {
+        "Fail"
+    }
+}
+
+/// 按模板布局写出报告：汇总、唤醒、交互、评估详情、时间参数、车机响应
+pub fn write_task_report(reports: &[TaskReportData], path: &Path) -> Result<()> {
+    let formats = Formats::new();
+    let mut workbook = Workbook::new();
+
+    workbook.push_worksheet(summary_sheet(reports, &formats)?.worksheet);
+    workbook.push_worksheet(wake_sheet(reports, &formats)?.worksheet);
+    workbook.push_worksheet(interaction_sheet(reports, &formats)?.worksheet);
+    workbook.push_worksheet(analysis_sheet(reports, &formats)?.worksheet);
+    workbook.push_worksheet(timing_sheet(reports, &formats)?.worksheet);
+    workbook.push_worksheet(response_sheet(reports, &formats)?.worksheet);
+    workbook.push_worksheet(stability_sheet(reports, &formats)?.worksheet);
+
+    workbook.save(path)?;
+    Ok(())
+}
+
+fn summary_sheet<'a>(reports: &[TaskReportData], formats: &'a Formats) -> Result<Sheet<'a>> {
+    let mut sheet = Sheet::new("汇总", formats)?;
+    sheet.header(&[
+        "任务ID",
+        "任务名称",
+        "创建时间",
+        "状态",
+        "样本总数",
+        "已完成样本",
+        "通过数",
+        "通过率",
+        "评估失败数",
+        "平均得分",
+        "唤醒次数",
+        "唤醒成功次数",
+        "唤醒率",
+        "字错误率",
+        "词准确率",
+        "句准确率",
+        "识别成功率",
+        "识别时间P50(ms)",
+        "识别时间P90(ms)",
+        "识别时间P99(ms)",
+        "平均交互响应时间(ms)",
+        "交互响应P50(ms)",
+        "交互响应P90(ms)",
+        "交互响应P99(ms)",
+    ])?;
+    for report in reports {
+        let task = &report.task;
+        let stats = &report.statistics;
+        sheet.row(vec![
+            Cell::Number(task.id as f64),
+            Cell::text(&task.name),
+            Cell::text(&task.created_at),
+            Cell::text(&task.task_status),
+            Cell::Number(stats.total_samples as f64),
+            Cell::Number(stats.completed_samples as f64),
+            Cell::Number(stats.passed_samples as f64),
+            Cell::opt_percent(stats.pass_rate),
+            Cell::Number(stats.evaluation_errors as f64),
+            Cell::opt_number(stats.average_score),
+            Cell::Number(stats.wake_attempts as f64),
+            Cell::Number(stats.wake_successes as f64),
+            Cell::opt_percent(stats.wake_rate),
+            Cell::opt_percent(task.character_error_rate.map(f64::from)),
+            Cell::opt_percent(task.word_accuracy.map(f64::from)),
+            Cell::opt_percent(task.sentence_accuracy.map(f64::from)),
+            Cell::opt_percent(stats.recognition_success_rate),
+            Cell::opt_number(stats.recognition_p50_ms),
+            Cell::opt_number(stats.recognition_p90_ms),
+            Cell::opt_number(stats.recognition_p99_ms),
+            Cell::opt_number(stats.response_avg_ms),
+            Cell::opt_number(stats.response_p50_ms),
+            Cell::opt_number(stats.response_p90_ms),
+            Cell::opt_number(stats.response_p99_ms),
+        ])?;
+    }
+    Ok(sheet)
+}
+
+/// 对应模板 Sheet1：唤醒测试
+fn wake_sheet<'a>(reports: &[TaskReportData], formats: &'a Formats) -> Result<Sheet<'a>> {
+    let mut sheet = Sheet::new("唤醒", formats)?;
+    for report in reports {
+        let results = &report.wake_results;
+        let average_duration = (!results.is_empty()).then(|| {
+            results.iter().map(|r| r.duration_ms as f64).sum::<f64>() / results.len() as f64
+        });
+
+        sheet.summary(vec![
+            ("任务名称", Cell::text(&report.task.name)),
+            ("测试类型", Cell::text("唤醒")),
+            ("测试数量", Cell::Number(results.len() as f64)),
+            ("成功率", Cell::opt_percent(report.statistics.wake_rate)),
+            ("平均唤醒响应时间(ms)", Cell::opt_number(average_duration)),
+        ])?;
+        sheet.skip(1);
+        sheet.header(&["序号", "测试语料", "结果", "唤醒响应时间（ms）", "判断依据", "详细结果", "备注"])?;
+
+        for (index, result) in results.iter().enumerate() {
+            let wake_word = report
+                .wake_words
+                .get(&result.wake_word_id)
+                .cloned()
+                .unwrap_or_default();
+            // 有识别文本时以语音回复为依据，否则为视觉检测
+            let (basis, detail) = match result.asr_result.as_deref().filter(|t| !t.is_empty()) {
+                Some(text) => ("语音", format!("语音识别：{}", text)),
+                None => (
+                    "图像",
+                    format!(
+                        "图像识别：置信度 {}",
+                        result
+                            .confidence
+                            .map(|c| format!("{:.2}", c))
+                            .unwrap_or_else(|| "-".to_string())
+                    ),
+                ),
+            };
+            sheet.row(vec![
+                Cell::Number((index + 1) as f64),
+                Cell::Text(wake_word),
+                Cell::text(success_text(result.success)),
+                Cell::Number(result.duration_ms as f64),
+                Cell::text(basis),
+                Cell::Text(detail),
+                Cell::Empty,
+            ])?;
+        }
+        sheet.skip(2);
+    }
+    Ok(sheet)
+}
+
+/// 对应模板 Sheet2：交互测试
+fn interaction_sheet<'a>(reports: &[TaskReportData], formats: &'a Formats) -> Result<Sheet<'a>> {
+    let mut sheet = Sheet::new("交互", formats)?;
+    for report in reports {
+        let runs = report.sample_runs();
+        sheet.summary(vec![
+            ("任务名称", Cell::text(&report.task.name)),
+            ("测试类型", Cell::text("交互")),
+            ("测试数量", Cell::Number(runs.len() as f64)),
+            ("成功率", Cell::opt_percent(report.statistics.pass_rate)),
+            ("平均交互响应时间(ms)", Cell::opt_number(report.statistics.response_avg_ms)),
+        ])?;
+        sheet.skip(1);
+        sheet.header(&[
+            "序号",
+            "唤醒语料",
+            "唤醒结果",
+            "测试语料",
+            "测试结果",
+            "交互响应时间（ms）",
+            "判断依据",
+            "详细结果",
+            "备注",
+        ])?;
+
+        for (index, (sample, run)) in runs.into_iter().enumerate() {
+            let wake_word = report.run_wake_word(run.map(|(key, _)| key));
+            let Some((key, result)) = run else {
+                sheet.row(vec![
+                    Cell::Number((index + 1) as f64),
+                    Cell::text(&wake_word),
+                    Cell::Empty,
+                    Cell::text(&sample.text),
+                    Cell::Empty,
+                    Cell::Empty,
+                    Cell::Empty,
+                    Cell::Empty,
+                    Cell::text("未执行"),
+                ])?;
+                continue;
+            };
+            let wake_success = result.result_status.as_deref() != Some("wake_failed");
+            let response_time = report
+                .timings
+                .get(key)
+                .and_then(|t| t.interaction_response_time_ms);
+            let detail = result
+                .recognized_text
+                .as_deref()
+                .map(|text| format!("语音识别：{}", text))
+                .unwrap_or_default();
+
+            sheet.row(vec![
+                Cell::Number((index + 1) as f64),
+                Cell::text(&wake_word),
+                Cell::text(success_text(wake_success)),
+                Cell::text(&sample.text),
+                Cell::text(interaction_status(result)),
+                Cell::opt_number(response_time.map(|v| v as f64)),
+                Cell::text("语音"),
+                Cell::Text(detail),
+                Cell::Text(result.assessment.suggestions.join("；")),
+            ])?;
+        }
+        sheet.skip(2);
+    }
+    Ok(sheet)
+}
+
+fn analysis_sheet<'a>(reports: &[TaskReportData], formats: &'a Formats) -> Result<Sheet<'a>> {
+    let mut sheet = Sheet::new("评估详情", formats)?;
+
+    // 不同任务可能使用不同的评分标准，取所有维度的并集作为列
+    let dimension_keys: BTreeSet<String> = reports
+        .iter()
+        .flat_map(|r| r.results.iter().map(|(_, result)| result))
+        .flat_map(|r| r.assessment.dimensions.keys().cloned())
+        .collect();
+
+    let mut titles = vec![
+        "任务ID", "任务名称", "序号", "样本ID", "测试语料", "唤醒词", "执行次序", "识别文本", "状态", "总分",
+        "是否通过",
+    ];
+    titles.extend(dimension_keys.iter().map(String::as_str));
+    titles.extend([
+        "建议",
+        "替换错误",
+        "删除错误",
+        "插入错误",
+        "参考字数",
+        "提示词模板版本",
+        "测试时间",
+    ]);
+    sheet.header(&titles)?;
+
+    for report in reports {
+        for (index, (sample, run)) in report.sample_runs().into_iter().enumerate() {
+            let Some((key, result)) = run else {
+                continue;
+            };
+            let mut cells = vec![
+                Cell::Number(report.task.id as f64),
+                Cell::text(&report.task.name),
+                Cell::Number((index + 1) as f64),
+                Cell::Number(sample.id as f64),
+                Cell::text(&sample.text),
+                Cell::Text(report.run_wake_word(Some(key))),
+                Cell::Number((key.run_index + 1) as f64),
+                Cell::text(result.recognized_text.clone().unwrap_or_default()),
+                Cell::text(result.result_status.clone().unwrap_or_else(|| "completed".to_string())),
+                Cell::Number(result.assessment.overall_score),
+                Cell::text(if result.assessment.valid { "是" } else { "否" }),
+            ];
+            cells.extend(dimension_keys.iter().map(|key| {
+                Cell::opt_number(result.assessment.dimensions.get(key).map(|item| item.score))
+            }));
+            cells.extend([
+                Cell::Text(result.assessment.suggestions.join("；")),
+                Cell::opt_number(result.substitution_errors),
+                Cell::opt_number(result.deletion_errors),
+                Cell::opt_number(result.insertion_errors),
+                Cell::opt_number(result.total_words),
+                Cell::opt_number(result.prompt_template_version.map(|v| v as f64)),
+                Cell::text(result.test_time.clone().unwrap_or_default()),
+            ]);
+            sheet.row(cells)?;
+        }
+    }
+    Ok(sheet)
+}
+
+fn timing_sheet<'a>(reports: &[TaskReportData], formats: &'a Formats) -> Result<Sheet<'a>> {
+    let mut sheet = Sheet::new("时间参数", formats)?;
+    sheet.header(&[
+        "任务ID",
+        "任务名称",
+        "序号",
+        "样本ID",
+        "测试语料",
+        "唤醒词",
+        "执行次序",
+        "语音指令开始",
+        "语音指令结束",
+        "首字上屏",
+        "全部上屏",
+        "动作开始",
+        "TTS首帧",
+        "语音识别时间(ms)",
+        "交互响应时间(ms)",
+        "TTS响应时间(ms)",
+    ])?;
+
+    for report in reports {
+        for (index, (sample, run)) in report.sample_runs().into_iter().enumerate() {
+            let Some((key, timing)) = run.and_then(|(key, _)| Some((key, report.timings.get(key)?)))
+            else {
+                continue;
+            };
+            sheet.row(vec![
+                Cell::Number(report.task.id as f64),
+                Cell::text(&report.task.name),
+                Cell::Number((index + 1) as f64),
+                Cell::Number(sample.id as f64),
+                Cell::text(&sample.text),
+                Cell::Text(report.run_wake_word(Some(key))),
+                Cell::Number((key.run_index + 1) as f64),
+                Cell::opt_time(timing.voice_command_start_time),
+                Cell::opt_time(timing.voice_command_end_time),
+                Cell::opt_time(timing.first_char_appear_time),
+                Cell::opt_time(timing.full_text_appear_time),
+                Cell::opt_time(timing.action_start_time),
+                Cell::opt_time(timing.tts_first_frame_time),
+                Cell::opt_number(timing.voice_recognition_time_ms.map(|v| v as f64)),
+                Cell::opt_number(timing.interaction_response_time_ms.map(|v| v as f64)),
+                Cell::opt_number(timing.tts_response_time_ms.map(|v| v as f64)),
+            ])?;
+        }
+    }
+    Ok(sheet)
+}
+
+fn response_sheet<'a>(reports: &[TaskReportData], formats: &'a Formats) -> Result<Sheet<'a>> {
+    let mut sheet = Sheet::new("车机响应", formats)?;
+    sheet.header(&["任务ID", "任务名称", "序号", "样本ID", "测试语料", "唤醒词", "执行次序", "车机响应", "连接状态"])?;
+
+    for report in reports {
+        for (index, (sample, run)) in report.sample_runs().into_iter().enumerate() {
+            let Some((key, response)) =
+                run.and_then(|(key, _)| Some((key, report.responses.get(key)?)))
+            else {
+                continue;
+            };
+            sheet.row(vec![
+                Cell::Number(report.task.id as f64),
+                Cell::text(&report.task.name),
+                Cell::Number((index + 1) as f64),
+                Cell::Number(sample.id as f64),
+                Cell::text(&sample.text),
+                Cell::Text(report.run_wake_word(Some(key))),
+                Cell::Number((key.run_index + 1) as f64),
+                Cell::text(&response.text),
+                Cell::text(if response.connected { "已连接" } else { "未连接" }),
+            ])?;
+        }
+    }
+    Ok(sheet)
+}
+
+/// 同一样本多次执行的稳定性
+fn stability_sheet<'a>(reports: &[TaskReportData], formats: &'a Formats) -> Result<Sheet<'a>> {
+    let mut sheet = Sheet::new("稳定性", formats)?;
+    sheet.header(&[
+        "任务ID",
+        "任务名称",
+        "样本ID",
+        "测试语料",
+        "执行次数",
+        "通过次数",
+        "评估失败次数",
+        "通过比例",
+        "平均得分",
+        "得分标准差",
+        "平均识别时间(ms)",
+        "识别时间标准差(ms)",
+        "平均交互响应时间(ms)",
+        "交互响应时间标准差(ms)",
+    ])?;
+
+    for report in reports {
+        for item in &report.stability {
+            sheet.row(vec![
+                Cell::Number(report.task.id as f64),
+                Cell::text(&report.task.name),
+                Cell::Number(item.sample_id as f64),
+                Cell::text(&item.sample_text),
+                Cell::Number(item.runs as f64),
+                Cell::Number(item.passed_runs as f64),
+                Cell::Number(item.evaluation_errors as f64),
+                Cell::opt_percent(item.pass_ratio),
+                Cell::opt_number(item.score_avg),
+                Cell::opt_number(item.score_std_dev),
+                Cell::opt_number(item.recognition_avg_ms),
+                Cell::opt_number(item.recognition_std_dev_ms),
+                Cell::opt_number(item.response_avg_ms),
+                Cell::opt_number(item.response_std_dev_ms),
+            ])?;
+        }
+    }
+    Ok(sheet)
+}
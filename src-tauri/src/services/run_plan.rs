@@ -0,0 +1,200 @@
+//! 样本运行计划：决定每个测试样本搭配哪个唤醒词执行。
+//!
+//! 车型通常有多个唤醒词，策略用于让同一个任务覆盖全部唤醒词，而不必为每个唤醒词单独建任务。
+//! 样本设置了重复次数（`TestSample.repeats`）时，每个 (样本, 唤醒词) 组合连续执行多次。
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{SampleRunKey, TestSample, WakeWord};
+
+/// 唤醒词分配策略
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WakeWordStrategy {
+    /// 所有样本使用同一个唤醒词，未指定时取任务的第一个
+    Fixed {
+        #[serde(default)]
+        wake_word_id: Option<u32>,
+    },
+    /// 按样本顺序轮流使用唤醒词
+    RoundRobin,
+    /// 每个样本随机选择唤醒词，相同种子得到相同的分配
+    Random { seed: u64 },
+    /// 每个唤醒词与每个样本都组合执行一次
+    CrossProduct,
+}
+
+impl Default for WakeWordStrategy {
+    fn default() -> Self {
+        WakeWordStrategy::Fixed { wake_word_id: None }
+    }
+}
+
+impl WakeWordStrategy {
+    /// 计划执行的运行次数（含重复），用于统计完成度
+    pub fn planned_runs(&self, samples: &[TestSample], wake_word_count: usize) -> usize {
+        let per_wake_word: usize = samples.iter().map(|s| sample_repeats(s) as usize).sum();
+        match self {
+            WakeWordStrategy::CrossProduct => per_wake_word * wake_word_count,
+            _ => per_wake_word,
+        }
+    }
+}
+
+/// 样本的重复执行次数，未设置或为0时执行一次
+pub fn sample_repeats(sample: &TestSample) -> u32 {
+    sample.repeats.unwrap_or(1).max(1)
+}
+
+/// 一次样本运行：样本 + 本次使用的唤醒词 + 第几次重复
+#[derive(Debug, Clone)]
+pub struct SampleRun {
+    pub sample: TestSample,
+    pub wakeword: WakeWord,
+    /// 唤醒词在任务唤醒词列表中的位置
+    pub wake_word_index: usize,
+    /// 同一 (样本, 唤醒词) 的第几次执行，从0开始
+    pub run_index: u32,
+}
+
+impl SampleRun {
+    /// 本次运行在结果表和运行状态表中的键
+    pub fn key(&self, task_id: i64) -> SampleRunKey {
+        SampleRunKey::new(task_id, self.sample.id as i64, self.wakeword.id, self.run_index)
+    }
+}
+
+/// splitmix64，只用于按种子复现随机分配，不需要密码学强度
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+/// 按策略展开样本运行列表。`wake_words` 为任务关联的唤醒词，顺序与任务中保存的一致。
+/// 唤醒词按样本分配，同一样本的重复执行使用相同的唤醒词。
+pub fn plan_sample_runs(
+    samples: &[TestSample],
+    wake_words: &[WakeWord],
+    strategy: &WakeWordStrategy,
+) -> Result<Vec<SampleRun>, String> {
+    if wake_words.is_empty() {
+        return Err("任务没有关联的唤醒词".to_string());
+    }
+
+    let assignments: Vec<(&TestSample, usize)> = match strategy {
+        WakeWordStrategy::Fixed { wake_word_id } => {
+            let index = match wake_word_id {
+                Some(id) => wake_words
+                    .iter()
+                    .position(|w| w.id == *id)
+                    .ok_or("指定的唤醒词不属于当前任务")?,
+                None => 0,
+            };
+            samples.iter().map(|s| (s, index)).collect()
+        }
+        WakeWordStrategy::RoundRobin => samples
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s, i % wake_words.len()))
+            .collect(),
+        WakeWordStrategy::Random { seed } => {
+            let mut rng = SplitMix64(*seed);
+            samples
+                .iter()
+                .map(|s| (s, (rng.next_u64() % wake_words.len() as u64) as usize))
+                .collect()
+        }
+        // 唤醒词在外层，连续的运行共用同一唤醒词
+        WakeWordStrategy::CrossProduct => (0..wake_words.len())
+            .flat_map(|index| samples.iter().map(move |s| (s, index)))
+            .collect(),
+    };
+
+    Ok(assignments
+        .into_iter()
+        .flat_map(|(sample, wake_word_index)| {
+            (0..sample_repeats(sample)).map(move |run_index| SampleRun {
+                sample: sample.clone(),
+                wakeword: wake_words[wake_word_index].clone(),
+                wake_word_index,
+                run_index,
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(n: u32) -> Vec<TestSample> {
+        (1..=n)
+            .map(|id| TestSample {
+                id,
+                text: format!("样本{}", id),
+                audio_file: None,
+                status: None,
+                repeats: None,
+                result: None,
+            })
+            .collect()
+    }
+
+    fn wake_words(n: u32) -> Vec<WakeWord> {
+        (1..=n)
+            .map(|id| WakeWord {
+                id: id * 10,
+                text: format!("唤醒词{}", id),
+                audio_file: None,
+            })
+            .collect()
+    }
+
+    fn wake_word_ids(runs: &[SampleRun]) -> Vec<u32> {
+        runs.iter().map(|r| r.wakeword.id).collect()
+    }
+
+    #[test]
+    fn test_fixed_and_round_robin() {
+        let fixed = plan_sample_runs(&samples(3), &wake_words(2), &WakeWordStrategy::Fixed { wake_word_id: Some(20) }).unwrap();
+        assert_eq!(wake_word_ids(&fixed), vec![20, 20, 20]);
+        assert!(plan_sample_runs(&samples(3), &wake_words(2), &WakeWordStrategy::Fixed { wake_word_id: Some(99) }).is_err());
+
+        let round_robin = plan_sample_runs(&samples(3), &wake_words(2), &WakeWordStrategy::RoundRobin).unwrap();
+        assert_eq!(wake_word_ids(&round_robin), vec![10, 20, 10]);
+    }
+
+    #[test]
+    fn test_random_is_reproducible() {
+        let strategy = WakeWordStrategy::Random { seed: 42 };
+        let first = plan_sample_runs(&samples(20), &wake_words(3), &strategy).unwrap();
+        let second = plan_sample_runs(&samples(20), &wake_words(3), &strategy).unwrap();
+        assert_eq!(wake_word_ids(&first), wake_word_ids(&second));
+    }
+
+    #[test]
+    fn test_cross_product() {
+        let runs = plan_sample_runs(&samples(2), &wake_words(2), &WakeWordStrategy::CrossProduct).unwrap();
+        let pairs: Vec<(u32, u32)> = runs.iter().map(|r| (r.wakeword.id, r.sample.id)).collect();
+        assert_eq!(pairs, vec![(10, 1), (10, 2), (20, 1), (20, 2)]);
+        assert_eq!(WakeWordStrategy::CrossProduct.planned_runs(&samples(2), 2), runs.len());
+    }
+
+    #[test]
+    fn test_repeats_expand_runs() {
+        let mut samples = samples(2);
+        samples[0].repeats = Some(3);
+        samples[1].repeats = Some(0);
+        let runs = plan_sample_runs(&samples, &wake_words(2), &WakeWordStrategy::RoundRobin).unwrap();
+        let keys: Vec<(u32, u32, u32)> = runs.iter().map(|r| (r.sample.id, r.wakeword.id, r.run_index)).collect();
+        assert_eq!(keys, vec![(1, 10, 0), (1, 10, 1), (1, 10, 2), (2, 20, 0)]);
+        assert_eq!(WakeWordStrategy::RoundRobin.planned_runs(&samples, 2), 4);
+    }
+}
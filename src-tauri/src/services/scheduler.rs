@@ -0,0 +1,147 @@
+//! 定时执行：到点后复制任务（名称附加执行时间，结果与原任务分开保存）并加入测试队列。
+//! 程序关闭期间错过的执行时间按计划的 `missed_run_policy` 补执行一次或跳过。
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::models::{MissedRunPolicy, ScheduleRule, TaskSchedule};
+use crate::services::cron::CronSchedule;
+use crate::state::AppState;
+
+/// 检查到期计划的间隔
+const SCHEDULE_POLL_INTERVAL: Duration = Duration::from_secs(30);
+/// 超过执行时间这么多分钟才检查到时（程序关闭或系统休眠期间），按错过处理
+const MISSED_RUN_GRACE_MINUTES: i64 = 5;
+
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+/// 定时执行循环，随程序启动，在后台一直运行
+pub async fn run_scheduler(state: Arc<AppState>) {
+    loop {
+        if let Err(e) = run_due_schedules(&state).await {
+            log::error!("[SCHEDULER] Failed to check schedules: {}", e);
+        }
+        tokio::time::sleep(SCHEDULE_POLL_INTERVAL).await;
+    }
+}
+
+/// 时间规则对应的 cron 表达式
+pub fn rule_schedule(rule: &ScheduleRule) -> Result<CronSchedule, String> {
+    match rule {
+        ScheduleRule::Cron { expression } => CronSchedule::parse(expression),
+        ScheduleRule::Daily { time } => {
+            let time = NaiveTime::parse_from_str(time, "%H:%M")
+                .map_err(|_| format!("每日执行时间的格式应为 HH:MM: '{}'", time))?;
+            CronSchedule::parse(&format!("{} {} * * *", time.minute(), time.hour()))
+        }
+    }
+}
+
+/// `after` 之后最多 `count` 次执行时间（UTC）
+pub fn upcoming_runs(
+    rule: &ScheduleRule,
+    after: DateTime<Local>,
+    count: usize,
+) -> Result<Vec<DateTime<Utc>>, String> {
+    let schedule = rule_schedule(rule)?;
+    let mut runs = Vec::with_capacity(count);
+    let mut cursor = after.naive_local();
+    while runs.len() < count {
+        let Some(next) = schedule.next_after(cursor) else {
+            break;
+        };
+        // 夏令时跳过的本地时间不存在，顺延到下一次
+        if let Some(time) = Local.from_local_datetime(&next).earliest() {
+            runs.push(time.with_timezone(&Utc));
+        }
+        cursor = next;
+    }
+    Ok(runs)
+}
+
+/// 下一次执行时间，格式与数据库中的其他时间相同；规则不会再触发时返回 None
+pub fn next_run_at(rule: &ScheduleRule, after: DateTime<Local>) -> Result<Option<String>, String> {
+    Ok(upcoming_runs(rule, after, 1)?.first().map(format_time))
+}
+
+pub fn format_time(time: &DateTime<Utc>) -> String {
+    time.format(TIME_FORMAT).to_string()
+}
+
+fn parse_time(time: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(time, TIME_FORMAT)
+        .ok()
+        .map(|time| Utc.from_utc_datetime(&time))
+}
+
+async fn run_due_schedules(state: &AppState) -> anyhow::Result<()> {
+    let now = Local::now();
+    let now_utc = now.with_timezone(&Utc);
+    for schedule in state.db.list_schedules().await? {
+        let Some(due) = schedule.next_run_at.as_deref().and_then(parse_time) else {
+            continue;
+        };
+        if !schedule.enabled || due > now_utc {
+            continue;
+        }
+
+        let missed = now_utc - due > chrono::Duration::minutes(MISSED_RUN_GRACE_MINUTES);
+        let task_id = if missed && schedule.missed_run_policy == MissedRunPolicy::Skip {
+            log::info!("[SCHEDULER] Schedule {} missed its run at {} UTC, skipped", schedule.id, format_time(&due));
+            None
+        } else {
+            match enqueue_scheduled_run(state, &schedule, now).await {
+                Ok(task_id) => Some(task_id),
+                Err(e) => {
+                    log::error!("[SCHEDULER] Schedule {} failed to queue a run: {}", schedule.id, e);
+                    None
+                }
+            }
+        };
+
+        // 错过多次也只执行一次，下一次执行时间从现在开始计算
+        let next = next_run_at(&schedule.rule, now).unwrap_or_else(|e| {
+            log::error!("[SCHEDULER] Schedule {} has an invalid rule: {}", schedule.id, e);
+            None
+        });
+        let run_at = format_time(&now_utc);
+        state
+            .db
+            .record_schedule_run(
+                schedule.id,
+                task_id.map(|task_id| (run_at.as_str(), task_id)),
+                next.as_deref(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// 复制计划所属的任务并加入测试队列，返回新任务ID
+async fn enqueue_scheduled_run(
+    state: &AppState,
+    schedule: &TaskSchedule,
+    now: DateTime<Local>,
+) -> anyhow::Result<i64> {
+    let task = state
+        .db
+        .get_task_by_id(schedule.task_id)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("任务 {} 不存在", schedule.task_id))?;
+    let name = format!("{} {}", task.name, now.format("%Y-%m-%d %H:%M"));
+    let task_id = state.db.duplicate_task(schedule.task_id, &name).await?;
+    let job_id = state
+        .db
+        .enqueue_job(task_id, &schedule.spec, schedule.priority)
+        .await?;
+    state.job_queue_notify.notify_one();
+    log::info!(
+        "[SCHEDULER] Schedule {} queued job {} for task {} '{}'",
+        schedule.id,
+        job_id,
+        task_id,
+        name
+    );
+    Ok(task_id)
+}
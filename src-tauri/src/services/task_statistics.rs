@@ -0,0 +1,259 @@
+use anyhow::Result;
+use chrono::Utc;
+use std::collections::HashMap;
+
+use crate::db::database::DatabaseService;
+use crate::models::{SampleStability, TaskStatistics};
+
+/// 一组耗时数据的分布
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub avg: Option<f64>,
+    pub p50: Option<f64>,
+    pub p90: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+impl LatencyStats {
+    /// 负值（时间戳采集异常）直接丢弃
+    pub fn from_samples(samples: impl IntoIterator<Item = i64>) -> Self {
+        let mut values: Vec<f64> = samples
+            .into_iter()
+            .filter(|v| *v >= 0)
+            .map(|v| v as f64)
+            .collect();
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_by(|a, b| a.total_cmp(b));
+
+        Self {
+            min: values.first().copied(),
+            max: values.last().copied(),
+            avg: Some(values.iter().sum::<f64>() / values.len() as f64),
+            p50: Some(percentile(&values, 50.0)),
+            p90: Some(percentile(&values, 90.0)),
+            p99: Some(percentile(&values, 99.0)),
+        }
+    }
+}
+
+/// 线性插值百分位数，`sorted` 必须已升序排列且非空
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100.0) * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// 均值和总体方差，负值（时间戳采集异常）直接丢弃
+fn mean_variance(values: &[f64]) -> Option<(f64, f64)> {
+    let values: Vec<f64> = values.iter().copied().filter(|v| *v >= 0.0).collect();
+    if values.is_empty() {
+        return None;
+    }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    Some((mean, variance))
+}
+
+fn ratio(numerator: i64, denominator: i64) -> Option<f64> {
+    (denominator > 0).then(|| numerator as f64 / denominator as f64)
+}
+
+/// 根据数据库中已保存的样本结果重新计算任务统计并持久化。
+/// 在工作流结束时自动调用，也可以通过 `refresh_task_statistics` 命令手动触发。
+pub async fn refresh_task_statistics(db: &DatabaseService, task_id: i64) -> Result<TaskStatistics> {
+    let samples = db.get_samples_by_task_id(task_id).await?;
+    let wake_word_count = db
+        .get_task_by_id(task_id)
+        .await?
+        .map(|task| task.wake_word_ids.len())
+        .unwrap_or(0);
+    let strategy = db.get_task_wake_word_strategy(task_id).await?;
+    // 多唤醒词策略和重复执行下同一样本会运行多次，统计按运行计数
+    let results = db.list_analysis_results_by_task(task_id).await?;
+    let timings = db.list_timing_data_by_task(task_id).await?;
+    let wake_results = db.get_wake_detection_results_by_task(task_id).await?;
+
+    let completed_samples = results.len() as i64;
+    let evaluation_errors = results
+        .iter()
+        .map(|(_, r)| r)
+        .filter(|r| r.result_status.as_deref() == Some("evaluation_error"))
+        .count() as i64;
+    let passed_samples = results.iter().filter(|(_, r)| r.assessment.valid).count() as i64;
+    let scored: Vec<f64> = results
+        .iter()
+        .map(|(_, r)| r)
+        .filter(|r| r.result_status.as_deref() != Some("evaluation_error"))
+        .map(|r| r.assessment.overall_score)
+        .collect();
+    let recognized = results
+        .iter()
+        .map(|(_, r)| r)
+        .filter(|r| {
+            r.recognized_text
+                .as_deref()
+                .is_some_and(|text| !text.trim().is_empty())
+        })
+        .count() as i64;
+
+    let recognition = LatencyStats::from_samples(
+        timings.iter().filter_map(|(_, t)| t.voice_recognition_time_ms),
+    );
+    let response = LatencyStats::from_samples(
+        timings.iter().filter_map(|(_, t)| t.interaction_response_time_ms),
+    );
+
+    let wake_attempts = wake_results.len() as i64;
+    let wake_successes = wake_results.iter().filter(|r| r.success).count() as i64;
+
+    let stats = TaskStatistics {
+        task_id,
+        total_samples: strategy.planned_runs(&samples, wake_word_count) as i64,
+        completed_samples,
+        passed_samples,
+        evaluation_errors,
+        pass_rate: ratio(passed_samples, completed_samples - evaluation_errors),
+        average_score: (!scored.is_empty())
+            .then(|| scored.iter().sum::<f64>() / scored.len() as f64),
+        recognition_success_rate: ratio(recognized, completed_samples),
+        wake_attempts,
+        wake_successes,
+        wake_rate: ratio(wake_successes, wake_attempts),
+        recognition_min_ms: recognition.min,
+        recognition_max_ms: recognition.max,
+        recognition_avg_ms: recognition.avg,
+        recognition_p50_ms: recognition.p50,
+        recognition_p90_ms: recognition.p90,
+        recognition_p99_ms: recognition.p99,
+        response_avg_ms: response.avg,
+        response_p50_ms: response.p50,
+        response_p90_ms: response.p90,
+        response_p99_ms: response.p99,
+        updated_at: Utc::now().to_rfc3339(),
+    };
+
+    db.save_task_statistics(&stats).await?;
+    log::info!(
+        "[TASK_STATISTICS] Task {} statistics refreshed: {}/{} completed, pass_rate={:?}, wake_rate={:?}",
+        task_id,
+        stats.completed_samples,
+        stats.total_samples,
+        stats.pass_rate,
+        stats.wake_rate
+    );
+    Ok(stats)
+}
+
+/// 按样本汇总多次运行（重复执行、多个唤醒词）的稳定性：通过比例、得分和耗时的波动。
+/// 只包含至少执行过一次的样本，顺序与任务样本列表一致。
+pub async fn compute_sample_stability(db: &DatabaseService, task_id: i64) -> Result<Vec<SampleStability>> {
+    let samples = db.get_samples_by_task_id(task_id).await?;
+    let results = db.list_analysis_results_by_task(task_id).await?;
+    let timings: HashMap<_, _> = db.list_timing_data_by_task(task_id).await?.into_iter().collect();
+
+    let mut stability = Vec::new();
+    for sample in &samples {
+        let runs: Vec<_> = results
+            .iter()
+            .filter(|(key, _)| key.sample_id == sample.id as i64)
+            .collect();
+        if runs.is_empty() {
+            continue;
+        }
+
+        let evaluation_errors = runs
+            .iter()
+            .filter(|(_, r)| r.result_status.as_deref() == Some("evaluation_error"))
+            .count() as i64;
+        let passed_runs = runs.iter().filter(|(_, r)| r.assessment.valid).count() as i64;
+        let scores: Vec<f64> = runs
+            .iter()
+            .filter(|(_, r)| r.result_status.as_deref() != Some("evaluation_error"))
+            .map(|(_, r)| r.assessment.overall_score)
+            .collect();
+        let run_timings: Vec<_> = runs.iter().filter_map(|(key, _)| timings.get(key)).collect();
+        let recognition: Vec<f64> = run_timings
+            .iter()
+            .filter_map(|t| t.voice_recognition_time_ms)
+            .map(|v| v as f64)
+            .collect();
+        let response: Vec<f64> = run_timings
+            .iter()
+            .filter_map(|t| t.interaction_response_time_ms)
+            .map(|v| v as f64)
+            .collect();
+
+        let score = mean_variance(&scores);
+        let recognition = mean_variance(&recognition);
+        let response = mean_variance(&response);
+        stability.push(SampleStability {
+            sample_id: sample.id as i64,
+            sample_text: sample.text.clone(),
+            runs: runs.len() as i64,
+            passed_runs,
+            evaluation_errors,
+            pass_ratio: ratio(passed_runs, runs.len() as i64 - evaluation_errors),
+            score_avg: score.map(|(mean, _)| mean),
+            score_std_dev: score.map(|(_, variance)| variance.sqrt()),
+            recognition_avg_ms: recognition.map(|(mean, _)| mean),
+            recognition_variance: recognition.map(|(_, variance)| variance),
+            recognition_std_dev_ms: recognition.map(|(_, variance)| variance.sqrt()),
+            response_avg_ms: response.map(|(mean, _)| mean),
+            response_variance: response.map(|(_, variance)| variance),
+            response_std_dev_ms: response.map(|(_, variance)| variance.sqrt()),
+        });
+    }
+    Ok(stability)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("缺少统计值");
+        assert!((actual - expected).abs() < 1e-9, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_percentile_interpolation() {
+        assert_close(Some(percentile(&[1.0, 2.0], 50.0)), 1.5);
+        assert_close(Some(percentile(&[1.0, 2.0, 3.0], 0.0)), 1.0);
+        assert_close(Some(percentile(&[1.0, 2.0, 3.0], 100.0)), 3.0);
+    }
+
+    #[test]
+    fn test_latency_stats() {
+        // 排序后为 100 200 300 400 1000，-5 丢弃；第 p 百分位的位置为 p% * 4
+        let stats = LatencyStats::from_samples([400, 100, -5, 300, 1000, 200]);
+        assert_close(stats.min, 100.0);
+        assert_close(stats.max, 1000.0);
+        assert_close(stats.avg, 400.0);
+        // 位置 2
+        assert_close(stats.p50, 300.0);
+        // 位置 3.6：400 + (1000 - 400) * 0.6
+        assert_close(stats.p90, 760.0);
+        // 位置 3.96：400 + (1000 - 400) * 0.96
+        assert_close(stats.p99, 976.0);
+    }
+
+    #[test]
+    fn test_latency_stats_small_inputs() {
+        let single = LatencyStats::from_samples([250]);
+        for value in [single.min, single.max, single.avg, single.p50, single.p90, single.p99] {
+            assert_close(value, 250.0);
+        }
+
+        for samples in [vec![], vec![-1, -20]] {
+            let empty = LatencyStats::from_samples(samples);
+            assert!(empty.min.is_none() && empty.max.is_none() && empty.avg.is_none());
+            assert!(empty.p50.is_none() && empty.p90.is_none() && empty.p99.is_none());
+        }
+    }
+}
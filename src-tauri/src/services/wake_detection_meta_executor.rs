@@ -1,16 +1,19 @@
 use async_trait::async_trait;
 use std::error::Error;
 use std::sync::Arc;
-use tauri::Emitter;
 use tokio::sync::watch;
 
-use crate::models::TaskProgress;
+use crate::services::event_sink::Events;
+use crate::models::{SampleRunKey, TaskProgress};
 use crate::services::active_task::ActiveTask;
 use crate::services::active_task::VisualWakeConfig;
 use crate::services::asr_task::AsrTask;
 use crate::services::audio_task::audio_task;
 use crate::services::finish_task::finish_task;
+use crate::services::meta_task_executor::{configured_limits, save_workflow_trace};
+use crate::services::task_statistics::refresh_task_statistics;
 use crate::services::workflow::ControlSignal;
+use crate::services::workflow::ExecutionLimits;
 use crate::services::workflow::Task;
 use crate::services::workflow::Workflow;
 use crate::services::workflow::WorkflowContext;
@@ -40,6 +43,7 @@ pub struct wake_detection_meta_executor {
     visual_config: VisualWakeConfig,
     state_snapshot: Arc<AppState>,
     expected_responses: Vec<String>, // 用户输入的预期回复
+    limits: ExecutionLimits, // 各唤醒词子工作流共享的并发和资源限制
 }
 
 impl wake_detection_meta_executor {
@@ -56,6 +60,7 @@ impl wake_detection_meta_executor {
             visual_config,
             state_snapshot: state,
             expected_responses,
+            limits: configured_limits(),
         }
     }
 
@@ -104,7 +109,7 @@ impl Task for wake_detection_meta_executor {
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         _context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         println!(
             "[WakeDetectionMetaTask '{}'] Starting wake detection tests for task {}",
@@ -137,7 +142,7 @@ impl Task for wake_detection_meta_executor {
             return Err("任务没有关联的唤醒词".into());
         }
 
-        app_handle
+        events
             .emit(
                 "wake_detection_meta_update",
                 format!("开始执行 {} 个唤醒词的检测测试", wakewords.len()),
@@ -157,7 +162,7 @@ impl Task for wake_detection_meta_executor {
                 wakeword.text
             );
 
-            app_handle
+            events
                 .emit(
                     "wake_detection_meta_update",
                     format!(
@@ -180,14 +185,14 @@ impl Task for wake_detection_meta_executor {
                     }
                     if signal == ControlSignal::Paused {
                         println!("[WakeDetectionMetaTask] Paused. Waiting to resume...");
-                        app_handle.emit("wake_detection_meta_update", "任务已暂停...").ok();
+                        events.emit("wake_detection_meta_update", "任务已暂停...").ok();
                         while *control_rx.borrow() == ControlSignal::Paused {
                             if control_rx.changed().await.is_err() {
                                 return Err("Control channel closed while paused".into());
                             }
                         }
                         println!("[WakeDetectionMetaTask] Resumed.");
-                        app_handle.emit("wake_detection_meta_update", "任务已恢复。").ok();
+                        events.emit("wake_detection_meta_update", "任务已恢复。").ok();
                     }
                 }
                 _ = tokio::time::sleep(std::time::Duration::from_millis(1)) => {}
@@ -195,6 +200,7 @@ impl Task for wake_detection_meta_executor {
 
             // 为当前唤醒词创建子工作流
             let (mut sub_workflow, _) = Workflow::new();
+            sub_workflow.set_execution_limits(self.limits.clone());
             let test_start_time = chrono::Utc::now().timestamp_millis();
 
             // 创建任务ID
@@ -204,23 +210,29 @@ impl Task for wake_detection_meta_executor {
             let finish_task_id = format!("finish_task_{}_{}", wakeword.id, wake_word_index);
 
             // 添加任务，使用唤醒词的音频文件路径
-            sub_workflow.add_task(audio_task {
-                id: wake_task_id.clone(),
-                keyword: wakeword.text.clone(),
-                url: wakeword.audio_file.clone(), // 使用数据库中的音频文件路径
-            });
+            sub_workflow.add_task_with_policy(
+                audio_task {
+                    id: wake_task_id.clone(),
+                    keyword: wakeword.text.clone(),
+                    url: wakeword.audio_file.clone(), // 使用数据库中的音频文件路径
+                },
+                audio_task::policy(),
+            );
 
-            sub_workflow.add_task(ActiveTask::new(
-                active_task_id.clone(),
-                self.visual_config.clone(),
-            ));
+            let active_task = ActiveTask::new(active_task_id.clone(), self.visual_config.clone());
+            let active_policy = active_task.policy();
+            sub_workflow.add_task_with_policy(active_task, active_policy);
 
-            sub_workflow.add_task(AsrTask::new(asr_task_id.clone(), wakeword.text.clone()));
+            sub_workflow.add_task_with_policy(
+                AsrTask::new(asr_task_id.clone(), wakeword.text.clone()),
+                AsrTask::policy(),
+            );
 
             sub_workflow.add_task(finish_task::new_for_wake_detection(
                 finish_task_id.clone(),
                 self.task_id,
                 active_task_id.clone(),
+                asr_task_id.clone(),
                 wakeword.id,
                 self.state_snapshot.db.clone(),
             ));
@@ -231,10 +243,29 @@ impl Task for wake_detection_meta_executor {
             sub_workflow.add_dependency(&finish_task_id, &active_task_id);
 
             // 执行子工作流
+            let trace = sub_workflow.trace();
+            let started_at = chrono::Utc::now();
             let result = sub_workflow
-                .run_and_wait(app_handle.clone(), control_rx.clone())
+                .run_and_wait(events.clone(), control_rx.clone())
                 .await;
 
+            // 唤醒检测没有样本，sample_id 记为 0，run_index 为唤醒词在本次测试中的序号
+            let (trace_status, trace_error) = match &result {
+                _ if *control_rx.borrow() == ControlSignal::Stopped => ("cancelled", None),
+                Ok(_) => ("completed", None),
+                Err(e) => ("failed", Some(e.as_str())),
+            };
+            save_workflow_trace(
+                &self.state_snapshot.db,
+                SampleRunKey::new(self.task_id, 0, wakeword.id, wake_word_index as u32),
+                "wake_detection",
+                started_at,
+                trace_status,
+                trace_error,
+                &trace,
+            )
+            .await;
+
             let test_end_time = chrono::Utc::now().timestamp_millis();
             let duration_ms = (test_end_time - test_start_time) as u64;
 
@@ -254,38 +285,32 @@ impl Task for wake_detection_meta_executor {
                 let context_guard = context.read().await;
                 
                 // 检查 Active 任务结果
-                if let Some(active_task_result_any) = context_guard.get(&active_task_id) {
-                    if let Some(active_task_result) =
-                        active_task_result_any.downcast_ref::<serde_json::Value>()
+                if let Some(active_task_result) = ActiveTask::output_key(&active_task_id).get(&context_guard) {
+                    if let Some(status) =
+                        active_task_result.get("status").and_then(|s| s.as_str())
                     {
-                        if let Some(status) =
-                            active_task_result.get("status").and_then(|s| s.as_str())
-                        {
-                            if status == "completed" {
-                                active_task_is_completed = true;
-                                wake_duration = active_task_result.get("duration_ms").and_then(|d| d.as_u64());
-                            } else if status == "timeout" {
-                                active_task_is_completed = false; // 明确设置为false
-                                wake_duration = active_task_result.get("duration_ms").and_then(|d| d.as_u64());
-                                println!("[WakeDetectionMetaTask] Active task timed out for wake word '{}'.", wakeword.text);
-                            }
+                        if status == "completed" {
+                            active_task_is_completed = true;
+                            wake_duration = active_task_result.get("duration_ms").and_then(|d| d.as_u64());
+                        } else if status == "timeout" {
+                            active_task_is_completed = false; // 明确设置为false
+                            wake_duration = active_task_result.get("duration_ms").and_then(|d| d.as_u64());
+                            println!("[WakeDetectionMetaTask] Active task timed out for wake word '{}'.", wakeword.text);
                         }
                     }
                 }
                 
                 // 检查 ASR 任务结果
-                if let Some(asr_task_result_any) = context_guard.get(&asr_task_id) {
-                    if let Some(asr_task_output) = asr_task_result_any.downcast_ref::<crate::services::asr_task::AsrTaskOutput>() {
-                        // 检查ASR结果是否为空字符串或只包含空白字符
-                        let response = asr_task_output.response.trim();
-                        if !response.is_empty() {
-                            asr_result = Some(asr_task_output.response.clone());
-                            asr_duration = Some(asr_task_output.duration_ms);
-                            println!("[WakeDetectionMetaTask] ASR task completed successfully for wake word '{}' with response: '{}', duration: {}ms", wakeword.text, asr_task_output.response, asr_task_output.duration_ms);
-                        } else {
-                            asr_duration = Some(asr_task_output.duration_ms);
-                            println!("[WakeDetectionMetaTask] ASR task completed but response is empty for wake word '{}', duration: {}ms", wakeword.text, asr_task_output.duration_ms);
-                        }
+                if let Some(asr_task_output) = AsrTask::output_key(&asr_task_id).get(&context_guard) {
+                    // 检查ASR结果是否为空字符串或只包含空白字符
+                    let response = asr_task_output.response.trim();
+                    if !response.is_empty() {
+                        asr_result = Some(asr_task_output.response.clone());
+                        asr_duration = Some(asr_task_output.duration_ms);
+                        println!("[WakeDetectionMetaTask] ASR task completed successfully for wake word '{}' with response: '{}', duration: {}ms", wakeword.text, asr_task_output.response, asr_task_output.duration_ms);
+                    } else {
+                        asr_duration = Some(asr_task_output.duration_ms);
+                        println!("[WakeDetectionMetaTask] ASR task completed but response is empty for wake word '{}', duration: {}ms", wakeword.text, asr_task_output.duration_ms);
                     }
                 }
                 
@@ -365,10 +390,12 @@ impl Task for wake_detection_meta_executor {
 
             // 发送进度更新
             let progress_value = (wake_word_index + 1) as f32 / wakewords.len() as f32 * 100.0;
-            app_handle
+            let passed = all_results.iter().filter(|r| r.success).count() as u32;
+            events
                 .emit(
                     "wake_detection_progress",
                     TaskProgress {
+                        task_id: self.task_id,
                         value: progress_value,
                         current_sample: (wake_word_index + 1) as u32,
                         current_stage: Some(format!(
@@ -378,12 +405,15 @@ impl Task for wake_detection_meta_executor {
                             wakeword.text
                         )),
                         total: wakewords.len() as u32,
+                        passed,
+                        failed: all_results.len() as u32 - passed,
+                        ..Default::default()
                     },
                 )
                 .ok();
 
             // 发送测试结果
-            app_handle
+            events
                 .emit("wake_detection_test_result", test_result)
                 .ok();
 
@@ -398,13 +428,13 @@ impl Task for wake_detection_meta_executor {
             } else {
                 let error_message = format!("唤醒词 '{}' 测试失败. 终止所有测试。", wakeword.text);
                 eprintln!("[WakeDetectionMetaTask] {}", error_message);
-                app_handle
+                events
                     .emit("wake_detection_meta_error", &error_message)
                     .ok();
                 return Err(error_message.into());
             }
 
-            app_handle
+            events
                 .emit(
                     "wake_detection_meta_update",
                     format!("唤醒词 '{}' 测试完成", wakeword.text),
@@ -435,7 +465,7 @@ impl Task for wake_detection_meta_executor {
             "results": all_results
         });
 
-        app_handle
+        events
             .emit("wake_detection_final_stats", final_stats)
             .ok();
 
@@ -444,7 +474,7 @@ impl Task for wake_detection_meta_executor {
             self.id, total_tests, success_count, total_tests
         );
 
-        app_handle
+        events
             .emit(
                 "wake_detection_meta_update",
                 format!(
@@ -454,6 +484,10 @@ impl Task for wake_detection_meta_executor {
             )
             .ok();
 
+        if let Err(e) = refresh_task_statistics(&self.state_snapshot.db, self.task_id).await {
+            log::error!("[WakeDetectionMetaTask '{}'] Failed to refresh task statistics: {}", self.id, e);
+        }
+
         Ok(())
     }
 }
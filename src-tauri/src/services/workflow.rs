@@ -1,17 +1,131 @@
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
 use futures::stream::{FuturesUnordered, StreamExt};
-use std::any::Any;
-use std::collections::{HashMap, VecDeque};
+use serde::{Deserialize, Serialize};
+use std::any::{Any, TypeId};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
-use std::sync::Arc;
-use tauri::AppHandle;
-use tauri::Emitter;
-use tokio::sync::{watch, RwLock};
-use tokio::task::JoinHandle;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{watch, Notify, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::{AbortHandle, JoinError};
+use tokio::time::Instant;
 
 use crate::services::audio_controller::AudioController;
+use crate::services::event_sink::Events;
 
-pub type WorkflowContext = Arc<RwLock<HashMap<String, Box<dyn Any + Send + Sync>>>>;
+pub type ContextMap = HashMap<String, Box<dyn Any + Send + Sync>>;
+pub type WorkflowContext = Arc<RwLock<ContextMap>>;
+
+// ===================================================================
+// 0. 类型化上下文键 (Typed Context Keys)
+// ===================================================================
+
+/// 带值类型的上下文键。生产者用它写入、消费者用它读取，
+/// 任务通过 `Task::outputs` / `Task::inputs` 声明，构建工作流时即可校验键名和类型。
+pub struct ContextKey<T> {
+    name: String,
+    _type: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for ContextKey<T> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            _type: PhantomData,
+        }
+    }
+}
+
+impl<T> fmt::Debug for ContextKey<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "ContextKey<{}>({})", std::any::type_name::<T>(), self.name)
+    }
+}
+
+impl<T: Any + Send + Sync> ContextKey<T> {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            _type: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get<'a>(&self, context: &'a ContextMap) -> Option<&'a T> {
+        context.get(&self.name).and_then(|value| value.downcast_ref::<T>())
+    }
+
+    /// 读取必须存在的值，缺失时返回带键名的错误
+    pub fn require<'a>(&self, context: &'a ContextMap) -> Result<&'a T, String> {
+        self.get(context)
+            .ok_or_else(|| format!("在context中找不到依赖项 '{}'", self.name))
+    }
+
+    pub fn insert(&self, context: &mut ContextMap, value: T) {
+        context.insert(self.name.clone(), Box::new(value));
+    }
+}
+
+impl<T: Any + Send + Sync + fmt::Debug> ContextKey<T> {
+    /// 用于 `Task::inputs` / `Task::outputs` 的声明
+    pub fn decl(&self) -> KeyDecl {
+        KeyDecl {
+            name: self.name.clone(),
+            type_id: TypeId::of::<T>(),
+            type_name: std::any::type_name::<T>(),
+            describe: describe_value::<T>,
+        }
+    }
+}
+
+/// 执行记录中上下文值摘要的最大长度，过长的值被截断
+const OUTPUT_SUMMARY_MAX_CHARS: usize = 200;
+
+fn describe_value<T: Any + fmt::Debug>(value: &(dyn Any + Send + Sync)) -> Option<String> {
+    let text = format!("{:?}", value.downcast_ref::<T>()?);
+    if text.chars().count() <= OUTPUT_SUMMARY_MAX_CHARS {
+        return Some(text);
+    }
+    let truncated: String = text.chars().take(OUTPUT_SUMMARY_MAX_CHARS).collect();
+    Some(format!("{}...", truncated))
+}
+
+/// 任务声明的上下文键（键名 + 值类型）
+#[derive(Debug, Clone)]
+pub struct KeyDecl {
+    pub name: String,
+    pub type_id: TypeId,
+    pub type_name: &'static str,
+    /// 生成执行记录中的值摘要
+    describe: fn(&(dyn Any + Send + Sync)) -> Option<String>,
+}
+
+impl PartialEq for KeyDecl {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.type_id == other.type_id
+    }
+}
+
+impl Eq for KeyDecl {}
+
+impl KeyDecl {
+    /// 上下文中该键当前值的摘要，键不存在时返回 None
+    pub fn summarize(&self, context: &ContextMap) -> Option<OutputSummary> {
+        let value = context.get(&self.name)?;
+        Some(OutputSummary {
+            key: self.name.clone(),
+            type_name: self.type_name.to_string(),
+            value: (self.describe)(value.as_ref())?,
+        })
+    }
+}
 
 // ===================================================================
 // 1. 控制信号与句柄 (Control Signals & Handle)
@@ -30,6 +144,22 @@ pub struct ControlHandle {
 }
 
 impl ControlHandle {
+    /// 创建控制句柄和对应的接收端，接收端交给 `Workflow::run_and_wait`
+    pub fn channel() -> (Self, watch::Receiver<ControlSignal>) {
+        let (tx, rx) = watch::channel(ControlSignal::Running);
+        (Self { tx }, rx)
+    }
+
+    /// 工作流是否仍在运行。工作流结束后所有接收端都被释放
+    pub fn is_running(&self) -> bool {
+        !self.tx.is_closed()
+    }
+
+    /// 是否已发送过停止信号
+    pub fn is_stopped(&self) -> bool {
+        *self.tx.borrow() == ControlSignal::Stopped
+    }
+
     pub fn pause(&self) {
         self.tx.send(ControlSignal::Paused).ok();
     }
@@ -55,8 +185,461 @@ pub trait Task: Send + Sync {
         &mut self,
         control_rx: &mut watch::Receiver<ControlSignal>,
         context: WorkflowContext,
-        app_handle: tauri::AppHandle,
+        events: Events,
     ) -> Result<(), Box<dyn Error + Send + Sync>>;
+
+    /// 任务写入上下文的键
+    fn outputs(&self) -> Vec<KeyDecl> {
+        Vec::new()
+    }
+
+    /// 任务读取的上下文键，必须由某个上游任务声明为输出
+    fn inputs(&self) -> Vec<KeyDecl> {
+        Vec::new()
+    }
+
+    /// 任务成功执行后的结论，供下游的条件依赖判断。默认为 `Completed`。
+    fn outcome(&self) -> TaskOutcome {
+        TaskOutcome::Completed
+    }
+
+    /// 任务执行期间独占的资源（见 `resources` 模块），使用同一 `ExecutionLimits` 的任务中
+    /// 同一时间只有一个能持有某个资源
+    fn resources(&self) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// 重试前调用，清理上一次执行留下的状态。超时的执行被直接取消，来不及自行清理
+    fn reset(&mut self) {}
+}
+
+/// 任务结束后的结果（执行出错的任务会直接终止工作流，不在此列）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskOutcome {
+    /// 正常完成
+    Completed,
+    /// 正常执行完毕但结论为否（如唤醒失败、检测超时），附带原因
+    Declined(String),
+    /// 上游条件不满足，任务未执行
+    Skipped,
+}
+
+/// 依赖边的条件：上游结束后根据其结果决定下游是否执行。
+/// 任一依赖边的条件不满足时下游任务被跳过，跳过结果继续沿默认依赖向下传递。
+#[derive(Clone)]
+pub enum Condition {
+    /// 上游实际执行过（Completed 或 Declined）时运行，上游被跳过时一并跳过。`add_dependency` 的默认条件
+    Executed,
+    /// 上游无论结果如何都运行，用于必须执行的收尾任务
+    Always,
+    /// 仅在上游正常完成（未被否决或跳过）时运行
+    Completed,
+    /// 由谓词判断上游结果
+    When(Arc<dyn Fn(&TaskOutcome) -> bool + Send + Sync>),
+}
+
+impl Condition {
+    pub fn completed() -> Self {
+        Condition::Completed
+    }
+
+    fn is_met(&self, outcome: &TaskOutcome) -> bool {
+        match self {
+            Condition::Executed => *outcome != TaskOutcome::Skipped,
+            Condition::Always => true,
+            Condition::Completed => *outcome == TaskOutcome::Completed,
+            Condition::When(predicate) => predicate(outcome),
+        }
+    }
+
+    /// 导出图时的边标签，默认条件不标注
+    fn label(&self) -> Option<&'static str> {
+        match self {
+            Condition::Executed => None,
+            Condition::Always => Some("always"),
+            Condition::Completed => Some("completed"),
+            Condition::When(_) => Some("when"),
+        }
+    }
+}
+
+impl fmt::Debug for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Executed => write!(f, "Executed"),
+            Condition::Always => write!(f, "Always"),
+            Condition::Completed => write!(f, "Completed"),
+            Condition::When(_) => write!(f, "When(<predicate>)"),
+        }
+    }
+}
+
+// ===================================================================
+// 2.1 任务策略 (Task Policy: timeout / retry)
+// ===================================================================
+
+/// 单次执行失败的原因
+#[derive(Debug, Clone)]
+pub enum TaskFailure {
+    /// 超过策略规定的执行时间，任务已被取消
+    Timeout(Duration),
+    /// 任务自身返回的错误
+    Error(String),
+}
+
+impl fmt::Display for TaskFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TaskFailure::Timeout(limit) => write!(f, "timed out after {}ms", limit.as_millis()),
+            TaskFailure::Error(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+pub type RetryPredicate = Arc<dyn Fn(&TaskFailure) -> bool + Send + Sync>;
+
+/// 任务执行策略，由 WorkflowRunner 统一执行。
+/// 默认策略与之前的行为一致：不超时、不重试。
+#[derive(Clone)]
+pub struct TaskPolicy {
+    /// 单次执行的最长时间，暂停期间不计时
+    pub timeout: Option<Duration>,
+    /// 失败后的最大重试次数（不含第一次执行）
+    pub max_retries: u32,
+    /// 第一次重试前的等待时间，之后每次翻倍
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// 判断某次失败是否值得重试，未设置时所有失败都重试
+    pub retry_on: Option<RetryPredicate>,
+}
+
+impl Default for TaskPolicy {
+    fn default() -> Self {
+        Self {
+            timeout: None,
+            max_retries: 0,
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            retry_on: None,
+        }
+    }
+}
+
+impl fmt::Debug for TaskPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TaskPolicy")
+            .field("timeout", &self.timeout)
+            .field("max_retries", &self.max_retries)
+            .field("backoff", &self.backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("retry_on", &self.retry_on.as_ref().map(|_| "<predicate>"))
+            .finish()
+    }
+}
+
+impl TaskPolicy {
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_retries(mut self, max_retries: u32, backoff: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn with_retry_on(
+        mut self,
+        predicate: impl Fn(&TaskFailure) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.retry_on = Some(Arc::new(predicate));
+        self
+    }
+
+    fn should_retry(&self, failure: &TaskFailure) -> bool {
+        self.retry_on.as_ref().map_or(true, |predicate| predicate(failure))
+    }
+
+    /// 第 `attempt` 次执行失败后的等待时间（attempt 从1开始）
+    fn backoff_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.backoff.saturating_mul(factor).min(self.max_backoff)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskEventKind {
+    Timeout,
+    Retry,
+    Failed,
+}
+
+/// 通过 "workflow_task_event" 发送给前端的超时/重试/失败事件
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowTaskEvent {
+    pub task_id: String,
+    pub kind: TaskEventKind,
+    /// 本次是第几次执行（从1开始）
+    pub attempt: u32,
+    pub max_retries: u32,
+    pub error: String,
+    pub timeout_ms: Option<u64>,
+    pub retry_delay_ms: Option<u64>,
+}
+
+/// 工作流因任务失败而终止时，通过 "workflow_cancelled" 发送的取消报告
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowCancelledEvent {
+    pub failed_task: String,
+    pub error: String,
+    /// 收到停止信号后在宽限期内自行退出的任务
+    pub stopped: Vec<String>,
+    /// 宽限期结束仍未退出、被强制中止的任务
+    pub aborted: Vec<String>,
+}
+
+// ===================================================================
+// 2.2 并发限制与独占资源 (Execution Limits)
+// ===================================================================
+
+/// 任务可以声明的独占资源
+pub mod resources {
+    /// 播放音频
+    pub const SPEAKER: &str = "speaker";
+    /// 录音识别。识别期间的其他播放会被一起录入，识别任务同时占用 `SPEAKER`
+    pub const MICROPHONE: &str = "microphone";
+    /// 全局视觉唤醒检测器
+    pub const VISUAL_DETECTOR: &str = "visual_detector";
+    /// OCR 会话和引擎池
+    pub const OCR: &str = "ocr";
+}
+
+/// 工作流执行任务时的限制：同时执行的任务数和独占资源。
+/// 克隆得到的是同一份限制，多个工作流（如相邻样本的子工作流）共享时，限制对它们整体生效。
+/// 不要与嵌套在其中执行的工作流共享，外层任务占着名额会导致内层任务永远等待。
+#[derive(Clone, Default)]
+pub struct ExecutionLimits {
+    /// 为空表示不限制同时执行的任务数
+    slots: Option<Arc<Semaphore>>,
+    resources: Arc<Mutex<HashMap<String, Arc<Semaphore>>>>,
+}
+
+impl fmt::Debug for ExecutionLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExecutionLimits")
+            .field("available_slots", &self.slots.as_ref().map(|s| s.available_permits()))
+            .finish()
+    }
+}
+
+/// 任务执行期间持有的名额和资源，释放时唤醒等待的任务
+struct ExecutionPermit {
+    _resources: Vec<OwnedSemaphorePermit>,
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl ExecutionLimits {
+    /// 最多同时执行 `max_concurrent_tasks` 个任务，0 表示不限制
+    pub fn new(max_concurrent_tasks: usize) -> Self {
+        Self {
+            slots: (max_concurrent_tasks > 0).then(|| Arc::new(Semaphore::new(max_concurrent_tasks))),
+            resources: Arc::default(),
+        }
+    }
+
+    fn resource(&self, name: &str) -> Arc<Semaphore> {
+        self.resources
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| Arc::new(Semaphore::new(1)))
+            .clone()
+    }
+
+    /// 等待任务需要的资源和一个执行名额。
+    /// 所有任务都按资源名顺序加锁、最后取名额，持有名额的任务不会再等待，因此不会互相死锁。
+    async fn acquire(&self, resources: &[&'static str]) -> ExecutionPermit {
+        let mut names = resources.to_vec();
+        names.sort_unstable();
+        names.dedup();
+        let mut held = Vec::with_capacity(names.len());
+        for name in names {
+            // 信号量从不关闭，acquire 不会失败
+            if let Ok(permit) = self.resource(name).acquire_owned().await {
+                held.push(permit);
+            }
+        }
+        let slot = match &self.slots {
+            Some(slots) => slots.clone().acquire_owned().await.ok(),
+            None => None,
+        };
+        ExecutionPermit {
+            _resources: held,
+            _slot: slot,
+        }
+    }
+}
+
+// ===================================================================
+// 2.3 执行记录 (Execution Trace)
+// ===================================================================
+
+/// 任务在一次工作流运行中的最终状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TraceStatus {
+    Completed,
+    Declined,
+    Skipped,
+    Failed,
+    /// 因其它任务失败或外部停止信号而退出/被中止
+    Cancelled,
+}
+
+impl TraceStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TraceStatus::Completed => "completed",
+            TraceStatus::Declined => "declined",
+            TraceStatus::Skipped => "skipped",
+            TraceStatus::Failed => "failed",
+            TraceStatus::Cancelled => "cancelled",
+        }
+    }
+
+    pub fn parse(status: &str) -> Self {
+        match status {
+            "completed" => TraceStatus::Completed,
+            "declined" => TraceStatus::Declined,
+            "skipped" => TraceStatus::Skipped,
+            "cancelled" => TraceStatus::Cancelled,
+            _ => TraceStatus::Failed,
+        }
+    }
+}
+
+/// 任务写入上下文的一个值的摘要
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutputSummary {
+    pub key: String,
+    pub type_name: String,
+    pub value: String,
+}
+
+/// 单个任务的执行记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaskTrace {
+    pub task_id: String,
+    pub status: TraceStatus,
+    /// 被跳过或未开始就被取消的任务没有开始和结束时间
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    /// 执行次数（含重试）
+    pub attempts: u32,
+    /// 失败原因、否决原因或取消说明
+    pub message: Option<String>,
+    /// 成功结束时声明的输出在上下文中的值
+    pub outputs: Vec<OutputSummary>,
+}
+
+/// 一次工作流运行的执行记录。运行前通过 `Workflow::trace` 取得，运行中可查询正在执行的任务，
+/// 运行结束（包括失败）后读取各任务的记录
+#[derive(Debug, Clone, Default)]
+pub struct WorkflowTrace {
+    state: Arc<Mutex<TraceState>>,
+    /// 写入记录或工作流结束时通知等待者
+    changed: Arc<Notify>,
+}
+
+#[derive(Debug, Default)]
+struct TraceState {
+    tasks: Vec<TaskTrace>,
+    /// 工作流已结束，之后不会再有新记录
+    closed: bool,
+    /// 已开始、尚未记录最终状态的任务
+    started: Vec<StartedTask>,
+}
+
+#[derive(Debug)]
+struct StartedTask {
+    task_id: String,
+    started_at: DateTime<Utc>,
+    /// 任务结束、释放资源的时间，工作流处理结果时再写入记录
+    finished_at: Option<DateTime<Utc>>,
+}
+
+impl WorkflowTrace {
+    /// 按任务结束顺序排列的记录
+    pub fn tasks(&self) -> Vec<TaskTrace> {
+        self.state.lock().unwrap().tasks.clone()
+    }
+
+    /// 已开始执行但尚未结束的任务，按开始顺序排列。等待资源或执行名额的任务不在其中
+    pub fn running(&self) -> Vec<String> {
+        let state = self.state.lock().unwrap();
+        state
+            .started
+            .iter()
+            .filter(|task| task.finished_at.is_none())
+            .map(|task| task.task_id.clone())
+            .collect()
+    }
+
+    fn start(&self, task_id: &str) {
+        self.state.lock().unwrap().started.push(StartedTask {
+            task_id: task_id.to_string(),
+            started_at: Utc::now(),
+            finished_at: None,
+        });
+    }
+
+    fn finish(&self, task_id: &str) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(task) = state.started.iter_mut().find(|task| task.task_id == task_id) {
+            task.finished_at = Some(Utc::now());
+        }
+    }
+
+    /// 任务的开始和结束时间，尚未开始的任务返回 None，结束时间未知时取当前时间
+    fn span(&self, task_id: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let state = self.state.lock().unwrap();
+        state
+            .started
+            .iter()
+            .find(|task| task.task_id == task_id)
+            .map(|task| (task.started_at, task.finished_at.unwrap_or_else(Utc::now)))
+    }
+
+    /// 等待指定的任务都有了最终记录，工作流提前结束（如失败）时也会返回
+    pub async fn wait_for_tasks(&self, task_ids: &[String]) {
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            {
+                let state = self.state.lock().unwrap();
+                let recorded = |id: &String| state.tasks.iter().any(|task| task.task_id == *id);
+                if state.closed || task_ids.iter().all(recorded) {
+                    return;
+                }
+            }
+            changed.await;
+        }
+    }
+
+    fn record(&self, trace: TaskTrace) {
+        let mut state = self.state.lock().unwrap();
+        state.started.retain(|task| task.task_id != trace.task_id);
+        state.tasks.push(trace);
+        self.changed.notify_waiters();
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.changed.notify_waiters();
+    }
 }
 
 // ===================================================================
@@ -66,9 +649,16 @@ pub trait Task: Send + Sync {
 // 4. Workflow (工作流结构与执行器)
 // ===================================================================
 
+/// 任务失败后等待其余任务响应停止信号的默认时间
+const DEFAULT_CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
 pub struct Workflow {
     tasks: HashMap<String, Box<dyn Task>>,
-    dependencies: HashMap<String, Vec<String>>,
+    dependencies: HashMap<String, Vec<(String, Condition)>>,
+    policies: HashMap<String, TaskPolicy>,
+    cancel_grace_period: Duration,
+    limits: ExecutionLimits,
+    trace: WorkflowTrace,
     audio_controller: AudioController,
 }
 
@@ -79,6 +669,10 @@ impl Workflow {
             Workflow {
                 tasks: HashMap::new(),
                 dependencies: HashMap::new(),
+                policies: HashMap::new(),
+                cancel_grace_period: DEFAULT_CANCEL_GRACE_PERIOD,
+                limits: ExecutionLimits::default(),
+                trace: WorkflowTrace::default(),
                 audio_controller,
             },
             audio_handle,
@@ -86,26 +680,315 @@ impl Workflow {
     }
 
     pub fn add_task(&mut self, task: impl Task + 'static) {
+        self.add_task_with_policy(task, TaskPolicy::default());
+    }
+
+    /// 添加任务并指定超时/重试策略
+    pub fn add_task_with_policy(&mut self, task: impl Task + 'static, policy: TaskPolicy) {
         let id = task.id();
         self.tasks.insert(id.clone(), Box::new(task));
+        self.policies.insert(id.clone(), policy);
         self.dependencies.entry(id).or_insert(vec![]);
     }
 
+    /// 设置任务失败后其余任务的退出宽限期，超时未退出的任务会被中止
+    pub fn set_cancel_grace_period(&mut self, grace_period: Duration) {
+        self.cancel_grace_period = grace_period;
+    }
+
+    /// 设置同时执行的任务数和独占资源的限制，默认不限制
+    pub fn set_execution_limits(&mut self, limits: ExecutionLimits) {
+        self.limits = limits;
+    }
+
+    /// 本工作流的执行记录，与运行时写入的是同一份
+    pub fn trace(&self) -> WorkflowTrace {
+        self.trace.clone()
+    }
+
     pub fn add_dependency(&mut self, task_id: &str, depends_on_id: &str) {
+        self.add_conditional_dependency(task_id, depends_on_id, Condition::Executed);
+    }
+
+    /// 添加带条件的依赖：`depends_on_id` 结束后按其结果判断 `task_id` 是否执行
+    pub fn add_conditional_dependency(
+        &mut self,
+        task_id: &str,
+        depends_on_id: &str,
+        condition: Condition,
+    ) {
         self.dependencies
             .entry(task_id.to_string())
             .or_insert(vec![])
-            .push(depends_on_id.to_string());
+            .push((depends_on_id.to_string(), condition));
+    }
+
+    /// 运行前校验工作流：缺失的依赖、循环依赖、永远无法开始的任务，以及上下文键的输入输出。
+    /// 所有问题汇总为一条错误返回。
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+
+        // 1. 悬空依赖：依赖声明中引用了不存在的任务
+        for (task_id, deps) in self.sorted_dependencies() {
+            if !self.tasks.contains_key(task_id) {
+                errors.push(format!("依赖声明中的任务 '{}' 不存在", task_id));
+            }
+            for (dep_id, _) in deps {
+                if !self.tasks.contains_key(dep_id) {
+                    errors.push(format!("任务 '{}' 依赖的任务 '{}' 不存在", task_id, dep_id));
+                }
+            }
+        }
+
+        // 2. 按拓扑顺序模拟执行，剩下的任务要么在环上，要么被环或缺失的依赖阻塞
+        let mut in_degrees: HashMap<&String, usize> = self
+            .tasks
+            .keys()
+            .map(|id| (id, self.dependencies.get(id).map_or(0, |deps| deps.len())))
+            .collect();
+        let mut queue: Vec<&String> = in_degrees
+            .iter()
+            .filter(|(_, degree)| **degree == 0)
+            .map(|(id, _)| *id)
+            .collect();
+        while let Some(id) = queue.pop() {
+            in_degrees.remove(id);
+            for (dependent_id, deps) in &self.dependencies {
+                let edges = deps.iter().filter(|(dep_id, _)| dep_id == id).count();
+                if edges == 0 {
+                    continue;
+                }
+                if let Some(degree) = in_degrees.get_mut(dependent_id) {
+                    *degree -= edges;
+                    if *degree == 0 {
+                        queue.push(dependent_id);
+                    }
+                }
+            }
+        }
+        if !in_degrees.is_empty() {
+            let blocked: HashSet<&String> = in_degrees.keys().copied().collect();
+            let cycle = self.find_cycle(&blocked);
+            if let Some(cycle) = &cycle {
+                let path: Vec<&str> = cycle.iter().map(|id| id.as_str()).collect();
+                errors.push(format!("存在循环依赖: {} -> {}", path.join(" -> "), path[0]));
+            }
+            let mut unreachable: Vec<&String> = blocked
+                .into_iter()
+                .filter(|id| cycle.as_ref().map_or(true, |cycle| !cycle.contains(id)))
+                .collect();
+            unreachable.sort();
+            for id in unreachable {
+                errors.push(format!(
+                    "任务 '{}' 永远无法开始（上游存在循环依赖或缺失的任务）",
+                    id
+                ));
+            }
+        }
+
+        // 3. 上下文键：每个声明的输入都由上游任务产生且类型一致
+        if let Err(e) = self.check_context_keys() {
+            errors.push(e);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("工作流校验失败: {}", errors.join("; ")))
+        }
+    }
+
+    fn sorted_dependencies(&self) -> Vec<(&String, &Vec<(String, Condition)>)> {
+        let mut dependencies: Vec<_> = self.dependencies.iter().collect();
+        dependencies.sort_by(|a, b| a.0.cmp(b.0));
+        dependencies
+    }
+
+    /// 在被阻塞的任务中找出一个环，按执行方向返回环上的任务
+    fn find_cycle<'a>(&'a self, blocked: &HashSet<&'a String>) -> Option<Vec<&'a String>> {
+        let mut starts: Vec<&String> = blocked.iter().copied().collect();
+        starts.sort();
+        let mut finished = HashSet::new();
+        for start in starts {
+            let mut path = Vec::new();
+            if let Some(cycle) = self.visit_for_cycle(start, blocked, &mut path, &mut finished) {
+                return Some(cycle);
+            }
+        }
+        None
+    }
+
+    /// 沿依赖边深度优先搜索，遇到仍在当前路径上的任务即找到环
+    fn visit_for_cycle<'a>(
+        &'a self,
+        id: &'a String,
+        blocked: &HashSet<&'a String>,
+        path: &mut Vec<&'a String>,
+        finished: &mut HashSet<&'a String>,
+    ) -> Option<Vec<&'a String>> {
+        path.push(id);
+        for (dep_id, _) in self.dependencies.get(id).into_iter().flatten() {
+            if !blocked.contains(dep_id) || finished.contains(dep_id) {
+                continue;
+            }
+            if let Some(position) = path.iter().position(|p| *p == dep_id) {
+                // 路径沿依赖方向向上，反转后按执行方向展示
+                let mut cycle = path[position..].to_vec();
+                cycle.reverse();
+                return Some(cycle);
+            }
+            if let Some(cycle) = self.visit_for_cycle(dep_id, blocked, path, finished) {
+                return Some(cycle);
+            }
+        }
+        path.pop();
+        finished.insert(id);
+        None
+    }
+
+    /// 导出 Graphviz DOT 格式，边的方向为执行顺序（上游 -> 下游）
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph workflow {\n    rankdir=LR;\n    node [shape=box];\n");
+        let mut task_ids: Vec<&String> = self.tasks.keys().collect();
+        task_ids.sort();
+        for id in task_ids {
+            dot.push_str(&format!("    \"{}\";\n", id));
+        }
+        for (task_id, deps) in self.sorted_dependencies() {
+            for (dep_id, condition) in deps {
+                match condition.label() {
+                    Some(label) => dot.push_str(&format!(
+                        "    \"{}\" -> \"{}\" [label=\"{}\", style=dashed];\n",
+                        dep_id, task_id, label
+                    )),
+                    None => dot.push_str(&format!("    \"{}\" -> \"{}\";\n", dep_id, task_id)),
+                }
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// 导出 Mermaid flowchart，可直接嵌入 Markdown 文档
+    pub fn to_mermaid(&self) -> String {
+        let mut task_ids: Vec<&String> = self.tasks.keys().collect();
+        for (task_id, deps) in self.sorted_dependencies() {
+            task_ids.push(task_id);
+            task_ids.extend(deps.iter().map(|(id, _)| id));
+        }
+        task_ids.sort();
+        task_ids.dedup();
+        // Mermaid 节点ID不能包含任意字符，使用序号作为ID，任务ID作为显示文本
+        let node = |id: &String| format!("n{}", task_ids.binary_search(&id).unwrap());
+
+        let mut mermaid = String::from("flowchart LR\n");
+        for id in &task_ids {
+            mermaid.push_str(&format!("    {}[\"{}\"]\n", node(id), id));
+        }
+        for (task_id, deps) in self.sorted_dependencies() {
+            for (dep_id, condition) in deps {
+                match condition.label() {
+                    Some(label) => mermaid.push_str(&format!(
+                        "    {} -.->|{}| {}\n",
+                        node(dep_id),
+                        label,
+                        node(task_id)
+                    )),
+                    None => mermaid.push_str(&format!("    {} --> {}\n", node(dep_id), node(task_id))),
+                }
+            }
+        }
+        mermaid
+    }
+
+    /// 校验每个任务声明的输入都由其上游任务产生，且类型一致
+    fn check_context_keys(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        let mut task_ids: Vec<&String> = self.tasks.keys().collect();
+        task_ids.sort();
+        for task_id in task_ids {
+            let inputs = self.tasks[task_id].inputs();
+            if inputs.is_empty() {
+                continue;
+            }
+            let upstream = self.upstream_of(task_id);
+            for input in inputs {
+                let producers: Vec<(&String, KeyDecl)> = upstream
+                    .iter()
+                    .filter_map(|id| self.tasks.get(*id).map(|task| (*id, task.outputs())))
+                    .flat_map(|(id, outputs)| {
+                        outputs
+                            .into_iter()
+                            .filter(|output| output.name == input.name)
+                            .map(move |output| (id, output))
+                    })
+                    .collect();
+                if producers.is_empty() {
+                    errors.push(format!(
+                        "任务 '{}' 读取的上下文键 '{}' 没有上游任务产生",
+                        task_id, input.name
+                    ));
+                }
+                for (producer_id, output) in producers {
+                    if output.type_id != input.type_id {
+                        errors.push(format!(
+                            "上下文键 '{}' 类型不匹配: 任务 '{}' 写入 {}，任务 '{}' 读取 {}",
+                            input.name, producer_id, output.type_name, task_id, input.type_name
+                        ));
+                    }
+                }
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// 所有直接或间接依赖的任务
+    fn upstream_of(&self, task_id: &str) -> HashSet<&String> {
+        let mut visited = HashSet::new();
+        let mut stack: Vec<&String> = self
+            .dependencies
+            .get(task_id)
+            .map(|deps| deps.iter().map(|(id, _)| id).collect())
+            .unwrap_or_default();
+        while let Some(id) = stack.pop() {
+            if visited.insert(id) {
+                if let Some(deps) = self.dependencies.get(id.as_str()) {
+                    stack.extend(deps.iter().map(|(dep_id, _)| dep_id));
+                }
+            }
+        }
+        visited
     }
 
-    pub async fn run(self, app_handle: AppHandle) -> ControlHandle {
+    pub async fn run(self, events: Events) -> ControlHandle {
         let (control_tx, control_rx) = watch::channel(ControlSignal::Running);
         let handle = ControlHandle { tx: control_tx };
 
         tokio::spawn(async move {
-            let mut workflow_runner =
-                WorkflowRunner::new(self.tasks, self.dependencies, control_rx);
-            workflow_runner.execute(app_handle).await;
+            let trace = self.trace.clone();
+            if let Err(e) = self.validate() {
+                eprintln!("[Workflow] Invalid workflow: {}", e);
+                events
+                    .emit("workflow_event", format!("workflow invalid: {}", e))
+                    .ok();
+                trace.close();
+                return;
+            }
+            let mut workflow_runner = WorkflowRunner::new(
+                self.tasks,
+                self.dependencies,
+                self.policies,
+                self.cancel_grace_period,
+                self.limits,
+                self.trace,
+                control_rx,
+            );
+            workflow_runner.execute(events).await;
+            trace.close();
         });
 
         handle
@@ -115,34 +998,59 @@ impl Workflow {
     /// 这对于需要顺序执行多个工作流的场景至关重要。
     pub async fn run_and_wait(
         self,
-        app_handle: AppHandle,
+        events: Events,
         // 我们也需要把控制信号接收器传进来，以便子流程能被外部主流程控制
         mut control_rx: watch::Receiver<ControlSignal>,
     ) -> Result<WorkflowContext, String> {
         // 注意：这里我们不再创建新的 control channel，而是复用传入的
         // 我们也不再 tokio::spawn，而是直接 .await
-        let mut workflow_runner = WorkflowRunner::new(self.tasks, self.dependencies, control_rx);
+        let trace = self.trace.clone();
+        if let Err(e) = self.validate() {
+            trace.close();
+            return Err(e);
+        }
+        let mut workflow_runner = WorkflowRunner::new(
+            self.tasks,
+            self.dependencies,
+            self.policies,
+            self.cancel_grace_period,
+            self.limits,
+            self.trace,
+            control_rx,
+        );
 
         // 直接 await 执行结果
-        workflow_runner.execute(app_handle).await
+        let result = workflow_runner.execute(events).await;
+        trace.close();
+        result
     }
 }
 
 struct WorkflowRunner {
     tasks: HashMap<String, Box<dyn Task>>,
+    policies: HashMap<String, TaskPolicy>,
+    cancel_grace_period: Duration,
+    limits: ExecutionLimits,
+    trace: WorkflowTrace,
     control_rx: watch::Receiver<ControlSignal>,
-    reverse_deps: HashMap<String, Vec<String>>,
+    reverse_deps: HashMap<String, Vec<(String, Condition)>>,
     in_degrees: HashMap<String, usize>,
+    /// 至少有一条依赖条件不满足、入度归零后将被跳过的任务
+    blocked: HashSet<String>,
 }
 
 impl WorkflowRunner {
     fn new(
         tasks: HashMap<String, Box<dyn Task>>,
-        dependencies: HashMap<String, Vec<String>>,
+        dependencies: HashMap<String, Vec<(String, Condition)>>,
+        policies: HashMap<String, TaskPolicy>,
+        cancel_grace_period: Duration,
+        limits: ExecutionLimits,
+        trace: WorkflowTrace,
         control_rx: watch::Receiver<ControlSignal>,
     ) -> Self {
         let mut in_degrees = HashMap::new();
-        let mut reverse_deps: HashMap<String, Vec<String>> = HashMap::new();
+        let mut reverse_deps: HashMap<String, Vec<(String, Condition)>> = HashMap::new();
 
         for task_id in tasks.keys() {
             in_degrees.entry(task_id.clone()).or_insert(0);
@@ -151,29 +1059,39 @@ impl WorkflowRunner {
 
         for (task_id, deps) in &dependencies {
             in_degrees.insert(task_id.clone(), deps.len());
-            for dep_id in deps {
+            for (dep_id, condition) in deps {
                 reverse_deps
                     .entry(dep_id.clone())
                     .or_insert(vec![])
-                    .push(task_id.clone());
+                    .push((task_id.clone(), condition.clone()));
             }
         }
 
         WorkflowRunner {
             tasks,
+            policies,
+            cancel_grace_period,
+            limits,
+            trace,
             control_rx,
             reverse_deps,
             in_degrees,
+            blocked: HashSet::new(),
         }
     }
 
-    async fn execute(&mut self, app_handle: tauri::AppHandle) -> Result<WorkflowContext, String> {
+    async fn execute(&mut self, events: Events) -> Result<WorkflowContext, String> {
         // 创建上下文
         let context = Arc::new(RwLock::new(HashMap::new()));
-        let mut running_tasks: FuturesUnordered<JoinHandle<(String, Result<(), String>)>> =
-            FuturesUnordered::new();
+        let mut running_tasks: FuturesUnordered<RunningTask> = FuturesUnordered::new();
+        let mut abort_handles: HashMap<String, AbortHandle> = HashMap::new();
         let mut ready_queue: VecDeque<String> = VecDeque::new();
 
+        // 任务使用本工作流自己的控制通道：转发外部信号，任务失败时单独向其余任务发送停止信号
+        let (cancel_tx, task_rx) = watch::channel(*self.control_rx.borrow());
+        let mut external_rx = self.control_rx.clone();
+        let mut forwarding = true;
+
         // 找到所有初始入度为 0 的任务
         for (id, &degree) in &self.in_degrees {
             if degree == 0 {
@@ -184,27 +1102,77 @@ impl WorkflowRunner {
         // [修改] 如果一开始就没有可执行的任务，直接结束
         if ready_queue.is_empty() && self.tasks.is_empty() {
              println!("[Workflow] No tasks to run.");
-             app_handle.emit("workflow_event", "workflow finished (no tasks)").ok();
+             events.emit("workflow_event", "workflow finished (no tasks)").ok();
              return Ok(context);
         }
 
         loop {
             // 启动所有就绪的任务
             while let Some(task_id) = ready_queue.pop_front() {
+                if self.blocked.contains(&task_id) {
+                    // 依赖条件不满足，不执行任务，直接以 Skipped 结束并通知下游
+                    self.tasks.remove(&task_id);
+                    println!("[Workflow] Task '{}' skipped (dependency condition not met).", task_id);
+                    events
+                        .emit("workflow_event", format!("task '{}' skipped", task_id))
+                        .ok();
+                    self.record(&task_id, TraceStatus::Skipped, 0, None, Vec::new());
+                    self.resolve_dependents(&task_id, &TaskOutcome::Skipped, &mut ready_queue);
+                    continue;
+                }
                 if let Some(mut task) = self.tasks.remove(&task_id) {
-                    let mut rx = self.control_rx.clone();
+                    let rx = task_rx.clone();
                     let ctx_clone = context.clone(); // <--- 克隆 Arc
-                    let app_handle_clone = app_handle.clone();
+                    let events_clone = events.clone();
+                    let policy = self.policies.remove(&task_id).unwrap_or_default();
+
+                    let limits = self.limits.clone();
+                    let trace = self.trace.clone();
+                    let id = task_id.clone();
 
                     println!("[Workflow] Spawning task '{}'.", task_id);
                     let handle = tokio::spawn(async move {
-                        let result = task
-                            .execute(&mut rx, ctx_clone, app_handle_clone) //在这里将任务控制信号接收器，工作流上下文，和应用程序句柄传递给任务执行函数
-                            .await
-                            .map_err(|e| e.to_string());
-                        (task.id(), result)
+                        // 等待独占资源和执行名额，等待期间收到停止信号则不再执行
+                        let resources = task.resources();
+                        let mut stop_rx = rx.clone();
+                        let _permit = tokio::select! {
+                            permit = limits.acquire(&resources) => permit,
+                            Ok(_) = stop_rx.wait_for(|signal| *signal == ControlSignal::Stopped) => {
+                                return TaskRun {
+                                    result: Ok(TaskOutcome::Skipped),
+                                    attempts: 0,
+                                    outputs: Vec::new(),
+                                };
+                            }
+                        };
+                        trace.start(&id);
+                        // 按策略执行（超时/重试），将任务控制信号接收器，工作流上下文，和事件出口传递给任务执行函数
+                        let (result, attempts) = run_with_policy(
+                            task.as_mut(),
+                            &policy,
+                            rx,
+                            ctx_clone.clone(),
+                            events_clone,
+                        )
+                        .await;
+                        trace.finish(&id);
+                        let mut outputs = Vec::new();
+                        if result.is_ok() {
+                            let context_reader = ctx_clone.read().await;
+                            outputs = task
+                                .outputs()
+                                .iter()
+                                .filter_map(|decl| decl.summarize(&context_reader))
+                                .collect();
+                        }
+                        TaskRun {
+                            result: result.map(|()| task.outcome()),
+                            attempts,
+                            outputs,
+                        }
                     });
-                    running_tasks.push(handle);
+                    abort_handles.insert(task_id.clone(), handle.abort_handle());
+                    running_tasks.push(Box::pin(async move { (task_id, handle.await) }));
                 }
             }
 
@@ -212,42 +1180,724 @@ impl WorkflowRunner {
             if running_tasks.is_empty() && ready_queue.is_empty() {
                 println!("[Workflow] All tasks finished or no tasks to run. Exiting.");
                 let data = String::from("workflow finished");
-                app_handle.emit("workflow_event", data).ok();
+                events.emit("workflow_event", data).ok();
 
                 return Ok(context);
             }
 
-            // 等待任何一个正在运行的任务完成
-            if let Some(Ok((completed_id, result))) = running_tasks.next().await {
-                match result {
-                    Ok(_) => {
-                        println!("[Workflow] Task '{}' completed successfully.", completed_id);
-                        // 任务成功，更新其下游任务的入度
-                        if let Some(dependents) = self.reverse_deps.get(&completed_id) {
-                            for dependent_id in dependents {
-                                let degree = self.in_degrees.get_mut(dependent_id).unwrap();
-                                *degree -= 1;
-                                if *degree == 0 {
-                                    ready_queue.push_back(dependent_id.clone());
-                                }
-                            }
+            // 等待任何一个正在运行的任务完成，同时转发外部控制信号
+            let (completed_id, joined) = tokio::select! {
+                next = running_tasks.next() => match next {
+                    Some(completed) => completed,
+                    None => return Err("A running task panicked or was cancelled.".to_string()),
+                },
+                changed = external_rx.changed(), if forwarding => {
+                    match changed {
+                        Ok(()) => {
+                            cancel_tx.send(*external_rx.borrow()).ok();
                         }
+                        Err(_) => forwarding = false,
+                    }
+                    continue;
+                }
+            };
+            abort_handles.remove(&completed_id);
+
+            let error = match joined {
+                Ok(TaskRun { result: Ok(outcome), attempts, outputs }) => {
+                    println!(
+                        "[Workflow] Task '{}' completed successfully ({:?}).",
+                        completed_id, outcome
+                    );
+                    // 收到停止信号的任务会提前正常返回，记录为取消而不是完成
+                    if *task_rx.borrow() == ControlSignal::Stopped {
+                        let message = Some("stopped by control signal".to_string());
+                        self.record(&completed_id, TraceStatus::Cancelled, attempts, message, outputs);
+                    } else {
+                        let (status, message) = match &outcome {
+                            TaskOutcome::Completed => (TraceStatus::Completed, None),
+                            TaskOutcome::Declined(reason) => (TraceStatus::Declined, Some(reason.clone())),
+                            TaskOutcome::Skipped => (TraceStatus::Skipped, None),
+                        };
+                        self.record(&completed_id, status, attempts, message, outputs);
+                    }
+                    // 任务成功，按结果更新其下游任务的入度
+                    self.resolve_dependents(&completed_id, &outcome, &mut ready_queue);
+                    continue;
+                }
+                Ok(TaskRun { result: Err(e), attempts, .. }) => {
+                    self.record(&completed_id, TraceStatus::Failed, attempts, Some(e.clone()), Vec::new());
+                    e
+                }
+                // 任务 panic 或被中止
+                Err(e) => {
+                    let error = e.to_string();
+                    self.record(&completed_id, TraceStatus::Failed, 1, Some(error.clone()), Vec::new());
+                    error
+                }
+            };
+
+            // [修改] 任务失败时，停止其余任务并返回错误以终止整个工作流
+            let (stopped, aborted) = self
+                .cancel_running_tasks(&cancel_tx, running_tasks, abort_handles)
+                .await;
+            let mut error_msg =
+                format!("Task '{}' failed: {}. Stopping workflow.", completed_id, error);
+            if !stopped.is_empty() || !aborted.is_empty() {
+                error_msg.push_str(&format!(
+                    " Cancelled tasks: stopped {:?}, aborted {:?}.",
+                    stopped, aborted
+                ));
+            }
+            eprintln!("[Workflow] {}", error_msg);
+            events
+                .emit(
+                    "workflow_cancelled",
+                    WorkflowCancelledEvent {
+                        failed_task: completed_id,
+                        error,
+                        stopped,
+                        aborted,
+                    },
+                )
+                .ok();
+            return Err(error_msg);
+        }
+    }
+
+    /// 记录任务的最终状态，开始和结束时间取自执行记录
+    fn record(
+        &self,
+        task_id: &str,
+        status: TraceStatus,
+        attempts: u32,
+        message: Option<String>,
+        outputs: Vec<OutputSummary>,
+    ) {
+        let span = self.trace.span(task_id);
+        self.trace.record(TaskTrace {
+            task_id: task_id.to_string(),
+            status,
+            started_at: span.map(|(started_at, _)| started_at),
+            finished_at: span.map(|(_, finished_at)| finished_at),
+            attempts,
+            message,
+            outputs,
+        });
+    }
+
+    /// 上游任务结束后更新下游任务的入度，条件不满足的下游标记为待跳过
+    fn resolve_dependents(
+        &mut self,
+        task_id: &str,
+        outcome: &TaskOutcome,
+        ready_queue: &mut VecDeque<String>,
+    ) {
+        let Some(dependents) = self.reverse_deps.get(task_id) else {
+            return;
+        };
+        for (dependent_id, condition) in dependents {
+            if !condition.is_met(outcome) {
+                self.blocked.insert(dependent_id.clone());
+            }
+            let degree = self.in_degrees.get_mut(dependent_id).unwrap();
+            *degree -= 1;
+            if *degree == 0 {
+                ready_queue.push_back(dependent_id.clone());
+            }
+        }
+    }
+
+    /// 向仍在运行的任务发送停止信号，在宽限期内等待它们自行清理退出（释放麦克风、关闭检测器），
+    /// 超时仍未退出的任务被中止。返回 (自行退出的任务, 被中止的任务)。
+    async fn cancel_running_tasks(
+        &self,
+        cancel_tx: &watch::Sender<ControlSignal>,
+        mut running_tasks: FuturesUnordered<RunningTask>,
+        mut abort_handles: HashMap<String, AbortHandle>,
+    ) -> (Vec<String>, Vec<String>) {
+        let mut stopped = Vec::new();
+        if running_tasks.is_empty() {
+            return (stopped, Vec::new());
+        }
+        cancel_tx.send(ControlSignal::Stopped).ok();
+
+        let grace = tokio::time::sleep(self.cancel_grace_period);
+        tokio::pin!(grace);
+        loop {
+            tokio::select! {
+                next = running_tasks.next() => match next {
+                    Some((task_id, joined)) => {
+                        abort_handles.remove(&task_id);
+                        let (attempts, message) = match joined {
+                            Ok(TaskRun { result: Err(e), attempts, .. }) => (attempts, e),
+                            Ok(TaskRun { attempts, .. }) => (attempts, "stopped after another task failed".to_string()),
+                            Err(e) => (1, e.to_string()),
+                        };
+                        self.record(&task_id, TraceStatus::Cancelled, attempts, Some(message), Vec::new());
+                        stopped.push(task_id);
+                    }
+                    None => break,
+                },
+                _ = &mut grace => break,
+            }
+        }
+
+        // 中止后任务在下一个 await 点被丢弃，其 Drop（如 AsrSession 停止采集线程）随之执行
+        let mut aborted: Vec<String> = abort_handles
+            .into_iter()
+            .map(|(task_id, handle)| {
+                handle.abort();
+                task_id
+            })
+            .collect();
+        aborted.sort();
+        for task_id in &aborted {
+            let message = Some("aborted after cancel grace period".to_string());
+            self.record(task_id, TraceStatus::Cancelled, 1, message, Vec::new());
+        }
+        (stopped, aborted)
+    }
+}
+
+/// 任务在工作流中一次执行（含重试）的结果
+struct TaskRun {
+    result: Result<TaskOutcome, String>,
+    attempts: u32,
+    outputs: Vec<OutputSummary>,
+}
+
+type RunningTask = BoxFuture<'static, (String, Result<TaskRun, JoinError>)>;
+
+fn emit_task_event(events: &Events, event: WorkflowTaskEvent) {
+    log::warn!(
+        "[Workflow] Task '{}' {:?} (attempt {}/{}): {}",
+        event.task_id,
+        event.kind,
+        event.attempt,
+        event.max_retries + 1,
+        event.error
+    );
+    events.emit("workflow_task_event", event).ok();
+}
+
+/// 按策略执行任务：超时取消、失败后按退避时间重试，停止信号会终止重试。
+/// 返回执行结果和执行次数。
+async fn run_with_policy(
+    task: &mut dyn Task,
+    policy: &TaskPolicy,
+    control_rx: watch::Receiver<ControlSignal>,
+    context: WorkflowContext,
+    events: Events,
+) -> (Result<(), String>, u32) {
+    let task_id = task.id();
+    let mut rx = control_rx.clone();
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let failure = match execute_attempt(
+            task,
+            &mut rx,
+            control_rx.clone(),
+            context.clone(),
+            events.clone(),
+            policy.timeout,
+        )
+        .await
+        {
+            Ok(()) => return (Ok(()), attempt),
+            Err(failure) => failure,
+        };
+
+        let event = |kind, retry_delay: Option<Duration>| WorkflowTaskEvent {
+            task_id: task_id.clone(),
+            kind,
+            attempt,
+            max_retries: policy.max_retries,
+            error: failure.to_string(),
+            timeout_ms: policy.timeout.map(|t| t.as_millis() as u64),
+            retry_delay_ms: retry_delay.map(|d| d.as_millis() as u64),
+        };
+        if matches!(failure, TaskFailure::Timeout(_)) {
+            emit_task_event(&events, event(TaskEventKind::Timeout, None));
+        }
+
+        let stopped = *control_rx.borrow() == ControlSignal::Stopped;
+        if stopped || attempt > policy.max_retries || !policy.should_retry(&failure) {
+            emit_task_event(&events, event(TaskEventKind::Failed, None));
+            return (Err(failure.to_string()), attempt);
+        }
+
+        task.reset();
+        let delay = policy.backoff_delay(attempt);
+        emit_task_event(&events, event(TaskEventKind::Retry, Some(delay)));
+
+        // 退避等待期间收到停止信号则不再重试
+        let mut stop_rx = control_rx.clone();
+        let sleep = tokio::time::sleep(delay);
+        tokio::pin!(sleep);
+        loop {
+            tokio::select! {
+                _ = &mut sleep => break,
+                changed = stop_rx.changed() => {
+                    if changed.is_err() || *stop_rx.borrow() == ControlSignal::Stopped {
+                        return (Err(failure.to_string()), attempt);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 执行一次任务，超过 `timeout` 时取消。暂停期间顺延截止时间，避免人工暂停被判为超时。
+async fn execute_attempt(
+    task: &mut dyn Task,
+    rx: &mut watch::Receiver<ControlSignal>,
+    mut watch_rx: watch::Receiver<ControlSignal>,
+    context: WorkflowContext,
+    events: Events,
+    timeout: Option<Duration>,
+) -> Result<(), TaskFailure> {
+    let execution = task.execute(rx, context, events);
+    let Some(limit) = timeout else {
+        return execution.await.map_err(|e| TaskFailure::Error(e.to_string()));
+    };
+    tokio::pin!(execution);
+
+    let mut deadline = Instant::now() + limit;
+    let mut paused_at = (*watch_rx.borrow() == ControlSignal::Paused).then(Instant::now);
+    let mut watching = true;
+    loop {
+        tokio::select! {
+            result = &mut execution => {
+                return result.map_err(|e| TaskFailure::Error(e.to_string()));
+            }
+            _ = tokio::time::sleep_until(deadline), if paused_at.is_none() => {
+                return Err(TaskFailure::Timeout(limit));
+            }
+            changed = watch_rx.changed(), if watching => {
+                if changed.is_err() {
+                    // 控制端已关闭，之后只按截止时间判断
+                    watching = false;
+                    if let Some(paused) = paused_at.take() {
+                        deadline += paused.elapsed();
                     }
-                    Err(e) => {
-                        let error_msg =
-                            format!("Task '{}' failed: {}. Stopping workflow.", completed_id, e);
-                        eprintln!("[Workflow] {}", error_msg);
-                        // [修改] 任务失败时，返回错误以终止整个工作流
-                        return Err(error_msg);
+                    continue;
+                }
+                let signal = *watch_rx.borrow();
+                match (signal, paused_at) {
+                    (ControlSignal::Paused, None) => paused_at = Some(Instant::now()),
+                    (ControlSignal::Paused, Some(_)) => {}
+                    (_, Some(paused)) => {
+                        deadline += paused.elapsed();
+                        paused_at = None;
                     }
+                    (_, None) => {}
                 }
-            } else {
-                // 如果运行的 task handle 出现问题 (e.g., panic)，或者 running_tasks 为空
-                if running_tasks.is_empty() {
-                    // 一个正在运行的task panic了或者被取消了
-                    return Err("A running task panicked or was cancelled.".to_string());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::event_sink::{ChannelEventSink, EmittedEvent};
+    use tokio::sync::mpsc;
+
+    /// 测试用任务：按设定的结论结束，或前 `failures` 次执行返回错误
+    struct FakeTask {
+        id: String,
+        outcome: TaskOutcome,
+        failures: u32,
+        runs: u32,
+        wait_for_stop: bool,
+        resources: Vec<&'static str>,
+        inputs: Vec<KeyDecl>,
+        outputs: Vec<KeyDecl>,
+    }
+
+    impl FakeTask {
+        fn new(id: &str) -> Self {
+            Self {
+                id: id.to_string(),
+                outcome: TaskOutcome::Completed,
+                failures: 0,
+                runs: 0,
+                wait_for_stop: false,
+                resources: Vec::new(),
+                inputs: Vec::new(),
+                outputs: Vec::new(),
+            }
+        }
+
+        fn declined(id: &str) -> Self {
+            Self {
+                outcome: TaskOutcome::Declined("no".to_string()),
+                ..Self::new(id)
+            }
+        }
+
+        fn failing(id: &str, failures: u32) -> Self {
+            Self {
+                failures,
+                ..Self::new(id)
+            }
+        }
+
+        /// 一直运行到收到停止信号
+        fn waiting(id: &str) -> Self {
+            Self {
+                wait_for_stop: true,
+                ..Self::new(id)
+            }
+        }
+
+        /// 占用资源执行一小段时间
+        fn holding(id: &str, resources: Vec<&'static str>) -> Self {
+            Self {
+                resources,
+                ..Self::new(id)
+            }
+        }
+
+        /// 声明读取和写入的上下文键
+        fn with_keys(id: &str, inputs: Vec<KeyDecl>, outputs: Vec<KeyDecl>) -> Self {
+            Self {
+                inputs,
+                outputs,
+                ..Self::new(id)
+            }
+        }
+    }
+
+    #[async_trait]
+    impl Task for FakeTask {
+        fn id(&self) -> String {
+            self.id.clone()
+        }
+
+        async fn execute(
+            &mut self,
+            control_rx: &mut watch::Receiver<ControlSignal>,
+            _context: WorkflowContext,
+            events: Events,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            self.runs += 1;
+            events.emit("fake_task_run", &self.id).ok();
+            if self.wait_for_stop {
+                while *control_rx.borrow() != ControlSignal::Stopped {
+                    control_rx.changed().await?;
                 }
+                return Ok(());
+            }
+            if self.runs <= self.failures {
+                // 留出时间让同时运行的任务启动
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                return Err(format!("run {} failed", self.runs).into());
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(())
+        }
+
+        fn outcome(&self) -> TaskOutcome {
+            self.outcome.clone()
+        }
+
+        fn resources(&self) -> Vec<&'static str> {
+            self.resources.clone()
+        }
+
+        fn inputs(&self) -> Vec<KeyDecl> {
+            self.inputs.clone()
+        }
+
+        fn outputs(&self) -> Vec<KeyDecl> {
+            self.outputs.clone()
+        }
+    }
+
+    fn drain(rx: &mut mpsc::UnboundedReceiver<EmittedEvent>, event: &str) -> Vec<serde_json::Value> {
+        let mut payloads = Vec::new();
+        while let Ok(emitted) = rx.try_recv() {
+            if emitted.event == event {
+                payloads.push(emitted.payload);
             }
         }
+        payloads
+    }
+
+    fn status_of(trace: &WorkflowTrace, task_id: &str) -> TraceStatus {
+        trace
+            .tasks()
+            .iter()
+            .find(|t| t.task_id == task_id)
+            .map(|t| t.status)
+            .unwrap_or_else(|| panic!("任务 '{}' 没有执行记录", task_id))
+    }
+
+    /// 两个任务的执行时间是否重叠
+    fn overlapped(trace: &WorkflowTrace, a: &str, b: &str) -> bool {
+        let tasks = trace.tasks();
+        let span = |id: &str| {
+            let task = tasks.iter().find(|t| t.task_id == id).unwrap();
+            (task.started_at.unwrap(), task.finished_at.unwrap())
+        };
+        let (a_start, a_end) = span(a);
+        let (b_start, b_end) = span(b);
+        a_start < b_end && b_start < a_end
+    }
+
+    #[tokio::test]
+    async fn test_declined_task_skips_conditional_dependents() {
+        let (events, mut rx) = ChannelEventSink::channel();
+        let (_control_tx, control_rx) = watch::channel(ControlSignal::Running);
+        let (mut workflow, _) = Workflow::new();
+        workflow.add_task(FakeTask::declined("check"));
+        workflow.add_task(FakeTask::new("audio"));
+        workflow.add_task(FakeTask::new("asr"));
+        workflow.add_task(FakeTask::new("finish"));
+        workflow.add_conditional_dependency("audio", "check", Condition::completed());
+        workflow.add_dependency("asr", "audio");
+        workflow.add_conditional_dependency("finish", "asr", Condition::Always);
+        workflow.add_dependency("finish", "check");
+        let trace = workflow.trace();
+
+        workflow.run_and_wait(events, control_rx).await.unwrap();
+
+        let ran = drain(&mut rx, "fake_task_run");
+        assert_eq!(ran, vec![serde_json::json!("check"), serde_json::json!("finish")]);
+        assert_eq!(status_of(&trace, "check"), TraceStatus::Declined);
+        assert_eq!(status_of(&trace, "audio"), TraceStatus::Skipped);
+        assert_eq!(status_of(&trace, "asr"), TraceStatus::Skipped);
+        assert_eq!(status_of(&trace, "finish"), TraceStatus::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let (events, mut rx) = ChannelEventSink::channel();
+        let (_control_tx, control_rx) = watch::channel(ControlSignal::Running);
+        let (mut workflow, _) = Workflow::new();
+        let policy = TaskPolicy::default().with_retries(2, Duration::from_millis(1));
+        workflow.add_task_with_policy(FakeTask::failing("flaky", 2), policy);
+        let trace = workflow.trace();
+
+        workflow.run_and_wait(events, control_rx).await.unwrap();
+
+        let retries = drain(&mut rx, "workflow_task_event");
+        assert_eq!(retries.len(), 2);
+        assert!(retries.iter().all(|event| event["kind"] == "retry"));
+        let flaky = &trace.tasks()[0];
+        assert_eq!(flaky.status, TraceStatus::Completed);
+        assert_eq!(flaky.attempts, 3);
+    }
+
+    /// 测试用任务：第一次执行建立的会话会一直挂起，重试时必须重新建立
+    struct SessionTask {
+        session: Option<u32>,
+        attempts: u32,
+    }
+
+    #[async_trait]
+    impl Task for SessionTask {
+        fn id(&self) -> String {
+            "session".to_string()
+        }
+
+        async fn execute(
+            &mut self,
+            _control_rx: &mut watch::Receiver<ControlSignal>,
+            _context: WorkflowContext,
+            _events: Events,
+        ) -> Result<(), Box<dyn Error + Send + Sync>> {
+            self.attempts += 1;
+            let session = *self.session.get_or_insert(self.attempts);
+            if session == 1 {
+                std::future::pending::<()>().await;
+            }
+            Ok(())
+        }
+
+        fn reset(&mut self) {
+            self.session = None;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_timeout_resets_task() {
+        let (events, _rx) = ChannelEventSink::channel();
+        let (_control_tx, control_rx) = watch::channel(ControlSignal::Running);
+        let (mut workflow, _) = Workflow::new();
+        let policy = TaskPolicy::default()
+            .with_timeout(Duration::from_millis(50))
+            .with_retries(1, Duration::from_millis(1));
+        workflow.add_task_with_policy(SessionTask { session: None, attempts: 0 }, policy);
+        let trace = workflow.trace();
+
+        workflow.run_and_wait(events, control_rx).await.unwrap();
+
+        let task = &trace.tasks()[0];
+        assert_eq!(task.status, TraceStatus::Completed);
+        assert_eq!(task.attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_failure_cancels_running_tasks() {
+        let (events, mut rx) = ChannelEventSink::channel();
+        let (control_tx, control_rx) = watch::channel(ControlSignal::Running);
+        let (mut workflow, _) = Workflow::new();
+        workflow.add_task(FakeTask::failing("broken", 1));
+        workflow.add_task(FakeTask::waiting("listener"));
+        workflow.add_task(FakeTask::new("after"));
+        workflow.add_dependency("after", "broken");
+        let trace = workflow.trace();
+
+        let error = workflow.run_and_wait(events, control_rx).await.unwrap_err();
+
+        assert!(error.contains("Task 'broken' failed: run 1 failed"));
+        let cancelled = drain(&mut rx, "workflow_cancelled");
+        assert_eq!(cancelled.len(), 1);
+        assert_eq!(cancelled[0]["failed_task"], "broken");
+        assert_eq!(cancelled[0]["stopped"], serde_json::json!(["listener"]));
+        assert_eq!(status_of(&trace, "broken"), TraceStatus::Failed);
+        assert_eq!(status_of(&trace, "listener"), TraceStatus::Cancelled);
+        assert!(trace.tasks().iter().all(|t| t.task_id != "after"));
+        // 停止信号只发给本工作流的任务，不影响外部控制通道
+        assert_eq!(*control_tx.borrow(), ControlSignal::Running);
+    }
+
+    #[tokio::test]
+    async fn test_limits_serialize_tasks() {
+        // 占用同一资源的任务依次执行，其余任务不受影响
+        let (events, _rx) = ChannelEventSink::channel();
+        let (_control_tx, control_rx) = watch::channel(ControlSignal::Running);
+        let (mut workflow, _) = Workflow::new();
+        workflow.add_task(FakeTask::holding("wake_audio", vec![resources::SPEAKER]));
+        workflow.add_task(FakeTask::holding("asr", vec![resources::MICROPHONE, resources::SPEAKER]));
+        workflow.add_task(FakeTask::new("analysis"));
+        let trace = workflow.trace();
+        workflow.run_and_wait(events.clone(), control_rx.clone()).await.unwrap();
+        assert!(!overlapped(&trace, "wake_audio", "asr"));
+        assert!(overlapped(&trace, "analysis", "wake_audio") || overlapped(&trace, "analysis", "asr"));
+
+        // 共享的名额限制对多个工作流整体生效
+        let limits = ExecutionLimits::new(1);
+        let (mut first, _) = Workflow::new();
+        first.add_task(FakeTask::new("first"));
+        first.set_execution_limits(limits.clone());
+        let (mut second, _) = Workflow::new();
+        second.add_task(FakeTask::new("second"));
+        second.set_execution_limits(limits);
+        let (first_trace, second_trace) = (first.trace(), second.trace());
+        let (a, b) = tokio::join!(
+            first.run_and_wait(events.clone(), control_rx.clone()),
+            second.run_and_wait(events, control_rx)
+        );
+        a.unwrap();
+        b.unwrap();
+        let first_task = &first_trace.tasks()[0];
+        let second_task = &second_trace.tasks()[0];
+        assert!(
+            first_task.finished_at.unwrap() <= second_task.started_at.unwrap()
+                || second_task.finished_at.unwrap() <= first_task.started_at.unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_tasks() {
+        let (events, _rx) = ChannelEventSink::channel();
+        let (control_tx, control_rx) = watch::channel(ControlSignal::Running);
+        let (mut workflow, _) = Workflow::new();
+        workflow.add_task(FakeTask::new("capture"));
+        workflow.add_task(FakeTask::waiting("analysis"));
+        workflow.add_dependency("analysis", "capture");
+        let trace = workflow.trace();
+        let execution = tokio::spawn(workflow.run_and_wait(events, control_rx));
+
+        // 采集结束后即可返回，不等待后续任务
+        trace.wait_for_tasks(&["capture".to_string()]).await;
+        assert_eq!(status_of(&trace, "capture"), TraceStatus::Completed);
+        assert!(!execution.is_finished());
+
+        // 工作流结束后，未执行的任务也不会一直等待
+        control_tx.send(ControlSignal::Stopped).unwrap();
+        execution.await.unwrap().unwrap();
+        trace.wait_for_tasks(&["missing".to_string()]).await;
+    }
+
+    #[tokio::test]
+    async fn test_validate_reports_cycle_and_blocked_tasks() {
+        let (mut workflow, _) = Workflow::new();
+        for id in ["a", "b", "c", "d", "e"] {
+            workflow.add_task(FakeTask::new(id));
+        }
+        workflow.add_dependency("b", "a");
+        workflow.add_dependency("c", "b");
+        workflow.add_dependency("a", "c");
+        workflow.add_dependency("d", "c");
+        workflow.add_dependency("e", "ghost");
+
+        let error = workflow.validate().unwrap_err();
+
+        assert_eq!(
+            error,
+            "工作流校验失败: 任务 'e' 依赖的任务 'ghost' 不存在; \
+             存在循环依赖: b -> c -> a -> b; \
+             任务 'd' 永远无法开始（上游存在循环依赖或缺失的任务）; \
+             任务 'e' 永远无法开始（上游存在循环依赖或缺失的任务）"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_context_key_types() {
+        let text = ContextKey::<String>::new("text");
+        let (mut workflow, _) = Workflow::new();
+        workflow.add_task(FakeTask::with_keys("asr", vec![], vec![text.decl()]));
+        workflow.add_task(FakeTask::with_keys("analysis", vec![text.decl()], vec![]));
+        workflow.add_dependency("analysis", "asr");
+        assert_eq!(workflow.validate(), Ok(()));
+
+        let count = ContextKey::<i64>::new("text");
+        workflow.add_task(FakeTask::with_keys("finish", vec![count.decl()], vec![]));
+        workflow.add_dependency("finish", "analysis");
+        // 类型名称的格式不稳定，只检查键名和任务
+        let error = workflow.validate().unwrap_err();
+        assert!(error.contains("上下文键 'text' 类型不匹配"), "{}", error);
+        assert!(error.contains("任务 'asr' 写入"), "{}", error);
+        assert!(error.contains("任务 'finish' 读取"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_export_graph() {
+        let (mut workflow, _) = Workflow::new();
+        workflow.add_task(FakeTask::new("capture"));
+        workflow.add_task(FakeTask::new("analysis"));
+        workflow.add_task(FakeTask::new("finish"));
+        workflow.add_dependency("analysis", "capture");
+        workflow.add_conditional_dependency("finish", "analysis", Condition::Always);
+
+        assert_eq!(
+            workflow.to_dot(),
+            r#"digraph workflow {
+    rankdir=LR;
+    node [shape=box];
+    "analysis";
+    "capture";
+    "finish";
+    "capture" -> "analysis";
+    "analysis" -> "finish" [label="always", style=dashed];
+}
+"#
+        );
+        assert_eq!(
+            workflow.to_mermaid(),
+            r#"flowchart LR
+    n0["analysis"]
+    n1["capture"]
+    n2["finish"]
+    n1 --> n0
+    n0 -.->|always| n2
+"#
+        );
     }
 }
@@ -1,8 +1,9 @@
 use crate::db::database::DatabaseService;
-use crate::models::VideoFrame;
+use crate::models::{TaskProgress, VideoFrame};
 use crate::services::audio_controller::AudioController;
 use crate::services::ocr_session::OcrSessionManager;
 use crate::services::workflow::ControlHandle;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tauri::ipc::Channel;
 use tokio::sync::Mutex;
@@ -43,12 +44,18 @@ pub struct AppState {
     pub is_testing: Arc<tokio::sync::RwLock<bool>>,
     pub audio_controller: AudioController,
     pub workflow_handle: Arc<Mutex<Option<ControlHandle>>>,
+    /// 测试队列有变化（入队、恢复）时唤醒队列执行循环
+    pub job_queue_notify: Arc<tokio::sync::Notify>,
+    /// 正在运行的样本测试最近一次上报的进度
+    pub task_progress: Arc<tokio::sync::RwLock<Option<TaskProgress>>>,
     pub http_client: Client,
     pub ocr_engine: Arc<ParkingLotMutex<Option<Tesseract>>>,
     pub ocr_channel: Arc<Mutex<Option<Channel>>>,
     pub ocr_pool: Arc<OcrEnginePool>,
     pub ocr_session_manager: Arc<parking_lot::Mutex<OcrSessionManager>>,
     pub ocr_frame_sender: Arc<tokio::sync::Mutex<Option<tokio::sync::mpsc::Sender<VideoFrame>>>>,
+    /// OCR 引擎使用的 tessdata 目录（随应用打包的资源），启动时解析
+    pub tessdata_dir: Option<PathBuf>,
 }
 
 impl AppState {
@@ -63,12 +70,15 @@ impl AppState {
             is_testing: Arc::new(tokio::sync::RwLock::new(false)),
             audio_controller,
             workflow_handle: Arc::new(Mutex::new(None)),
+            job_queue_notify: Arc::new(tokio::sync::Notify::new()),
+            task_progress: Arc::new(tokio::sync::RwLock::new(None)),
             http_client: Client::new(),
             ocr_engine: Arc::new(ParkingLotMutex::new(None)),
             ocr_channel: Arc::new(Mutex::new(None)),
             ocr_pool: Arc::new(OcrEnginePool::new(6)), // 设置ocr线程池中的ocr引擎数
             ocr_session_manager: Arc::new(parking_lot::Mutex::new(OcrSessionManager::new())),
             ocr_frame_sender: Arc::new(tokio::sync::Mutex::new(None)),
+            tessdata_dir: None,
         })
     }
 }
@@ -91,12 +91,17 @@ export interface MachineResponseData {
   connected: boolean
 }
 
-// 任务进度类型
+// 任务进度类型（"progress_update" 事件 / get_task_progress）
 export interface TaskProgress {
-  value: number
+  task_id?: number
+  value: number // 0-100
   current_sample: number
-  current_stage?: string
+  current_stage?: string | null // 当前样本正在执行的环节，如 "audio_task"
   total: number
+  passed?: number // 已结束运行中评估通过的次数
+  failed?: number
+  elapsed_ms?: number
+  eta_ms?: number | null // 预计剩余时间，尚无完成的样本时为空
 }
 
 // 时间参数类型 - 车机语音测试时间数据
@@ -40,6 +40,137 @@ export interface AnalysisResult {
   result_status: string | null;
   recognition_time: number | null; // Option<f32>
   response_time: number | null; // Option<f32>
+  wake_word_id?: number | null; // Option<u32>
+  run_index?: number; // u32，样本重复执行的第几次（从0开始）
+}
+
+// Corresponds to Rust enum `WakeWordStrategy`
+export type WakeWordStrategy =
+  | { type: 'fixed'; wake_word_id?: number | null }
+  | { type: 'round_robin' }
+  | { type: 'random'; seed: number }
+  | { type: 'cross_product' };
+
+// Corresponds to Rust enum `StepDependency`: 只写步骤ID时使用默认条件
+export type StepDependency =
+  | string
+  | { step: string; condition: 'executed' | 'always' | 'completed' };
+
+// Corresponds to Rust struct `PipelineStep`（`type` 之外的参数随步骤类型不同）
+export type PipelineStep = {
+  id: string;
+  depends_on?: StepDependency[];
+  timeout_secs?: number; // u64，覆盖步骤类型默认超时
+  retries?: number; // u32，覆盖步骤类型默认重试次数
+} & (
+  | { type: 'wake_word_audio' }
+  | { type: 'command_audio' }
+  | { type: 'visual_detection' }
+  | { type: 'asr'; reference: 'wake_word' | 'sample' }
+  | { type: 'checkpoint'; asr: string; visual?: string; expected_responses?: string[] }
+  | { type: 'ocr' }
+  | { type: 'analysis'; asr: string }
+  | { type: 'finish'; asr: string; analysis: string; command_audio: string; visual?: string; ocr?: string }
+);
+
+// Corresponds to Rust struct `PipelineDefinition`
+export interface PipelineDefinition {
+  name: string;
+  description: string;
+  steps: PipelineStep[];
+  max_background_samples?: number; // 大于0时，前一样本的分析在后台进行，同时开始下一样本的采集
+}
+
+// Corresponds to Rust struct `SampleRunState`（中断后 resume_task 从第一个未完成的运行继续）
+export interface SampleRunState {
+  task_id: number;
+  sample_id: number;
+  wake_word_id: number;
+  run_index: number;
+  run_order: number; // 运行在计划中的序号
+  status: 'pending' | 'running' | 'done' | 'failed';
+  error: string | null;
+  updated_at: string;
+}
+
+// Corresponds to Rust enum `JobStatus`（paused 为暂不执行的排队任务）
+export type JobStatus = 'queued' | 'paused' | 'running' | 'done' | 'failed' | 'cancelled';
+
+// Corresponds to Rust struct `JobVisualSettings`，未提供的项使用默认值
+export interface JobVisualSettings {
+  template_data?: [string, string][];
+  frame_rate?: number | null;
+  threshold?: number | null;
+  max_detection_time_secs?: number | null;
+}
+
+// Corresponds to Rust enum `JobSpec`
+export type JobSpec =
+  | { kind: 'sample_test'; wake_word_strategy?: WakeWordStrategy | null; visual?: JobVisualSettings }
+  | { kind: 'wake_test'; visual?: JobVisualSettings; expected_responses?: string[] };
+
+// Corresponds to Rust struct `QueuedJob`（按 priority 从高到低、position 从小到大执行）
+export interface QueuedJob {
+  id: number;
+  task_id: number;
+  spec: JobSpec;
+  priority: number;
+  position: number;
+  status: JobStatus;
+  error: string | null;
+  created_at: string;
+  started_at: string | null;
+  finished_at: string | null;
+}
+
+// Corresponds to Rust enum `ScheduleRule`（按本地时间计算）
+export type ScheduleRule =
+  | { type: 'cron'; expression: string } // 5 段：分 时 日 月 周
+  | { type: 'daily'; time: string }; // HH:MM
+
+// Corresponds to Rust enum `MissedRunPolicy`（程序关闭期间错过的执行时间补执行一次或跳过）
+export type MissedRunPolicy = 'run_once' | 'skip';
+
+// Corresponds to Rust struct `TaskSchedule`（到点复制任务并加入测试队列）
+export interface TaskSchedule {
+  id: number;
+  task_id: number;
+  rule: ScheduleRule;
+  spec: JobSpec;
+  priority: number;
+  missed_run_policy: MissedRunPolicy;
+  enabled: boolean;
+  last_run_at: string | null; // UTC
+  next_run_at: string | null; // UTC，规则不会再触发时为 null
+  last_task_id: number | null; // 最近一次执行创建的任务
+  created_at: string;
+}
+
+// Corresponds to Rust struct `WorkflowTaskRecord`（子工作流中单个任务节点的执行记录）
+export interface WorkflowTaskRecord {
+  node_id: string;
+  status: 'completed' | 'declined' | 'skipped' | 'failed' | 'cancelled';
+  started_at: string | null; // 被跳过的任务为 null
+  finished_at: string | null;
+  duration_ms: number | null;
+  attempts: number; // 执行次数（含重试）
+  message: string | null; // 失败原因、否决原因或取消说明
+  outputs: { key: string; type_name: string; value: string }[];
+}
+
+// Corresponds to Rust struct `WorkflowRunRecord`（get_workflow_traces / export_workflow_traces）
+export interface WorkflowRunRecord {
+  id: number;
+  task_id: number;
+  sample_id: number; // 唤醒检测的运行为 0
+  wake_word_id: number;
+  run_index: number;
+  pipeline: string;
+  status: 'completed' | 'failed' | 'cancelled';
+  error: string | null;
+  started_at: string;
+  finished_at: string;
+  tasks: WorkflowTaskRecord[];
 }
 
 // Corresponds to Rust struct `TaskProgress`
@@ -49,6 +180,25 @@ export interface TaskProgress {
   total: number; // u32 in Rust
 }
 
+// Corresponds to Rust struct `WorkflowTaskEvent` ("workflow_task_event")
+export interface WorkflowTaskEvent {
+  task_id: string;
+  kind: 'timeout' | 'retry' | 'failed';
+  attempt: number; // u32，从1开始
+  max_retries: number; // u32
+  error: string;
+  timeout_ms: number | null;
+  retry_delay_ms: number | null;
+}
+
+// Corresponds to Rust struct `WorkflowCancelledEvent` ("workflow_cancelled")
+export interface WorkflowCancelledEvent {
+  failed_task: string;
+  error: string;
+  stopped: string[]; // 宽限期内自行退出的任务
+  aborted: string[]; // 被强制中止的任务
+}
+
 // Corresponds to Rust struct `PlayAudioEvent`
 export interface PlayAudioEvent {
   wake_word_id: number; // u32 in Rust
@@ -90,7 +240,7 @@ export interface Task {
   wake_word_ids: number[]; // Vec<u32> - 修改为支持多个唤醒词
   machine_response?: Record<string, MachineResponseData> | null; // Option<HashMap<u32, MachineResponseData>> (JS object keys are strings)
   test_result?: Record<string, AnalysisResult> | null;    // Option<HashMap<u32, AnalysisResult>>
-  task_status: string;
+  task_status: string; // pending / in_progress / completed / failed / interrupted（程序退出时仍在运行）
   task_progress?: number | null; // Option<f32>
   created_at: string;
   audio_type?: string | null;